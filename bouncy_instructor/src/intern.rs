@@ -1,17 +1,30 @@
 //! Wrapper module for all internal code of the instructor, which can be
 //! modified without affecting the WASM module interface.
 
+pub(crate) mod adaptive;
+pub(crate) mod beat_tracker;
 pub(crate) mod body_shift;
+pub(crate) mod bounce;
 pub(crate) mod content_collection;
+pub(crate) mod content_pack;
+pub(crate) mod correction;
 pub(crate) mod dance;
 pub(crate) mod dance_detector;
+pub(crate) mod framing;
 pub(crate) mod geom;
+pub(crate) mod jump;
 pub(crate) mod keypoints_iter;
+pub(crate) mod language;
 pub(crate) mod lfsr;
+pub(crate) mod loop_practice;
 pub(crate) mod pose;
 pub(crate) mod pose_score;
+pub(crate) mod rep_counter;
+pub(crate) mod session_log;
 pub(crate) mod skeleton_3d;
 pub(crate) mod step;
 pub(crate) mod step_pace;
 pub(crate) mod teacher;
+pub(crate) mod tempo_map;
 pub(crate) mod tracker_dance_collection;
+pub(crate) mod travel;
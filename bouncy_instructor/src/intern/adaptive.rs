@@ -0,0 +1,232 @@
+//! Adapting the difficulty of a session to the dancer.
+//!
+//! Whenever a tracked section ends, its hit rate decides whether the next
+//! section gets easier or harder. Easier means a slower pace and more tolerant
+//! pose error thresholds, harder means the opposite.
+//!
+//! The pace of the section currently playing is never changed, to keep the
+//! teacher in sync with the music. Thresholds don't affect timing, they apply
+//! right away, already to the rest of the current section.
+
+use super::dance_detector::DanceDetector;
+use crate::tracker::Adaptation;
+
+/// Hit rate below which the next section gets easier.
+const LOW_HIT_RATE: f32 = 0.5;
+/// Hit rate above which the next section gets harder.
+const HIGH_HIT_RATE: f32 = 0.9;
+/// Factor by which thresholds change per adaptation.
+const THRESHOLD_STEP: f32 = 1.2;
+const MIN_THRESHOLD_FACTOR: f32 = 0.8;
+const MAX_THRESHOLD_FACTOR: f32 = 1.5;
+
+impl DanceDetector {
+    /// Score a section once the teacher moved past it. Adapts the pace of the
+    /// section after the current one and the thresholds from now on.
+    pub(crate) fn adapt_difficulty(&mut self, scored_section: usize, section: usize) {
+        let Some(expected) = self.teacher.tracked_poses_in_section(scored_section) else {
+            return;
+        };
+        if expected == 0 {
+            return;
+        }
+        let matched = self.session.matched_poses(scored_section);
+        let hit_rate = (matched as f32 / expected as f32).min(1.0);
+        let easier = if hit_rate < LOW_HIT_RATE {
+            true
+        } else if hit_rate > HIGH_HIT_RATE {
+            false
+        } else {
+            return;
+        };
+
+        let factor = if easier {
+            self.threshold_factor * THRESHOLD_STEP
+        } else {
+            self.threshold_factor / THRESHOLD_STEP
+        };
+        self.threshold_factor = factor.clamp(MIN_THRESHOLD_FACTOR, MAX_THRESHOLD_FACTOR);
+        let next_section = section + 1;
+        let paced_section = self
+            .teacher
+            .adapt_section_pace(next_section, easier)
+            .map(|_pace| next_section);
+
+        self.session.adaptations.push(Adaptation {
+            scored_section,
+            hit_rate,
+            easier,
+            paced_section,
+            threshold_factor: self.threshold_factor,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intern::dance_detector::DetectionState;
+    use crate::intern::pose_score::ErrorDetails;
+    use crate::intern::session_log::LoggedPose;
+    use crate::intern::skeleton_3d::Skeleton3d;
+    use crate::intern::step::StepSource;
+    use crate::intern::step_pace::StepPace;
+    use crate::intern::tempo_map::TempoMap;
+    use crate::intern::tracker_dance_collection::TrackerDanceCollection;
+    use crate::keypoints::Cartesian3d;
+    use crate::pose_file::PoseFile;
+    use crate::step_file::StepFile;
+    use crate::tracker::{DanceCursor, PoseApproximation};
+    use crate::{Keypoints, StepInfo};
+
+    /// A step of four kicks with the left shin at 30°.
+    fn collection() -> TrackerDanceCollection {
+        let poses = PoseFile::from_str(
+            r#"(
+              version: 0,
+              poses: [
+                (
+                  name: "kick",
+                  direction: Front,
+                  limbs: [(limb: LeftShin, angle: 30, tolerance: 5, weight: 1.0)],
+                ),
+              ],
+            )"#,
+        )
+        .unwrap();
+        let kick = r#"(pose: "kick", orientation: ToCamera)"#;
+        let steps = StepFile::from_str(&format!(
+            r#"(version: 0, steps: [(id: "kicks", name: "Kicks", keyframes: [{kick}, {kick}, {kick}, {kick}])])"#
+        ))
+        .unwrap();
+        let mut db = TrackerDanceCollection::default();
+        db.add_poses(poses.poses.iter()).unwrap();
+        db.add_steps(&steps.steps, StepSource::new("test".to_owned()))
+            .unwrap();
+        db
+    }
+
+    /// Three sections of the step, repeated twice.
+    fn detector(db: &TrackerDanceCollection, pace: StepPace) -> DanceDetector {
+        let info = StepInfo::from_step(db.step("kicks").unwrap().clone(), db);
+        let mut detector = DanceDetector::default();
+        detector.adaptive = true;
+        for _ in 0..3 {
+            detector.teacher.add_step(info.clone(), 2, pace);
+        }
+        detector
+    }
+
+    fn hit_all(detector: &mut DanceDetector, section_index: usize) {
+        let poses = detector.teacher.tracked_poses_in_section(section_index);
+        for _ in 0..poses.unwrap() {
+            detector.session.add_pose(LoggedPose {
+                cursor: DanceCursor {
+                    section_index,
+                    ..Default::default()
+                },
+                step_id: "kicks".to_owned(),
+                step_name: "Kicks".to_owned(),
+                pose: PoseApproximation {
+                    id: "kick".to_owned(),
+                    name: "kick".to_owned(),
+                    error: 0.0,
+                    timestamp: 0.0,
+                    error_details: ErrorDetails::default(),
+                    jump: None,
+                },
+                expected_timestamp: 0.0,
+                missed: false,
+            });
+        }
+    }
+
+    /// Facing the camera with the left shin rotated by `degree`.
+    fn kicking(degree: f32) -> Keypoints {
+        let mut kp = Keypoints::default();
+        kp.left.shoulder = Cartesian3d::new(1.0, -2.0, 0.0);
+        kp.right.shoulder = Cartesian3d::new(-1.0, -2.0, 0.0);
+        kp.left.hip = Cartesian3d::new(1.0, -1.0, 0.0);
+        kp.right.hip = Cartesian3d::new(-1.0, -1.0, 0.0);
+        kp.left.knee = Cartesian3d::new(1.0, 0.0, 0.0);
+        let angle = degree.to_radians();
+        kp.left.ankle = Cartesian3d::new(1.0 - angle.sin(), angle.cos(), 0.0);
+        kp
+    }
+
+    #[test]
+    fn test_easier_after_misses() {
+        let mut detector = detector(&collection(), StepPace::normal());
+        let before = detector.teacher.tracked_subbeats();
+        // no pose matched in the first section of 8 subbeats
+        detector.adapt_difficulty(0, 1);
+
+        let adaptation = detector.session.adaptations[0];
+        assert!(adaptation.easier);
+        assert_eq!(adaptation.scored_section, 0);
+        assert_eq!(adaptation.paced_section, Some(2));
+        assert!(detector.threshold_factor > 1.0);
+        // the third section now takes twice as long
+        assert_eq!(detector.teacher.tracked_subbeats(), before + 8);
+    }
+
+    #[test]
+    fn test_harder_after_hits() {
+        let mut detector = detector(&collection(), StepPace::half_speed());
+        hit_all(&mut detector, 0);
+        detector.adapt_difficulty(0, 1);
+        hit_all(&mut detector, 1);
+        detector.adapt_difficulty(1, 2);
+
+        let adaptations = &detector.session.adaptations;
+        assert_eq!(adaptations.len(), 2);
+        assert!(!adaptations[0].easier);
+        assert_eq!(adaptations[0].paced_section, Some(2));
+        // there is no section after the last one to pace
+        assert_eq!(adaptations[1].paced_section, None);
+        assert_eq!(detector.threshold_factor, MIN_THRESHOLD_FACTOR);
+    }
+
+    #[test]
+    fn test_adapt_to_pose_errors() {
+        let db = collection();
+        let mut detector = detector(&db, StepPace::normal());
+        detector.force_beat = true;
+        detector.tempo = TempoMap::Constant {
+            bpm: 120.0,
+            first_beat: 0.0,
+        };
+        detector.transition_to_state(DetectionState::LiveTracking, 0.0);
+
+        // kicking a bit too high, just above the normal threshold
+        let kp = kicking(52.0);
+        let skeleton = Skeleton3d::from_keypoints(&kp, &db);
+        let error = db.poses()[0].skeleton_error(&skeleton).error_score();
+        let threshold = detector.config.pose_error_threshold("kick");
+        assert!(error > threshold, "{error}");
+        assert!(error < threshold * THRESHOLD_STEP, "{error}");
+
+        let (mut timestamps, mut keypoints, mut skeletons) = (vec![], vec![], vec![]);
+        let mut t = 0.0;
+        while !matches!(detector.detection_state, DetectionState::TrackingDone) && t < 60_000.0 {
+            t += 50.0;
+            timestamps.push(t);
+            keypoints.push(kp);
+            skeletons.push(Skeleton3d::from_keypoints(&kp, &db));
+            detector.tick(t, &db, &skeletons, &timestamps, &keypoints);
+        }
+
+        // all missed at first, then all matched with the tolerant threshold
+        let adaptations = &detector.session.adaptations;
+        assert_eq!(adaptations.len(), 3);
+        assert_eq!(adaptations[0].hit_rate, 0.0);
+        assert!(adaptations[0].easier);
+        assert!((adaptations[0].threshold_factor - THRESHOLD_STEP).abs() < 1e-6);
+        assert_eq!(detector.session.matched_poses(1), 8);
+        // hitting everything makes it harder again, too hard for this dancer
+        assert!(!adaptations[1].easier);
+        assert!((adaptations[1].threshold_factor - 1.0).abs() < 1e-6);
+        assert!(adaptations[2].hit_rate < LOW_HIT_RATE);
+        assert!(adaptations[2].easier);
+    }
+}
@@ -0,0 +1,301 @@
+//! Find the beats in a piece of music.
+//!
+//! This follows the classic approach by Ellis, "Beat Tracking by Dynamic
+//! Programming" (2007). First, an onset envelope is computed that peaks where
+//! notes or drums start. Autocorrelation of the envelope gives the tempo.
+//! Finally, dynamic programming picks beats that fall on strong onsets while
+//! keeping a steady tempo.
+//!
+//! Only plain energy is used for onsets, no spectrum. This works well for the
+//! drum-heavy music we dance to and keeps the code small enough for WASM.
+
+/// Onset envelope frames per second.
+const FRAME_RATE: f32 = 100.0;
+/// Tempo prior, most dance music is around this tempo.
+const PRIOR_BPM: f32 = 120.0;
+/// Width of the tempo prior, in octaves.
+const PRIOR_OCTAVES: f32 = 1.0;
+/// Tempo range searched by default.
+pub(crate) const MIN_BPM: f32 = 60.0;
+pub(crate) const MAX_BPM: f32 = 200.0;
+/// How strictly beats follow the estimated tempo.
+const TIGHTNESS: f32 = 100.0;
+/// Leading and trailing beats weaker than this, relative to the RMS of all
+/// beat onsets, are dropped as silence.
+const TRIM_RATIO: f32 = 0.5;
+
+/// Beat times in ms, with the tempo they follow.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Beats {
+    pub bpm: f32,
+    pub beats: Vec<f64>,
+}
+
+/// Track beats in mono PCM samples, with a tempo between `min_bpm` and
+/// `max_bpm`.
+///
+/// Music usually fits two tempi, one double the other. Restrict the range to
+/// pick one, otherwise the tempo closer to 120 bpm wins.
+///
+/// Returns `None` if the samples are too short or too quiet to find at least
+/// two beats.
+pub(crate) fn track_beats(
+    samples: &[f32],
+    sample_rate: f32,
+    min_bpm: f32,
+    max_bpm: f32,
+) -> Option<Beats> {
+    let hop = (sample_rate / FRAME_RATE).round().max(1.0) as usize;
+    let frame_rate = sample_rate / hop as f32;
+    let envelope = onset_envelope(samples, hop);
+    let period = estimate_period(&envelope, frame_rate, min_bpm, max_bpm)?;
+    let frames = trim_silence(&envelope, dynamic_programming(&envelope, period));
+    if frames.len() < 2 {
+        return None;
+    }
+
+    // Onsets are detected when they enter the second half of a frame window.
+    let to_ms = |frame: usize| ((frame * hop + hop / 2) as f64 / sample_rate as f64) * 1000.0;
+    let beats: Vec<f64> = frames.into_iter().map(to_ms).collect();
+    let bpm = (60_000.0 / fitted_interval(&beats)) as f32;
+    Some(Beats { bpm, beats })
+}
+
+/// Positive changes of log energy in frames of `2 * hop` samples, normalized
+/// to unit standard deviation.
+///
+/// Energy of the differentiated signal is added to emphasize high-frequency
+/// onsets like hi-hats and snares over sustained bass.
+fn onset_envelope(samples: &[f32], hop: usize) -> Vec<f32> {
+    let window = 2 * hop;
+    if samples.len() < window {
+        return vec![];
+    }
+    let num_frames = (samples.len() - window) / hop + 1;
+    let log_energy = |f: &dyn Fn(usize) -> f32, frame: usize| {
+        let start = frame * hop;
+        let sum: f32 = (start..start + window).map(|i| f(i).powi(2)).sum();
+        (1.0 + 1000.0 * sum / window as f32).ln()
+    };
+    let signal = |i: usize| samples[i];
+    let diff = |i: usize| samples[i] - samples[i.saturating_sub(1)];
+
+    let mut prev = (log_energy(&signal, 0), log_energy(&diff, 0));
+    let mut envelope = vec![0.0];
+    for frame in 1..num_frames {
+        let current = (log_energy(&signal, frame), log_energy(&diff, frame));
+        envelope.push((current.0 - prev.0).max(0.0) + (current.1 - prev.1).max(0.0));
+        prev = current;
+    }
+
+    let mean = envelope.iter().sum::<f32>() / envelope.len() as f32;
+    let variance = envelope.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / envelope.len() as f32;
+    let std = variance.sqrt();
+    if std > 0.0 {
+        for x in &mut envelope {
+            *x /= std;
+        }
+    }
+    envelope
+}
+
+/// Beat period in frames, from the autocorrelation of the envelope weighted
+/// by the tempo prior.
+fn estimate_period(envelope: &[f32], frame_rate: f32, min_bpm: f32, max_bpm: f32) -> Option<f32> {
+    let min_lag = (60.0 * frame_rate / max_bpm).floor() as usize;
+    let max_lag = ((60.0 * frame_rate / min_bpm).ceil() as usize).min(envelope.len() / 2);
+    if min_lag < 1 || max_lag <= min_lag + 1 {
+        return None;
+    }
+    let autocorrelation = |lag: usize| -> f32 {
+        envelope
+            .iter()
+            .zip(&envelope[lag..])
+            .map(|(a, b)| a * b)
+            .sum::<f32>()
+            / (envelope.len() - lag) as f32
+    };
+    let weight = |lag: f32| {
+        let bpm = 60.0 * frame_rate / lag;
+        (-0.5 * ((bpm / PRIOR_BPM).log2() / PRIOR_OCTAVES).powi(2)).exp()
+    };
+    let scores: Vec<f32> = (min_lag - 1..=max_lag + 1)
+        .map(|lag| autocorrelation(lag) * weight(lag as f32))
+        .collect();
+    let best = (1..scores.len() - 1).max_by(|a, b| scores[*a].total_cmp(&scores[*b]))?;
+    if scores[best] <= 0.0 {
+        return None;
+    }
+
+    // parabolic interpolation for a period between frames
+    let (left, center, right) = (scores[best - 1], scores[best], scores[best + 1]);
+    let denominator = left - 2.0 * center + right;
+    let offset = if denominator < 0.0 {
+        (0.5 * (left - right) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    Some((min_lag - 1 + best) as f32 + offset)
+}
+
+/// Pick beat frames that maximize onset strength while keeping intervals
+/// close to `period`.
+fn dynamic_programming(envelope: &[f32], period: f32) -> Vec<usize> {
+    let n = envelope.len();
+    let mut score = vec![0.0f32; n];
+    let mut backlink: Vec<Option<usize>> = vec![None; n];
+    let min_step = (period / 2.0).round().max(1.0) as usize;
+    let max_step = (period * 2.0).round() as usize;
+    for t in 0..n {
+        let mut best: Option<(f32, usize)> = None;
+        let first = t.saturating_sub(max_step);
+        let end = t.saturating_sub(min_step - 1).min(t);
+        for (prev, prev_score) in score[first..end].iter().enumerate() {
+            let prev = first + prev;
+            let interval = (t - prev) as f32;
+            let candidate = prev_score - TIGHTNESS * (interval / period).ln().powi(2);
+            if best.map_or(true, |(s, _)| candidate > s) {
+                best = Some((candidate, prev));
+            }
+        }
+        score[t] = envelope[t];
+        if let Some((s, prev)) = best.filter(|(s, _)| *s > 0.0) {
+            score[t] += s;
+            backlink[t] = Some(prev);
+        }
+    }
+
+    // the last beat is the best scoring frame within the last period
+    let last_start = n.saturating_sub(period.ceil() as usize);
+    let Some(mut t) = (last_start..n).max_by(|a, b| score[*a].total_cmp(&score[*b])) else {
+        return vec![];
+    };
+    let mut beats = vec![t];
+    while let Some(prev) = backlink[t] {
+        beats.push(prev);
+        t = prev;
+    }
+    beats.reverse();
+    beats
+}
+
+/// Remove beats at the start and end that don't fall on real onsets, which
+/// happens in intros and outros without beat.
+fn trim_silence(envelope: &[f32], beats: Vec<usize>) -> Vec<usize> {
+    let strength = |frame: usize| {
+        envelope[frame.saturating_sub(2)..(frame + 3).min(envelope.len())]
+            .iter()
+            .copied()
+            .fold(0.0, f32::max)
+    };
+    let strengths: Vec<f32> = beats.iter().map(|b| strength(*b)).collect();
+    if strengths.is_empty() {
+        return beats;
+    }
+    let rms = (strengths.iter().map(|s| s * s).sum::<f32>() / strengths.len() as f32).sqrt();
+    let threshold = TRIM_RATIO * rms;
+    let Some(first) = strengths.iter().position(|s| *s >= threshold) else {
+        return vec![];
+    };
+    let last = strengths
+        .iter()
+        .rposition(|s| *s >= threshold)
+        .expect("first exists");
+    beats[first..=last].to_vec()
+}
+
+/// Average beat interval from a least squares fit over all beats, which is
+/// more precise than a single interval measured in frames.
+fn fitted_interval(beats: &[f64]) -> f64 {
+    let n = beats.len() as f64;
+    let mean_i = (n - 1.0) / 2.0;
+    let mean_t = beats.iter().sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (i, t) in beats.iter().enumerate() {
+        covariance += (i as f64 - mean_i) * (t - mean_t);
+        variance += (i as f64 - mean_i).powi(2);
+    }
+    covariance / variance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 22050.0;
+
+    /// Decaying noise bursts on each beat, like a simple drum machine.
+    fn click_track(bpm: f32, first_beat_ms: f32, duration_s: f32) -> Vec<f32> {
+        let len = (duration_s * SAMPLE_RATE) as usize;
+        let mut samples = vec![0.0; len];
+        let mut lfsr = 0xACE1u16;
+        let period = 60.0 / bpm * SAMPLE_RATE;
+        let mut beat = first_beat_ms / 1000.0 * SAMPLE_RATE;
+        while (beat as usize) < len {
+            for i in 0..(0.05 * SAMPLE_RATE) as usize {
+                let Some(sample) = samples.get_mut(beat as usize + i) else {
+                    break;
+                };
+                lfsr = (lfsr >> 1) ^ (0u16.wrapping_sub(lfsr & 1) & 0xB400);
+                let noise = lfsr as f32 / u16::MAX as f32 - 0.5;
+                *sample = noise * (-(i as f32) / (0.01 * SAMPLE_RATE)).exp();
+            }
+            beat += period;
+        }
+        samples
+    }
+
+    #[track_caller]
+    fn check_click_track(bpm: f32, first_beat_ms: f32, range: (f32, f32)) {
+        let samples = click_track(bpm, first_beat_ms, 20.0);
+        let beats =
+            track_beats(&samples, SAMPLE_RATE, range.0, range.1).expect("should find beats");
+        assert!(
+            (beats.bpm - bpm).abs() < 1.0,
+            "expected {bpm} bpm, got {}",
+            beats.bpm
+        );
+        let first = beats.beats[0];
+        assert!(
+            (first - first_beat_ms as f64).abs() < 20.0,
+            "expected first beat at {first_beat_ms}, got {first}"
+        );
+        let expected_beats = ((20.0 - first_beat_ms / 1000.0) * bpm / 60.0).ceil() as usize;
+        assert!(
+            beats.beats.len().abs_diff(expected_beats) <= 1,
+            "expected {expected_beats} beats, got {}",
+            beats.beats.len()
+        );
+    }
+
+    #[test]
+    fn test_click_track_120() {
+        check_click_track(120.0, 250.0, (MIN_BPM, MAX_BPM));
+    }
+
+    #[test]
+    fn test_click_track_slow() {
+        check_click_track(93.0, 3000.0, (MIN_BPM, MAX_BPM));
+    }
+
+    #[test]
+    fn test_click_track_fast() {
+        check_click_track(174.0, 1000.0, (120.0, MAX_BPM));
+    }
+
+    /// Without a hint, fast music is tracked at half tempo.
+    #[test]
+    fn test_click_track_fast_without_range() {
+        check_click_track(87.0, 1000.0, (MIN_BPM, MAX_BPM));
+        let samples = click_track(174.0, 1000.0, 20.0);
+        let beats = track_beats(&samples, SAMPLE_RATE, MIN_BPM, MAX_BPM).unwrap();
+        assert!((beats.bpm - 87.0).abs() < 1.0, "got {}", beats.bpm);
+    }
+
+    #[test]
+    fn test_silence() {
+        let silence = vec![0.0; 22050 * 5];
+        assert_eq!(None, track_beats(&silence, SAMPLE_RATE, MIN_BPM, MAX_BPM));
+        assert_eq!(None, track_beats(&[], SAMPLE_RATE, MIN_BPM, MAX_BPM));
+    }
+}
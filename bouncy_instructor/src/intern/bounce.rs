@@ -0,0 +1,264 @@
+//! Scoring bounces to the music, for dancers not ready for steps yet.
+//!
+//! No poses are matched. Instead, the torso is followed up and down and each
+//! lowest point counts as a bounce. Heights are measured from the ankles and
+//! relative to the body height, to be independent of where the dancer stands
+//! in the camera frame.
+
+use super::dance_detector::DanceDetector;
+use crate::tracker::{Bounce, BounceInstruction, BounceTiming};
+use crate::Keypoints;
+
+type Timestamp = f64;
+
+/// How far the torso has to go down within a quarter beat, and up again, to
+/// count as a bounce, relative to the body height.
+const MIN_DEPTH: f32 = 0.01;
+/// How far from the beat or off-beat a bounce may be to count for it, in
+/// beats.
+const TIMING_TOLERANCE: f64 = 0.2;
+
+#[derive(Debug, Clone)]
+pub(crate) struct BounceTracking {
+    /// What the dancer was asked to do.
+    pub(crate) instruction: BounceInstruction,
+    pub(crate) bounces: Vec<Bounce>,
+    /// Frames up to this time have been checked for bounces.
+    checked_until: Timestamp,
+}
+
+impl BounceTracking {
+    pub(crate) fn new(instruction: BounceInstruction) -> Self {
+        Self {
+            instruction,
+            bounces: vec![],
+            checked_until: Timestamp::NEG_INFINITY,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self::new(self.instruction);
+    }
+}
+
+/// How low the torso is, 0.0 for a torso at shoulder height and 1.0 at ankle
+/// height.
+///
+/// Keypoints are in image coordinates, where a larger y is lower.
+fn torso_depth(kp: &Keypoints) -> f32 {
+    let shoulders = (kp.left.shoulder.y + kp.right.shoulder.y) / 2.0;
+    let hips = (kp.left.hip.y + kp.right.hip.y) / 2.0;
+    let ankles = (kp.left.ankle.y + kp.right.ankle.y) / 2.0;
+    let body_height = (ankles - shoulders).max(f32::EPSILON);
+    1.0 - (ankles - (shoulders + hips) / 2.0) / body_height
+}
+
+/// Timestamps of bounces between `from` (exclusive) and `until` (inclusive).
+///
+/// A frame is a bounce if the torso is lower than anywhere else within
+/// `window` ms before and after, and at least `MIN_DEPTH` lower than the
+/// highest point in that window. Frames need `window` ms of data after them to
+/// be checked.
+pub(crate) fn find_bounces(
+    timestamps: &[Timestamp],
+    keypoints: &[Keypoints],
+    from: Timestamp,
+    until: Timestamp,
+    window: f64,
+) -> Vec<Timestamp> {
+    let first = timestamps.partition_point(|t| *t <= from);
+    let last = timestamps.partition_point(|t| *t <= until);
+    // only frames within the window of a checked frame are needed
+    let offset = timestamps.partition_point(|t| *t < from - window);
+    let depths: Vec<f32> = keypoints[offset..]
+        .iter()
+        .zip(&timestamps[offset..])
+        .take_while(|(_, t)| **t <= until + window)
+        .map(|(kp, _)| torso_depth(kp))
+        .collect();
+    let depth = |i: usize| depths[i - offset];
+
+    let mut out = vec![];
+    for i in first..last {
+        let t = timestamps[i];
+        let start = timestamps.partition_point(|s| *s < t - window);
+        let end = timestamps.partition_point(|s| *s <= t + window);
+        // ties go to the first frame
+        let lowest =
+            (start..i).all(|j| depth(j) < depth(i)) && (i + 1..end).all(|j| depth(j) <= depth(i));
+        let highest = (start..end).map(depth).fold(f32::INFINITY, f32::min);
+        if lowest && depth(i) - highest >= MIN_DEPTH {
+            out.push(t);
+        }
+    }
+    out
+}
+
+/// Offset from the closest instructed beat or off-beat in beats and the timing
+/// it counts for.
+///
+/// `beat` is the position of the bounce in beats, the fractional part is
+/// where in the beat it happened.
+fn bounce_timing(beat: f64, instruction: BounceInstruction) -> (f64, BounceTiming) {
+    let target = instruction.timing();
+    let (target_phase, other) = match instruction {
+        BounceInstruction::OnBeat => (0.0, BounceTiming::OffBeat),
+        BounceInstruction::OffBeat => (0.5, BounceTiming::OnBeat),
+    };
+    let offset = (beat - target_phase) - (beat - target_phase).round();
+    let timing = if offset.abs() <= TIMING_TOLERANCE {
+        target
+    } else if offset.abs() >= 0.5 - TIMING_TOLERANCE {
+        other
+    } else {
+        BounceTiming::Between
+    };
+    (offset, timing)
+}
+
+impl BounceInstruction {
+    /// The timing of bounces that follow the instruction.
+    pub(crate) fn timing(self) -> BounceTiming {
+        match self {
+            BounceInstruction::OnBeat => BounceTiming::OnBeat,
+            BounceInstruction::OffBeat => BounceTiming::OffBeat,
+        }
+    }
+}
+
+impl BounceTiming {
+    /// Short feedback to show to the dancer.
+    pub(crate) fn text(&self) -> &'static str {
+        match self {
+            BounceTiming::OnBeat => "On the beat",
+            BounceTiming::OffBeat => "On the off-beat",
+            BounceTiming::Between => "Listen to the beat",
+        }
+    }
+}
+
+impl DanceDetector {
+    /// Look for new bounces in the frames since the last check and give
+    /// feedback whenever the timing changes.
+    pub(crate) fn detect_bounces(
+        &mut self,
+        now: Timestamp,
+        timestamps: &[Timestamp],
+        keypoints: &[Keypoints],
+    ) {
+        let window = self.tempo.beat_duration_at(now) / 4.0;
+        let start = self.beat_zero.unwrap_or(Timestamp::NEG_INFINITY);
+        let Some(tracking) = &mut self.bounce else {
+            return;
+        };
+        let from = tracking.checked_until.max(start);
+        let until = now - window;
+        if until <= from {
+            return;
+        }
+        tracking.checked_until = until;
+
+        for t in find_bounces(timestamps, keypoints, from, until, window) {
+            let seen = t - self.camera_input_delay;
+            let (beat_offset, timing) =
+                bounce_timing(self.tempo.beat_at(seen), tracking.instruction);
+            let previous = tracking.bounces.last().map(|bounce| bounce.timing);
+            if previous != Some(timing) {
+                self.ui_events
+                    .add_text(now, timing.text().to_owned(), 1000.0);
+            }
+            tracking.bounces.push(Bounce {
+                timestamp: t,
+                timing,
+                offset: beat_offset * self.tempo.beat_duration_at(seen),
+                beat_offset,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intern::tempo_map::TempoMap;
+    use crate::test_utils::standing_keypoints;
+    use crate::tracker::BounceScore;
+    use std::f64::consts::TAU;
+
+    /// Standing with the torso lowered by `dip`, in image coordinates.
+    fn standing(dip: f32) -> Keypoints {
+        let mut kp = standing_keypoints(0.5, 0.5, 0.6);
+        for side in [&mut kp.left, &mut kp.right] {
+            side.shoulder.y += dip;
+            side.hip.y += dip;
+        }
+        kp
+    }
+
+    #[test]
+    fn test_find_bounces() {
+        // 30 fps, going down every 500 ms, lowest at 250, 750, ...
+        let timestamps: Vec<f64> = (0..60).map(|i| i as f64 * 1000.0 / 30.0).collect();
+        let keypoints: Vec<Keypoints> = timestamps
+            .iter()
+            .map(|t| standing(0.04 * (1.0 - (t / 500.0 * TAU).cos()) as f32))
+            .collect();
+        let bounces = find_bounces(&timestamps, &keypoints, -1.0, 2000.0, 125.0);
+        let rounded: Vec<f64> = bounces.iter().map(|t| (t / 50.0).round() * 50.0).collect();
+        assert_eq!(rounded, [250.0, 750.0, 1250.0, 1750.0]);
+
+        // standing still is no bounce
+        let still = vec![standing(0.0); 60];
+        assert!(find_bounces(&timestamps, &still, -1.0, 2000.0, 125.0).is_empty());
+    }
+
+    #[test]
+    fn test_detect_off_beat_bounces() {
+        let mut detector = DanceDetector::default();
+        detector.tempo = TempoMap::Constant {
+            bpm: 120.0,
+            first_beat: 0.0,
+        };
+        detector.beat_zero = Some(0.0);
+        detector.bounce = Some(BounceTracking::new(BounceInstruction::OffBeat));
+        // lowest on the off-beat, as seen on camera with a delay
+        let delay = detector.camera_input_delay;
+        let timestamps: Vec<f64> = (1..150).map(|i| i as f64 * 1000.0 / 30.0).collect();
+        let keypoints: Vec<Keypoints> = timestamps
+            .iter()
+            .map(|t| standing(0.04 * (1.0 - ((t - delay) / 500.0 * TAU).cos()) as f32))
+            .collect();
+        for i in 0..timestamps.len() {
+            detector.detect_bounces(timestamps[i], &timestamps[..=i], &keypoints[..=i]);
+        }
+
+        let tracking = detector.bounce.as_ref().unwrap();
+        assert_eq!(tracking.bounces.len(), 10);
+        let score = BounceScore::new(&tracking.bounces, tracking.instruction.timing()).unwrap();
+        assert_eq!(score.misses, 0);
+        assert!(score.consistency > 0.95, "{score:?}");
+        assert!(score.mean_offset.abs() < 20.0, "{score:?}");
+        let text = detector.ui_events.next_text(f64::INFINITY).unwrap();
+        assert_eq!(text.text(), "On the off-beat");
+    }
+
+    #[test]
+    fn test_bounce_timing() {
+        let on = BounceTiming::OnBeat;
+        let off = BounceTiming::OffBeat;
+        let (offset, timing) = bounce_timing(4.1, BounceInstruction::OnBeat);
+        assert!((offset - 0.1).abs() < 1e-9);
+        assert_eq!(timing, on);
+        assert_eq!(bounce_timing(3.95, BounceInstruction::OnBeat).1, on);
+        assert_eq!(bounce_timing(4.5, BounceInstruction::OnBeat).1, off);
+        assert_eq!(
+            bounce_timing(4.3, BounceInstruction::OnBeat).1,
+            BounceTiming::Between
+        );
+
+        let (offset, timing) = bounce_timing(4.4, BounceInstruction::OffBeat);
+        assert!((offset + 0.1).abs() < 1e-9);
+        assert_eq!(timing, off);
+        assert_eq!(bounce_timing(5.0, BounceInstruction::OffBeat).1, on);
+    }
+}
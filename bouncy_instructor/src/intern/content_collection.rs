@@ -1,8 +1,11 @@
 use std::collections::{BTreeMap, HashSet};
 use std::rc::Rc;
 
+use super::language::Language;
 use super::step::StepSource;
 use super::tracker_dance_collection::{AddPoseError, TrackerDanceCollection};
+use crate::parsing::course_file::TranslatedString;
+use crate::parsing::ParseFileError;
 use crate::wrapper::dance_wrapper::DanceWrapper;
 use crate::wrapper::pose_wrapper::PoseWrapper;
 use crate::wrapper::step_wrapper::StepWrapper;
@@ -27,6 +30,9 @@ pub(crate) struct ContentCollection {
     poses: Vec<PoseWrapper>,
     steps: BTreeMap<StepSource, Vec<StepWrapper>>,
     dances: Vec<DanceWrapper>,
+    /// Poses created by merging the layers of layered steps, listed in `poses`
+    /// but not defined in any pose file.
+    layered_pose_ids: HashSet<String>,
     // TODO: should use wrapper
     // courses: Vec<course_file::CourseFile>,
     //
@@ -44,6 +50,13 @@ pub(crate) struct ContentCollection {
 }
 
 impl ContentCollection {
+    pub(crate) fn new(lang: String) -> Self {
+        Self {
+            tracker_view: Rc::new(TrackerDanceCollection::new(Language::new(lang))),
+            ..Default::default()
+        }
+    }
+
     pub(crate) fn poses(&self) -> &[PoseWrapper] {
         &self.poses
     }
@@ -75,7 +88,12 @@ impl ContentCollection {
         poses: Vec<crate::pose_file::Pose>,
     ) -> Result<(), AddPoseError> {
         Rc::make_mut(&mut self.tracker_view).add_poses(poses.iter())?;
-        self.poses.extend(poses.into_iter().map(PoseWrapper::new));
+        let lang = self.lang().clone();
+        self.poses.extend(
+            poses
+                .into_iter()
+                .map(|def| PoseWrapper::new(def, lang.clone())),
+        );
         Ok(())
     }
 
@@ -108,6 +126,7 @@ impl ContentCollection {
         source: StepSource,
     ) -> Result<(), AddStepError> {
         Rc::make_mut(&mut self.tracker_view).add_steps(steps.as_ref().iter(), source.clone())?;
+        self.register_layered_poses();
         self.steps.entry(source.clone()).or_default().extend(
             steps.into_iter().map(|def| {
                 StepWrapper::new_cold(def, source.clone()).warmed_up(&self.tracker_view)
@@ -116,6 +135,26 @@ impl ContentCollection {
         Ok(())
     }
 
+    /// Add wrappers for merged poses the tracker view created for layered
+    /// steps, so they can be looked up like any other pose.
+    fn register_layered_poses(&mut self) {
+        let known: HashSet<String> = self
+            .poses
+            .iter()
+            .map(|pose| pose.definition().id.clone())
+            .collect();
+        let lang = self.lang().clone();
+        for (index, pose) in self.tracker_view.poses().iter().enumerate() {
+            let id = self.tracker_view.pose_id(index);
+            if known.contains(id) {
+                continue;
+            }
+            let def = crate::pose_file::Pose::from_intern(id.to_owned(), pose, &self.tracker_view);
+            self.layered_pose_ids.insert(def.id.clone());
+            self.poses.push(PoseWrapper::new(def, lang.clone()));
+        }
+    }
+
     pub(crate) fn replace_steps(&mut self, source: StepSource, steps: Vec<StepWrapper>) {
         let removed = self.steps.insert(source.clone(), steps.clone());
         if let Some(removed) = removed {
@@ -144,26 +183,134 @@ impl ContentCollection {
         Ok(())
     }
 
-    /// Change language and reload the tracker view on it.
-    pub(crate) fn set_language(&mut self, lang: String) {
-        let mut db = TrackerDanceCollection::new(lang);
-        const EXPECT: &str = "input checked previously";
+    /// Change the display language in place.
+    ///
+    /// All translations are kept in memory, hence nothing needs to be
+    /// reloaded. Wrappers, trackers and lessons created from this collection
+    /// share the language and show the new translations from now on.
+    pub(crate) fn set_language(&self, lang: String) {
+        self.tracker_view.lang().set(lang);
+    }
 
-        db.add_poses(self.poses.iter().map(|p| p.definition()))
-            .expect(EXPECT);
-        for step in self.steps() {
-            db.add_steps(std::iter::once(step.definition()), step.source().clone())
-                .expect(EXPECT);
+    pub(crate) fn lang(&self) -> &Language {
+        self.tracker_view.lang()
+    }
+
+    /// Remove poses, steps and dances by id.
+    ///
+    /// Removing poses shifts the indices in the tracker view, hence it is
+    /// rebuilt and all remaining steps are warmed up again. Trackers created
+    /// before keep working on the previous view.
+    ///
+    /// Fails if remaining content references removed content, in which case
+    /// the collection is left in an undefined state. Call this on a copy to
+    /// keep the original on errors.
+    pub(crate) fn remove_content(
+        &mut self,
+        pose_ids: &[String],
+        step_ids: &[String],
+        dance_ids: &[String],
+    ) -> Result<(), ParseFileError> {
+        // merged poses are created again for the remaining layered steps
+        let layered_pose_ids = std::mem::take(&mut self.layered_pose_ids);
+        self.poses.retain(|pose| {
+            let id = &pose.definition().id;
+            !pose_ids.contains(id) && !layered_pose_ids.contains(id)
+        });
+        for steps in self.steps.values_mut() {
+            steps.retain(|step| !step_ids.contains(&step.definition().id));
         }
-        db.add_dances(self.dances.iter().map(DanceWrapper::definition))
-            .expect(EXPECT);
+        self.steps.retain(|_source, steps| !steps.is_empty());
+        self.dances
+            .retain(|dance| !dance_ids.contains(&dance.definition().id));
 
+        let mut db = TrackerDanceCollection::new(self.lang().clone());
+        db.add_poses(self.poses.iter().map(|p| p.definition()))?;
+        // Layered steps need their layers added first, which may come from
+        // any source.
+        let mut pending: Vec<&StepWrapper> = self.steps().collect();
+        while !pending.is_empty() {
+            let (ready, blocked): (Vec<_>, Vec<_>) = pending.into_iter().partition(|step| {
+                step.definition()
+                    .layers
+                    .iter()
+                    .all(|layer| db.step(&layer.step).is_some())
+            });
+            if ready.is_empty() {
+                let missing = blocked
+                    .iter()
+                    .flat_map(|step| &step.definition().layers)
+                    .find(|layer| db.step(&layer.step).is_none())
+                    .expect("blocked steps have a missing layer");
+                return Err(AddStepError::MissingLayerStep(missing.step.clone()).into());
+            }
+            for step in ready {
+                db.add_steps(std::iter::once(step.definition()), step.source().clone())?;
+            }
+            pending = blocked;
+        }
+        db.add_dances(self.dances.iter().map(DanceWrapper::definition))?;
+        for step in self.steps.values_mut().flatten() {
+            step.warm_up(&db);
+        }
         self.tracker_view = Rc::new(db);
+        self.register_layered_poses();
+        Ok(())
     }
 
     pub(crate) fn pose_by_id(&self, pose_id: &str) -> Option<&PoseWrapper> {
         self.poses.iter().find(|p| p.definition().id == pose_id)
     }
+
+    /// List all translations missing for any of the given languages.
+    ///
+    /// Names are expected on every pose, step and dance. Variation
+    /// descriptions are optional but once a step has one, it should be
+    /// available in all languages.
+    pub(crate) fn missing_translations(&self, langs: &[String]) -> Vec<ParseFileError> {
+        let mut out = vec![];
+        let mut check = |id: String, translations: Option<&TranslatedString>, required: bool| {
+            if !required && translations.is_none() {
+                return;
+            }
+            for lang in langs {
+                if !translations.is_some_and(|t| t.has_lang(lang)) {
+                    out.push(ParseFileError::MissingTranslation {
+                        id: id.clone(),
+                        lang: lang.clone(),
+                    });
+                }
+            }
+        };
+
+        for pose in &self.poses {
+            let def = pose.definition();
+            check(format!("name of pose {}", def.id), def.names.as_ref(), true);
+        }
+        for step in self.steps() {
+            let def = step.definition();
+            check(format!("name of step {}", def.id), def.names.as_ref(), true);
+            check(
+                format!("variation description of step {}", def.id),
+                def.variation_descriptions.as_ref(),
+                false,
+            );
+        }
+        for dance in &self.dances {
+            let def = dance.definition();
+            check(
+                format!("name of dance {}", def.id),
+                def.names.as_ref(),
+                true,
+            );
+            check(
+                format!("description of dance {}", def.id),
+                def.descriptions.as_ref(),
+                false,
+            );
+        }
+        out
+    }
 }
 
 impl std::fmt::Debug for ContentCollection {
@@ -0,0 +1,501 @@
+//! Content packs put poses, steps, dances and courses in a namespace, to load
+//! them next to the built-in content without id collisions.
+//!
+//! This module rewrites ids and references into the namespace of a pack and
+//! remembers what was loaded from which pack, to unload it again.
+
+use super::content_collection::ContentCollection;
+use super::step::StepSource;
+use crate::parsing::course_file::{CourseFile, Prerequisite};
+use crate::parsing::pack_file::PackFile;
+use crate::parsing::ParseFileError;
+use crate::PackError;
+use std::collections::HashSet;
+
+/// Separates the pack id from the local id in qualified ids.
+const SEPARATOR: char = '/';
+
+/// Bookkeeping for a pack that has been added to the global collection.
+#[derive(Clone, Debug)]
+pub(crate) struct LoadedPack {
+    pub id: String,
+    pub version: u32,
+    pub dependencies: Vec<String>,
+    /// Qualified ids
+    pub pose_ids: Vec<String>,
+    /// Qualified ids
+    pub step_ids: Vec<String>,
+    /// Qualified ids
+    pub dance_ids: Vec<String>,
+    /// Courses are not part of the global collection, they are kept here to
+    /// be parsed on demand.
+    pub courses: Vec<CourseFile>,
+    /// Local ids which also exist without namespace.
+    pub shadowed_ids: Vec<String>,
+}
+
+/// All packs loaded into the global collection, in load order.
+#[derive(Default)]
+pub(crate) struct PackRegistry {
+    packs: Vec<LoadedPack>,
+}
+
+/// Local ids of a pack, for resolving references inside the pack.
+struct Namespace {
+    pack_id: String,
+    dependencies: Vec<String>,
+    poses: HashSet<String>,
+    steps: HashSet<String>,
+    dances: HashSet<String>,
+    courses: HashSet<String>,
+}
+
+impl PackRegistry {
+    pub(crate) fn packs(&self) -> &[LoadedPack] {
+        &self.packs
+    }
+
+    pub(crate) fn pack(&self, id: &str) -> Option<&LoadedPack> {
+        self.packs.iter().find(|pack| pack.id == id)
+    }
+
+    /// Add all content of the pack to the collection, in the namespace of the
+    /// pack. On errors, the collection remains unchanged.
+    pub(crate) fn load(
+        &mut self,
+        mut file: PackFile,
+        db: &mut ContentCollection,
+    ) -> Result<&LoadedPack, PackError> {
+        if file.id.is_empty() || file.id.contains(SEPARATOR) {
+            return Err(PackError::InvalidId(file.id));
+        }
+        if self.pack(&file.id).is_some() {
+            return Err(PackError::AlreadyLoaded(file.id));
+        }
+        for dependency in &file.dependencies {
+            if !self
+                .pack(&dependency.id)
+                .is_some_and(|loaded| loaded.version >= dependency.min_version)
+            {
+                return Err(PackError::MissingDependency {
+                    pack: file.id.clone(),
+                    dependency: dependency.id.clone(),
+                    min_version: dependency.min_version,
+                });
+            }
+        }
+
+        let ns = Namespace::new(&file)?;
+        let shadowed_ids = ns.shadowed_ids(db);
+
+        for pose in &mut file.poses {
+            if !pose.mirror_of.is_empty() {
+                pose.mirror_of = ns.resolve(&ns.poses, &pose.mirror_of)?;
+            }
+            pose.id = ns.qualify(&pose.id);
+        }
+        for step in &mut file.steps {
+            for keyframe in &mut step.keyframes {
+                keyframe.pose = ns.resolve(&ns.poses, &keyframe.pose)?;
+            }
+            for layer in &mut step.layers {
+                layer.step = ns.resolve(&ns.steps, &layer.step)?;
+            }
+            step.id = ns.qualify(&step.id);
+        }
+        for dance in &mut file.dances {
+            for step in &mut dance.steps {
+                step.id = ns.resolve(&ns.steps, &step.id)?;
+            }
+            dance.id = ns.qualify(&dance.id);
+        }
+        for course in &mut file.courses {
+            course.featured_step = ns.resolve(&ns.steps, &course.featured_step)?;
+            for prerequisite in course.prerequisites_mut() {
+                match prerequisite {
+                    Prerequisite::Lesson {
+                        course: Some(id), ..
+                    } => *id = ns.resolve(&ns.courses, id)?,
+                    Prerequisite::Lesson { course: None, .. } => (),
+                    Prerequisite::Step { id, .. } => *id = ns.resolve(&ns.steps, id)?,
+                }
+            }
+            course.id = ns.qualify(&course.id);
+        }
+
+        let pose_ids = file.poses.iter().map(|p| p.id.clone()).collect();
+        let step_ids = file.steps.iter().map(|s| s.id.clone()).collect();
+        let dance_ids = file.dances.iter().map(|d| d.id.clone()).collect();
+
+        // Work on a copy to leave the collection untouched if anything fails.
+        let mut new_db = db.clone();
+        new_db.add_poses(file.poses).map_err(ParseFileError::from)?;
+        new_db
+            .add_steps(file.steps, StepSource::new(file.id.clone()))
+            .map_err(ParseFileError::from)?;
+        new_db
+            .add_dances(file.dances)
+            .map_err(ParseFileError::from)?;
+        *db = new_db;
+
+        self.packs.push(LoadedPack {
+            id: file.id,
+            version: file.pack_version,
+            dependencies: ns.dependencies,
+            pose_ids,
+            step_ids,
+            dance_ids,
+            courses: file.courses,
+            shadowed_ids,
+        });
+        Ok(self.packs.last().expect("just pushed"))
+    }
+
+    /// Remove all content of the pack from the collection.
+    pub(crate) fn unload(
+        &mut self,
+        pack_id: &str,
+        db: &mut ContentCollection,
+    ) -> Result<(), PackError> {
+        let index = self
+            .packs
+            .iter()
+            .position(|pack| pack.id == pack_id)
+            .ok_or_else(|| PackError::NotLoaded(pack_id.to_owned()))?;
+        if let Some(dependent) = self
+            .packs
+            .iter()
+            .find(|pack| pack.dependencies.iter().any(|dep| dep == pack_id))
+        {
+            return Err(PackError::StillRequired {
+                pack: pack_id.to_owned(),
+                dependent: dependent.id.clone(),
+            });
+        }
+        let pack = &self.packs[index];
+        // Work on a copy to leave the collection untouched if anything fails.
+        let mut new_db = db.clone();
+        new_db
+            .remove_content(&pack.pose_ids, &pack.step_ids, &pack.dance_ids)
+            .map_err(|error| PackError::StillReferenced {
+                pack: pack_id.to_owned(),
+                error: Box::new(error),
+            })?;
+        *db = new_db;
+        self.packs.remove(index);
+        Ok(())
+    }
+}
+
+impl Namespace {
+    /// Collect local ids, ensuring they are valid and unique.
+    fn new(file: &PackFile) -> Result<Self, PackError> {
+        let mut ns = Namespace {
+            pack_id: file.id.clone(),
+            dependencies: file.dependencies.iter().map(|d| d.id.clone()).collect(),
+            poses: HashSet::new(),
+            steps: HashSet::new(),
+            dances: HashSet::new(),
+            courses: HashSet::new(),
+        };
+        let all_ids = [
+            (
+                &mut ns.poses,
+                file.poses.iter().map(|p| &p.id).collect::<Vec<_>>(),
+            ),
+            (&mut ns.steps, file.steps.iter().map(|s| &s.id).collect()),
+            (&mut ns.dances, file.dances.iter().map(|d| &d.id).collect()),
+            (
+                &mut ns.courses,
+                file.courses.iter().map(|c| &c.id).collect(),
+            ),
+        ];
+        for (set, ids) in all_ids {
+            for id in ids {
+                if id.is_empty() || id.contains(SEPARATOR) {
+                    return Err(PackError::InvalidId(id.clone()));
+                }
+                if !set.insert(id.clone()) {
+                    return Err(PackError::DuplicateId(format!(
+                        "{}{SEPARATOR}{id}",
+                        file.id
+                    )));
+                }
+            }
+        }
+        Ok(ns)
+    }
+
+    fn qualify(&self, local_id: &str) -> String {
+        format!("{}{SEPARATOR}{local_id}", self.pack_id)
+    }
+
+    /// Resolve a reference made from inside the pack to a global id.
+    fn resolve(&self, local_ids: &HashSet<String>, id: &str) -> Result<String, PackError> {
+        match id.split_once(SEPARATOR) {
+            Some((pack, _))
+                if pack == self.pack_id || self.dependencies.iter().any(|d| d == pack) =>
+            {
+                Ok(id.to_owned())
+            }
+            Some(_) => Err(PackError::UndeclaredDependency {
+                pack: self.pack_id.clone(),
+                id: id.to_owned(),
+            }),
+            None if local_ids.contains(id) => Ok(self.qualify(id)),
+            None => Ok(id.to_owned()),
+        }
+    }
+
+    fn shadowed_ids(&self, db: &ContentCollection) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .poses
+            .iter()
+            .filter(|id| db.pose_by_id(id).is_some())
+            .chain(self.steps.iter().filter(|id| db.step(id).is_some()))
+            .chain(
+                self.dances
+                    .iter()
+                    .filter(|id| db.dances().any(|d| d.definition().id == **id)),
+            )
+            .cloned()
+            .collect();
+        out.sort();
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::public::course::Prerequisite;
+    use crate::{
+        content_pack_courses, content_packs, dances, load_content_pack, load_dance_str,
+        load_pose_str, load_step_str, poses, step_by_id, steps_by_source, unload_content_pack,
+        PackError,
+    };
+
+    const GLOBAL_POSE_STR: &str = r#"
+      (
+        version: 1,
+        poses: [
+          (id: "left", direction: Right),
+        ]
+      )
+      "#;
+
+    const PACK_STR: &str = r#"
+      #![enable(implicit_some)]
+      (
+        version: 0,
+        id: "club",
+        pack_version: 2,
+        poses: [
+          (
+            id: "left",
+            direction: Right,
+            limbs: [
+              (limb: LeftShin, angle: 0, tolerance: 5, weight: 1.0),
+            ]
+          ),
+          (id: "right", direction: Right, mirror_of: "left"),
+        ],
+        steps: [
+          (
+            id: "rm-0",
+            name: "Club RM",
+            keyframes: [(pose: "left"), (pose: "right")],
+          ),
+        ],
+        dances: [
+          (id: "dance", steps: [(id: "rm-0")]),
+        ],
+      )
+      "#;
+
+    fn dependent_pack(min_version: u32) -> String {
+        format!(
+            r#"(
+              version: 0,
+              id: "friends",
+              pack_version: 1,
+              dependencies: [(id: "club", min_version: {min_version})],
+              dances: [
+                (id: "dance", steps: [(id: "club/rm-0"), (id: "club/rm-0")]),
+              ],
+            )"#
+        )
+    }
+
+    #[test]
+    fn test_load_pack_with_namespace() {
+        load_pose_str(GLOBAL_POSE_STR).unwrap();
+        let info = load_content_pack(PACK_STR).unwrap();
+
+        assert_eq!(info.pose_ids(), ["club/left", "club/right"]);
+        assert_eq!(info.step_ids(), ["club/rm-0"]);
+        assert_eq!(info.dance_ids(), ["club/dance"]);
+        assert_eq!(info.shadowed_ids(), ["left"]);
+
+        assert!(step_by_id("club/rm-0".to_owned(), false).is_some());
+        assert_eq!(steps_by_source("club").len(), 1);
+        assert_eq!(dances()[0].steps()[0].id(), "club/rm-0");
+        // the global pose is untouched
+        assert_eq!(poses().len(), 3);
+
+        match load_content_pack(PACK_STR) {
+            Err(PackError::AlreadyLoaded(id)) if id == "club" => (),
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_pack_dependencies() {
+        match load_content_pack(&dependent_pack(1)) {
+            Err(PackError::MissingDependency { dependency, .. }) if dependency == "club" => (),
+            other => panic!("expected an error, got {other:?}"),
+        }
+        load_content_pack(PACK_STR).unwrap();
+        assert!(load_content_pack(&dependent_pack(3)).is_err());
+        load_content_pack(&dependent_pack(2)).unwrap();
+        assert_eq!(dances().len(), 2);
+
+        match unload_content_pack("club") {
+            Err(PackError::StillRequired { dependent, .. }) if dependent == "friends" => (),
+            other => panic!("expected an error, got {other:?}"),
+        }
+        unload_content_pack("friends").unwrap();
+        unload_content_pack("club").unwrap();
+
+        assert!(content_packs().is_empty());
+        assert!(dances().is_empty());
+        assert!(poses().is_empty());
+        assert!(step_by_id("club/rm-0".to_owned(), false).is_none());
+    }
+
+    #[test]
+    fn test_pack_undeclared_dependency() {
+        load_content_pack(PACK_STR).unwrap();
+        let text = dependent_pack(0).replace("dependencies: [(id: \"club\", min_version: 0)],", "");
+        match load_content_pack(&text) {
+            Err(PackError::UndeclaredDependency { id, .. }) if id == "club/rm-0" => (),
+            other => panic!("expected an error, got {other:?}"),
+        }
+        // nothing from the failed pack was added
+        assert_eq!(content_packs().len(), 1);
+        assert_eq!(dances().len(), 1);
+    }
+
+    #[test]
+    fn test_unload_referenced_pack() {
+        load_content_pack(PACK_STR).unwrap();
+        load_dance_str(
+            r#"(
+              version: 0,
+              dances: [(id: "global", steps: ["club/rm-0"])],
+            )"#,
+        )
+        .unwrap();
+        match unload_content_pack("club") {
+            Err(PackError::StillReferenced { pack, .. }) if pack == "club" => (),
+            other => panic!("expected an error, got {other:?}"),
+        }
+        // nothing was removed
+        assert_eq!(content_packs().len(), 1);
+        assert!(step_by_id("club/rm-0".to_owned(), false).is_some());
+        assert_eq!(dances().len(), 2);
+    }
+
+    /// Unloading rebuilds all other content, layered steps must still find
+    /// their layers from other sources.
+    #[test]
+    fn test_unload_keeps_layered_steps() {
+        load_content_pack(PACK_STR).unwrap();
+        load_content_pack(&dependent_pack(1)).unwrap();
+        load_step_str(
+            r#"(
+              version: 0,
+              steps: [
+                (
+                  id: "layered",
+                  name: "Layered",
+                  keyframes: [],
+                  layers: [
+                    (step: "club/rm-0", region: LowerBody),
+                    (step: "club/rm-0", region: UpperBody),
+                  ],
+                ),
+              ],
+            )"#,
+            "aa".to_owned(),
+        )
+        .unwrap();
+        let merged_poses = || {
+            step_by_id("layered".to_owned(), false)
+                .unwrap()
+                .poses()
+                .iter()
+                .map(|pose| pose.id())
+                .collect::<Vec<_>>()
+        };
+        let expected = [
+            "club/left[LowerBody]+club/left[UpperBody]",
+            "club/right[LowerBody]+club/right[UpperBody]",
+        ];
+        assert_eq!(merged_poses(), expected);
+
+        unload_content_pack("friends").unwrap();
+        assert_eq!(merged_poses(), expected);
+        assert_eq!(poses().len(), 2 + expected.len());
+    }
+
+    #[test]
+    fn test_pack_prerequisites_in_namespace() {
+        let course = |id: &str, prerequisites: &str| {
+            format!(
+                r#"(
+                  version: 0,
+                  id: "{id}",
+                  names: {{"en": "{id}"}},
+                  featured_step: "rm-0",
+                  prerequisites: [{prerequisites}],
+                  lessons: [],
+                  poses: [],
+                  steps: [],
+                )"#
+            )
+        };
+        let courses = [
+            course("basics", ""),
+            course(
+                "advanced",
+                r#"Step(id: "rm-0", min_hit_ratio: 0.5),
+                   Lesson(course: "basics", lesson: 0, min_hit_ratio: 0.5)"#,
+            ),
+        ]
+        .join(",");
+        let text = PACK_STR.replace(
+            "dances: [",
+            &format!("courses: [{courses}],\n        dances: ["),
+        );
+        load_content_pack(&text).unwrap();
+
+        let courses = content_pack_courses("club", "en").unwrap();
+        assert_eq!(courses[1].id, "club/advanced");
+        match &courses[1].prerequisites[..] {
+            [Prerequisite::Step { step_id, .. }, Prerequisite::Lesson { course_id, .. }] => {
+                assert_eq!(step_id, "club/rm-0");
+                assert_eq!(course_id, "club/basics");
+            }
+            other => panic!("unexpected prerequisites {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_pack_duplicate_id() {
+        let text = PACK_STR.replace("id: \"right\"", "id: \"left\"");
+        match load_content_pack(&text) {
+            Err(PackError::DuplicateId(id)) if id == "club/left" => (),
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+}
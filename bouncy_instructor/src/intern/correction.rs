@@ -0,0 +1,182 @@
+//! Turning pose error details into concrete corrections for the dancer.
+//!
+//! Each limb off by more than its tolerance gets one correction, telling which
+//! way to move it. Limb errors are zero within the tolerance, so these are the
+//! limbs with a non-zero error. Angles measure how far a limb is rotated away
+//! from pointing down. Seen from the front, positive angles point to the right
+//! side of the dancer. Seen from the side, positive angles point forward.
+//!
+//! Depth is only corrected through body part orderings. Absolute depth and its
+//! quadrant errors don't count towards the error score and the z-tracking is
+//! too inaccurate to tell a dancer to move a single body part forward.
+
+use super::pose::{BodyPart, BodyPartOrdering, BodySide};
+use super::pose_score::ErrorDetails;
+use super::tracker_dance_collection::TrackerDanceCollection;
+use crate::tracker::{CorrectionKind, PoseCorrection};
+
+/// Limbs this close to pointing down, in radians, are on neither side.
+const CENTER_TOLERANCE: f32 = 0.1;
+
+impl ErrorDetails {
+    /// Corrections for all limbs with an error, plus one for each body part
+    /// ordering that is wrong, highest impact on the error score first.
+    ///
+    /// Wrong orderings prevent a match regardless of the error score, hence
+    /// they always come first.
+    pub(crate) fn corrections(&self, db: &TrackerDanceCollection) -> Vec<PoseCorrection> {
+        let mut out: Vec<PoseCorrection> = self
+            .z_order_errors
+            .iter()
+            .map(ordering_correction)
+            .collect();
+
+        let total_weight: f32 = self.weights.iter().sum();
+        for (i, &limb_index) in self.limbs.iter().enumerate() {
+            let (error, weight) = (self.errors[i], self.weights[i]);
+            if error <= 0.0 || weight <= 0.0 {
+                continue;
+            }
+            let side = db.limb(limb_index).end.side;
+            out.push(PoseCorrection {
+                kind: limb_correction(
+                    self.angles[i].as_radians(),
+                    self.target_angles[i].as_radians(),
+                    side,
+                    self.sideway,
+                ),
+                limb: Some(limb_index),
+                ordering: None,
+                impact: error * weight / total_weight,
+            });
+        }
+
+        out.sort_by(|a, b| f32::total_cmp(&b.impact, &a.impact));
+        out
+    }
+}
+
+/// Which way a limb at `angle` has to move to reach `target`.
+fn limb_correction(angle: f32, target: f32, side: BodySide, sideway: bool) -> CorrectionKind {
+    let centered = |alpha: f32| alpha.abs() <= CENTER_TOLERANCE;
+    let same_side = angle.signum() == target.signum() || centered(angle) || centered(target);
+    if same_side {
+        // further away from pointing down means higher
+        if angle.abs() < target.abs() {
+            CorrectionKind::Raise
+        } else {
+            CorrectionKind::Lower
+        }
+    } else if sideway {
+        if target > 0.0 {
+            CorrectionKind::Forward
+        } else {
+            CorrectionKind::Backward
+        }
+    } else {
+        let outward = match side {
+            BodySide::Right => target > 0.0,
+            BodySide::Left => target < 0.0,
+        };
+        if outward {
+            CorrectionKind::Outward
+        } else {
+            CorrectionKind::Inward
+        }
+    }
+}
+
+fn ordering_correction(ordering: &BodyPartOrdering) -> PoseCorrection {
+    let kind = match (ordering.forward.part, ordering.backward.part) {
+        (BodyPart::Hip, BodyPart::Hip) => CorrectionKind::TurnHips,
+        (BodyPart::Shoulder, BodyPart::Shoulder) => CorrectionKind::TurnShoulders,
+        _ => CorrectionKind::InFront,
+    };
+    PoseCorrection {
+        kind,
+        limb: None,
+        ordering: Some(*ordering),
+        impact: 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intern::geom::SignedAngle;
+    use crate::intern::pose::{BodyPoint, Limb};
+
+    #[test]
+    fn test_limb_correction_front() {
+        let right = BodySide::Right;
+        let left = BodySide::Left;
+        // arm hanging down but should point sideways
+        assert_eq!(
+            limb_correction(0.0, 1.5, right, false),
+            CorrectionKind::Raise
+        );
+        assert_eq!(
+            limb_correction(1.5, 0.3, right, false),
+            CorrectionKind::Lower
+        );
+        // arm crossing in front of the body
+        assert_eq!(
+            limb_correction(-0.5, 0.5, right, false),
+            CorrectionKind::Outward
+        );
+        assert_eq!(
+            limb_correction(-0.5, 0.5, left, false),
+            CorrectionKind::Inward
+        );
+    }
+
+    #[test]
+    fn test_limb_correction_side() {
+        let side = BodySide::Right;
+        // knee not high enough for a kick
+        assert_eq!(limb_correction(0.3, 0.8, side, true), CorrectionKind::Raise);
+        // leg behind instead of in front
+        assert_eq!(
+            limb_correction(-0.4, 0.4, side, true),
+            CorrectionKind::Forward
+        );
+        assert_eq!(
+            limb_correction(0.4, -0.4, side, true),
+            CorrectionKind::Backward
+        );
+    }
+
+    #[test]
+    fn test_corrections_ranked() {
+        let db = TrackerDanceCollection::default();
+        let details = ErrorDetails {
+            limbs: vec![Limb::LEFT_THIGH, Limb::RIGHT_ARM, Limb::RIGHT_SHIN],
+            errors: vec![0.2, 0.5, 0.0],
+            weights: vec![1.0, 1.0, 2.0],
+            angles: vec![SignedAngle(0.0), SignedAngle(0.0), SignedAngle(0.0)],
+            target_angles: vec![SignedAngle(-0.8), SignedAngle(1.5), SignedAngle(0.0)],
+            z_order_errors: vec![BodyPartOrdering {
+                forward: BodyPoint {
+                    side: BodySide::Left,
+                    part: BodyPart::Hip,
+                },
+                backward: BodyPoint {
+                    side: BodySide::Right,
+                    part: BodyPart::Hip,
+                },
+            }],
+            ..Default::default()
+        };
+        let corrections = details.corrections(&db);
+        let kinds: Vec<_> = corrections.iter().map(|c| (c.kind, c.limb)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (CorrectionKind::TurnHips, None),
+                (CorrectionKind::Raise, Some(Limb::RIGHT_ARM)),
+                (CorrectionKind::Raise, Some(Limb::LEFT_THIGH)),
+            ]
+        );
+        assert!((corrections[1].impact - 0.125).abs() < 1e-6);
+    }
+}
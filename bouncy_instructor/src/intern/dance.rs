@@ -1,8 +1,11 @@
 use crate::dance_file;
+use crate::parsing::course_file::TranslatedString;
 
 #[derive(Clone, Debug)]
 pub(crate) struct Dance {
     pub id: String,
+    pub names: Option<TranslatedString>,
+    pub descriptions: Option<TranslatedString>,
     pub step_ids: Vec<String>,
     pub flip_orientation: Vec<bool>,
 }
@@ -17,6 +20,8 @@ impl From<dance_file::Dance> for Dance {
 
         Dance {
             id: def.id,
+            names: def.names,
+            descriptions: def.descriptions,
             step_ids,
             flip_orientation,
         }
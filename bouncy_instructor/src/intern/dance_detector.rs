@@ -1,15 +1,25 @@
 use svelte_store::Readable;
 use wasm_bindgen::prelude::wasm_bindgen;
 
+use crate::tracker::FramingHint;
 use crate::tracker::{DanceCursor, PoseApproximation, TeacherView};
 use crate::ui_event::UiEvents;
-use crate::{DetectionFailureReason, DetectionResult, PoseHint, StepInfo};
+use crate::{DetectionFailureReason, DetectionResult, DetectorConfig, PoseHint, StepInfo};
 
+use super::bounce::BounceTracking;
+use super::framing::framing_hint;
+use super::jump::JumpScore;
+use super::loop_practice::LoopPractice;
 use super::pose::PoseDirection;
+use super::rep_counter::RepCounter;
+use super::session_log::{LoggedPose, SessionLog};
 use super::skeleton_3d::Skeleton3d;
 use super::step_pace::StepPace;
 use super::teacher::Teacher;
+use super::tempo_map::TempoMap;
 use super::tracker_dance_collection::TrackerDanceCollection;
+use super::travel::TravelScore;
+use crate::{Keypoints, Meter};
 
 type Timestamp = f64;
 
@@ -18,17 +28,21 @@ type Timestamp = f64;
 #[wasm_bindgen]
 pub(crate) struct DanceDetector {
     // config
-    pub(crate) bpm: f32,
-    pub(crate) error_threshold: f32,
-    /// When this is set, pose detection happens on the beat only.
-    pub(crate) beat_alignment: Option<Timestamp>,
+    /// Beats of the music, which poses are timed on.
+    pub(crate) tempo: TempoMap,
+    pub(crate) config: DetectorConfig,
     /// The timestamp of when the beat zero was.
     pub(crate) beat_zero: Option<Timestamp>,
     /// Enforce that a pose is evaluated on beat, regardless of how well it matches.
     pub(crate) force_beat: bool,
+    /// Adapt the difficulty to the dancer section by section.
+    pub(crate) adaptive: bool,
     /// How long it takes from a movement of the person on camera to be visible
     /// in an image.
     pub(crate) camera_input_delay: f64,
+    /// Width divided by height of the camera image. Keypoints are normalized
+    /// per axis, this converts horizontal to vertical distances.
+    pub(crate) aspect_ratio: f32,
 
     /// picks steps, switches between views, etc
     pub(crate) teacher: Teacher,
@@ -40,12 +54,37 @@ pub(crate) struct DanceDetector {
     /// collected here until the tolerance time frame is surpassed or a good
     /// match is found.
     on_beat_candidates: Vec<PoseApproximation>,
+    /// Subbeat of the first pose in the partially detected step.
+    step_start_subbeat: u32,
+    /// Subbeat at which the teacher continues after a pause, zero before the
+    /// first pause.
+    resume_subbeat: u32,
+    /// What is wrong with the placement of the dancer, while positioning.
+    pub(crate) framing_hint: Option<FramingHint>,
+    /// Applied to all pose error thresholds, changed in adaptive mode.
+    pub(crate) threshold_factor: f32,
+    /// The teacher section at the last tick, to notice when one ends.
+    pub(crate) time_section: Option<usize>,
+    /// Set when practicing a segment in a loop.
+    pub(crate) loop_practice: Option<LoopPractice>,
+    /// The loop after which the dancer reached the mastery hit rate.
+    pub(crate) mastered_loop: Option<u32>,
+    /// Set in repetition mode, which counts steps instead of following the
+    /// teacher.
+    pub(crate) rep_counter: Option<RepCounter>,
+    /// Set in bounce mode, which scores bounces instead of poses.
+    pub(crate) bounce: Option<BounceTracking>,
+    /// Record of the session for the report after tracking.
+    pub(crate) session: SessionLog,
     /// State machine of the detector.
-    detection_state: DetectionState,
+    pub(crate) detection_state: DetectionState,
     /// A svelte store that can be subscribed to for state updates.
     pub(crate) detection_state_store: Readable<DetectionState>,
     /// When the tracker entered the current state.
     pub(crate) detection_state_start: Timestamp,
+    /// Beat zero for the tracking after the current countdown, one subbeat
+    /// before the teacher starts.
+    countdown_beat_zero: Timestamp,
     /// The timestamp of the last skeleton evaluated, to avoid duplicated work on tick.
     pub(crate) last_evaluation: Timestamp,
     pub(crate) ui_events: UiEvents,
@@ -67,17 +106,19 @@ pub enum DetectionState {
     /// No longer tracking but the results of the previous tracking are
     /// available.
     TrackingDone = 6,
+    /// Interrupted by the user, the teacher and beat are frozen until resumed.
+    Paused = 7,
 }
 
 impl Default for DanceDetector {
     fn default() -> Self {
         Self {
-            bpm: 120.0,
-            error_threshold: 0.075,
+            tempo: TempoMap::default(),
+            config: DetectorConfig::default(),
             detected: DetectionResult::default(),
-            beat_alignment: None,
             beat_zero: None,
             force_beat: false,
+            adaptive: false,
             // This is what I measured on my desktop with my webcam by looking
             // at the timestamps of claps timed on the audio output. Basically,
             // I measured the remaining error in timing after I have considered
@@ -86,12 +127,24 @@ impl Default for DanceDetector {
             // now, it seems more stable to underestimate and then just use a
             // large tolerance.
             camera_input_delay: 50.0,
+            aspect_ratio: 1.0,
             detection_state: DetectionState::Init,
             detection_state_store: Readable::new(DetectionState::Init),
             detection_state_start: 0.0,
+            countdown_beat_zero: 0.0,
             last_evaluation: -0.1,
             ui_events: UiEvents::default(),
             on_beat_candidates: vec![],
+            step_start_subbeat: 0,
+            resume_subbeat: 0,
+            framing_hint: None,
+            threshold_factor: 1.0,
+            time_section: None,
+            loop_practice: None,
+            mastered_loop: None,
+            rep_counter: None,
+            bounce: None,
+            session: SessionLog::default(),
             teacher: Default::default(),
         }
     }
@@ -114,6 +167,7 @@ impl DanceDetector {
     pub(crate) fn new_from_teacher(teacher: Teacher) -> Self {
         Self {
             teacher,
+            config: crate::STATE.with_borrow(|state| state.detector_config.clone()),
             ..Default::default()
         }
     }
@@ -125,6 +179,17 @@ impl DanceDetector {
         self.detected.failure_reason = None;
         self.detected.pose_matches = 0;
         self.detected.pose_misses = 0;
+        self.session.clear();
+        self.resume_subbeat = 0;
+        self.threshold_factor = 1.0;
+        self.time_section = None;
+        self.mastered_loop = None;
+        if let Some(counter) = &mut self.rep_counter {
+            counter.clear();
+        }
+        if let Some(tracking) = &mut self.bounce {
+            tracking.clear();
+        }
         self.beat_zero = None;
         self.transition_to_state(DetectionState::Init, self.detection_state_start);
     }
@@ -135,15 +200,33 @@ impl DanceDetector {
         now: Timestamp,
         db: &TrackerDanceCollection,
         skeletons: &[Skeleton3d],
+        timestamps: &[Timestamp],
+        keypoints: &[Keypoints],
     ) -> DetectionResult {
+        if self.rep_counter.is_some() {
+            return self.tick_repetitions(now, db, skeletons.last());
+        }
         match self.detection_state {
             DetectionState::Init => {
                 self.transition_to_state(DetectionState::Positioning, now);
             }
             DetectionState::Positioning => {
-                if let Some((target, _)) = self.teacher.step_at_subbeat(0) {
-                    if let Some(skeleton) = skeletons.last() {
-                        let resting_pose_idx = if target.skeleton(0).sideway {
+                let sideway = match self.teacher.step_at_subbeat(self.resume_subbeat) {
+                    Some((target, _)) => Some(target.skeleton(0).sideway),
+                    // bounces are checked from the front
+                    None if self.bounce.is_some() => Some(false),
+                    None => None,
+                };
+                if let Some(sideway) = sideway {
+                    if let (Some(skeleton), Some(kp)) = (skeletons.last(), keypoints.last()) {
+                        // only check the pose once the dancer is placed well
+                        let hint = framing_hint(kp, skeleton, sideway, self.aspect_ratio);
+                        if let Some(new_hint) = hint.filter(|h| Some(*h) != self.framing_hint) {
+                            self.ui_events
+                                .add_text(now, new_hint.text().to_owned(), 2000.0);
+                        }
+                        self.framing_hint = hint;
+                        let resting_pose_idx = if sideway {
                             db.pose_by_id("standing-straight-side")
                                 .expect("missing resting pose")
                         } else {
@@ -152,20 +235,21 @@ impl DanceDetector {
                         };
                         let resting_pose = &db.poses()[resting_pose_idx];
                         let error_details = resting_pose.skeleton_error(skeleton);
-                        if error_details.error_score() < 0.075 {
+                        if hint.is_none()
+                            && error_details.error_score() < self.config.positioning_threshold
+                        {
                             self.transition_to_state(DetectionState::CountDown, now);
                         }
                     }
                 }
             }
             DetectionState::CountDown => {
-                let time_between_poses = self.subbeat_time();
-                if now > self.detection_state_start + (time_between_poses * 15.0).floor() {
-                    let beat_zero = self.next_subbeat_timestamp(now);
+                let beat_zero = self.countdown_beat_zero;
+                if now >= beat_zero {
                     self.beat_zero = Some(beat_zero);
                     // the change to the next state must happen BEFORE it
                     // actually starts, to give time to the animation
-                    let actual_start = beat_zero + time_between_poses;
+                    let actual_start = self.add_subbeats(beat_zero, 1.0);
                     self.transition_to_state(DetectionState::LiveTracking, actual_start);
                 }
             }
@@ -180,7 +264,13 @@ impl DanceDetector {
                 // Finish activity when the teacher is done.
                 self.last_evaluation = now;
                 let subbeat = self.subbeat(now);
-                if self.teacher.is_done(subbeat) {
+                if let Some((previous, section)) = self.section_change(subbeat) {
+                    if self.adaptive {
+                        self.adapt_difficulty(previous, section);
+                    }
+                    self.check_loop_mastery(previous, section);
+                }
+                if self.teacher.is_done(subbeat) || self.mastered_loop.is_some() {
                     self.transition_to_state(DetectionState::TrackingDone, now);
                 }
                 // Change state to "InstructorDemo" if there is currently no tracking going on.
@@ -188,8 +278,12 @@ impl DanceDetector {
                     self.transition_to_state(DetectionState::InstructorDemo, now);
                 }
 
+                if self.bounce.is_some() {
+                    self.detect_bounces(now, timestamps, keypoints);
+                    return self.detected.clone();
+                }
                 if let Some(skeleton) = skeletons.last() {
-                    return self.detect_next_pose(db, skeleton, now);
+                    return self.detect_next_pose(db, skeleton, now, timestamps, keypoints);
                 } else {
                     return self
                         .detected
@@ -203,7 +297,7 @@ impl DanceDetector {
                     self.transition_to_state(DetectionState::LiveTracking, now);
                 }
             }
-            DetectionState::TrackingDone => (),
+            DetectionState::TrackingDone | DetectionState::Paused => (),
         }
         self.detected
             .clone()
@@ -216,17 +310,18 @@ impl DanceDetector {
         db: &TrackerDanceCollection,
         skeleton: &Skeleton3d,
         pose_timestamp: Timestamp,
+        timestamps: &[Timestamp],
+        keypoints: &[Keypoints],
     ) -> DetectionResult {
         let prev_detection = &mut self.detected;
         let last_step = prev_detection
             .partial
             .as_ref()
             .or_else(|| prev_detection.steps.last());
-        let prev_t = last_step.map_or(0.0, |step| step.end);
+        let prev_t = last_step.map(|step| step.end);
 
         // skip at least a quarter beat
-        let min_delay = self.subbeat_time() / 2.0;
-        if pose_timestamp < prev_t + min_delay {
+        if pose_timestamp < self.add_subbeats(prev_t.unwrap_or(0.0), 0.5) {
             return self
                 .detected
                 .clone()
@@ -234,15 +329,14 @@ impl DanceDetector {
         }
 
         // check we are on beat, if aligned to beat
-        let time_delta = self.subbeat_time();
-        // let first_beat =
-        //     self.next_subbeat_timestamp(self.detection_state_start + (time_delta / 2.0));
         let first_beat = self.next_subbeat_timestamp(self.detection_state_start);
         let next_subbeat = self.recorded_subbeats();
 
+        let subbeats_since_start = next_subbeat.saturating_sub(self.resume_subbeat);
         let expected_next_pose_t =
-            first_beat + (next_subbeat as f64 * time_delta) + self.camera_input_delay;
-        if self.force_beat && pose_timestamp < expected_next_pose_t - self.beat_tolerance() {
+            self.add_subbeats(first_beat, subbeats_since_start as f64) + self.camera_input_delay;
+        let beat_tolerance = self.beat_tolerance(expected_next_pose_t);
+        if self.force_beat && pose_timestamp < expected_next_pose_t - beat_tolerance {
             return self
                 .detected
                 .clone()
@@ -251,8 +345,8 @@ impl DanceDetector {
         let cursor = self.teacher.cursor_at_subbeat(next_subbeat);
 
         // look up step data we expect to match
-        let step_id = match self.step(&cursor) {
-            Some(step_info) => step_info.id(),
+        let (step_id, step_name) = match self.step(&cursor) {
+            Some(step_info) => (step_info.id(), step_info.name()),
             None => {
                 // TODO: To support freestyle, this should match against any step
                 return self
@@ -270,36 +364,63 @@ impl DanceDetector {
         // might make more sense to compare with the original angles rather
         // than the normalized angles.
         let has_direction_error = !pose.direction.matches_direction(skeleton.direction());
-        let error_details = if has_direction_error && pose.direction == PoseDirection::Front {
+        let mut error_details = if has_direction_error && pose.direction == PoseDirection::Front {
             let original_angles = skeleton.original_angles();
             pose.error(&original_angles, skeleton.positions(), skeleton.direction())
         } else {
             pose.skeleton_error(skeleton)
         };
+        error_details.resolve_regions(db);
+
+        // Jumps are measured from the previous pose, or one pose duration
+        // for the first pose.
+        let jump = step.jump_heights[cursor.pose_index % step.jump_heights.len()]
+            .filter(|height| *height > 0.0)
+            .and_then(|expected| {
+                let start = prev_t.unwrap_or_else(|| {
+                    let duration = self.teacher.pose_duration(&cursor).unwrap_or(1);
+                    self.add_subbeats(pose_timestamp, -(duration as f64))
+                });
+                JumpScore::between(expected, timestamps, keypoints, start, pose_timestamp)
+            });
+        let missed_jump = jump.is_some_and(|jump| jump.error > self.config.jump_error_threshold);
 
         let error = error_details.error_score();
         let has_z_error = !error_details.z_order_errors.is_empty();
         let pose_approximation = PoseApproximation {
             id: db.pose_id(pose_idx).to_owned(),
-            name: db.pose_name(pose_idx).to_owned(),
+            name: db.pose_name(pose_idx),
             error,
             timestamp: pose_timestamp,
             error_details,
+            jump,
         };
-        if !has_z_error && error < self.error_threshold {
-            self.add_pose(pose_approximation);
+        let error_threshold =
+            self.config.pose_error_threshold(db.pose_id(pose_idx)) * self.threshold_factor;
+        let log_pose = |pose: &PoseApproximation, missed: bool| LoggedPose {
+            cursor: cursor.clone(),
+            step_id: step_id.clone(),
+            step_name: step_name.clone(),
+            pose: pose.clone(),
+            expected_timestamp: expected_next_pose_t,
+            missed,
+        };
+        if !has_z_error && error < error_threshold && !missed_jump {
+            self.session.add_pose(log_pose(&pose_approximation, false));
+            self.add_pose(pose_approximation, timestamps, keypoints);
             self.detected.last_error = None;
             self.detected.pose_matches += 1;
         } else {
             let hint = {
                 if has_z_error {
                     PoseHint::ZOrder
+                } else if error < error_threshold {
+                    PoseHint::JumpHigher
                 } else {
                     let left_right_pose = db.pose_left_right_switched(pose_idx);
                     let lr_error = left_right_pose.skeleton_error(skeleton);
                     let lr_error_score = lr_error.error_score();
-                    // TODO: fine-tune 0.5
-                    if lr_error_score < error * 0.5 {
+                    if lr_error_score < error * self.config.left_right_ratio {
                         PoseHint::LeftRight
                     } else if has_direction_error {
                         PoseHint::WrongDirection
@@ -313,7 +434,7 @@ impl DanceDetector {
             // smallest error in the tolerated range.
             if self.force_beat {
                 self.on_beat_candidates.push(pose_approximation.clone());
-                if pose_timestamp > expected_next_pose_t + self.beat_tolerance() {
+                if pose_timestamp > expected_next_pose_t + beat_tolerance {
                     let closest_fit = self
                         .on_beat_candidates
                         .drain(..)
@@ -321,10 +442,12 @@ impl DanceDetector {
                     // just added a pose above, min() can't be empty
                     let pose_approximation =
                         closest_fit.expect("on_beat_candidates shouldn't be empty");
-                    self.add_pose(pose_approximation);
+                    self.session.add_pose(log_pose(&pose_approximation, true));
+                    self.add_pose(pose_approximation, timestamps, keypoints);
                     self.detected.pose_misses += 1;
                 }
             }
+            self.session.add_hint(cursor.subbeat, hint);
             self.detected.last_error = Some((hint, pose_approximation));
         }
 
@@ -348,6 +471,7 @@ impl DanceDetector {
                 self.teacher.ui_view_at_subbeat(subbeat)
             }
             DetectionState::TrackingDone => TeacherView::Off,
+            DetectionState::Paused => TeacherView::UserCameraOnly,
         }
     }
 
@@ -356,17 +480,56 @@ impl DanceDetector {
         self.teacher.step_at_subbeat(subbeat)
     }
 
-    /// Calculate the subbeat since the current detection phase
+    /// Calculate the subbeat since the current detection phase, counting on
+    /// from where the last pause was.
+    ///
+    /// While paused, the subbeat stays where tracking will resume.
     pub(crate) fn subbeat(&self, t: f64) -> u32 {
-        self.timestamp_to_subbeat_from_zero(t)
-            .saturating_sub(self.timestamp_to_subbeat_from_zero(self.detection_state_start))
+        if matches!(self.detection_state, DetectionState::Paused) {
+            return self.resume_subbeat;
+        }
+        let elapsed = self
+            .timestamp_to_subbeat_from_zero(t)
+            .saturating_sub(self.timestamp_to_subbeat_from_zero(self.detection_state_start));
+        self.resume_subbeat + elapsed
+    }
+
+    /// Freeze the teacher at the next expected pose, until `resume` is called.
+    pub(crate) fn pause(&mut self, t: Timestamp) {
+        match self.detection_state {
+            DetectionState::CountDown
+            | DetectionState::LiveTracking
+            | DetectionState::InstructorDemo => (),
+            DetectionState::Init
+            | DetectionState::Positioning
+            | DetectionState::TrackingDone
+            | DetectionState::Paused => return,
+        }
+        self.resume_subbeat = self.detected.cursor.subbeat;
+        self.on_beat_candidates.clear();
+        self.detected.last_error = None;
+        self.ui_events.clear();
+        self.transition_to_state(DetectionState::Paused, t);
+    }
+
+    /// Continue a paused session with a positioning check and a countdown,
+    /// then track from the same cursor as before the pause.
+    pub(crate) fn resume(&mut self, t: Timestamp) {
+        if matches!(self.detection_state, DetectionState::Paused) {
+            self.transition_to_state(DetectionState::Positioning, t);
+        }
     }
 
     pub(crate) fn step(&self, cursor: &DanceCursor) -> Option<&StepInfo> {
         self.teacher.step(cursor)
     }
 
-    pub(crate) fn add_pose(&mut self, pose: PoseApproximation) {
+    pub(crate) fn add_pose(
+        &mut self,
+        pose: PoseApproximation,
+        timestamps: &[Timestamp],
+        keypoints: &[Keypoints],
+    ) {
         let pose_duration = self
             .teacher
             .pose_duration(&self.detected.cursor)
@@ -377,19 +540,53 @@ impl DanceDetector {
         let prev_subbeat = self.detected.cursor.subbeat;
         let new_subbeat = prev_subbeat + pose_duration;
 
+        if self.detected.partial.is_none() {
+            self.step_start_subbeat = prev_subbeat;
+        }
         self.detected.add_pose(pose);
 
         self.on_beat_candidates.clear();
+        let completed_steps = self.detected.steps.len();
         if let Some((target_step, _beat)) = &self.teacher.step_at_subbeat(prev_subbeat) {
             self.detected.match_step(target_step);
         }
+        if self.detected.steps.len() > completed_steps {
+            self.score_travel(prev_subbeat, timestamps, keypoints);
+        }
 
         let new_cursor = self.teacher.cursor_at_subbeat(new_subbeat);
         self.detected.cursor = new_cursor;
     }
 
+    /// Compare the hip movement during the step just completed with the last
+    /// pose at `last_subbeat` to the body shift the teacher expects.
+    fn score_travel(
+        &mut self,
+        last_subbeat: u32,
+        timestamps: &[Timestamp],
+        keypoints: &[Keypoints],
+    ) {
+        let start = self
+            .teacher
+            .pose_body_shift_at_subbeat(self.step_start_subbeat);
+        let end = self.teacher.pose_body_shift_at_subbeat(last_subbeat);
+        let threshold = self.config.travel_error_threshold;
+        if let Some(step) = self.detected.steps.last_mut() {
+            step.travel = TravelScore::between(
+                end.x - start.x,
+                self.aspect_ratio,
+                timestamps,
+                keypoints,
+                step.start,
+                step.end,
+            );
+            step.travel_hint = step.travel.and_then(|travel| travel.hint(threshold));
+        }
+    }
+
     pub(crate) fn transition_to_state(&mut self, state: DetectionState, t: Timestamp) {
         self.detection_state = state;
+        self.framing_hint = None;
         self.detection_state_start = t;
         self.detection_state_store.set(state);
 
@@ -397,82 +594,164 @@ impl DanceDetector {
             DetectionState::Init => (),
             DetectionState::Positioning => (),
             DetectionState::CountDown => {
-                self.emit_countdown_audio(t + self.start_countdown_delay())
+                // after a pause, the intro is already over
+                let intro = self
+                    .teacher
+                    .subbeats_before_tracking()
+                    .saturating_sub(self.resume_subbeat) as f64;
+                let start = self.countdown_start(self.add_subbeats(t, intro));
+                self.emit_countdown_audio(start);
+                // The first tracked pose is due two bars after the first
+                // count. The teacher starts one subbeat after beat zero, with
+                // the intro.
+                let bar_len = self.meter().subbeats_per_bar() as f64;
+                self.countdown_beat_zero = self.add_subbeats(start, 2.0 * bar_len - intro - 1.0);
             }
             DetectionState::LiveTracking => (),
             DetectionState::InstructorDemo => (),
             DetectionState::TrackingDone => (),
+            DetectionState::Paused => (),
+        }
+    }
+
+    pub(crate) fn meter(&self) -> &Meter {
+        self.teacher.meter()
+    }
+
+    /// Fractional number of subbeats since the first beat.
+    fn subbeat_at(&self, t: Timestamp) -> f64 {
+        self.meter().subbeat_at_beat(self.tempo.beat_at(t))
+    }
+
+    fn timestamp_at_subbeat(&self, subbeat: f64) -> Timestamp {
+        self.tempo
+            .timestamp_at(self.meter().beat_at_subbeat(subbeat))
+    }
+
+    /// The timestamp `subbeats` after `t`, following tempo changes and swing.
+    pub(crate) fn add_subbeats(&self, t: Timestamp, subbeats: f64) -> Timestamp {
+        let meter = self.meter();
+        if meter.swing() == 0.5 {
+            self.tempo
+                .add_beats(t, subbeats / meter.subdivisions() as f64)
+        } else {
+            self.timestamp_at_subbeat(self.subbeat_at(t) + subbeats)
+        }
+    }
+
+    fn subbeats_between(&self, start: Timestamp, end: Timestamp) -> f64 {
+        let meter = self.meter();
+        if meter.swing() == 0.5 {
+            self.tempo.beats_between(start, end) * meter.subdivisions() as f64
+        } else {
+            self.subbeat_at(end) - self.subbeat_at(start)
         }
     }
 
-    pub(crate) fn subbeat_time(&self) -> f64 {
-        30_000.0 / self.bpm as f64
+    /// Duration of the subbeat around `t`.
+    pub(crate) fn subbeat_time(&self, t: Timestamp) -> f64 {
+        let meter = self.meter();
+        if meter.swing() == 0.5 {
+            self.tempo.beat_duration_at(t) / meter.subdivisions() as f64
+        } else {
+            let subbeat = self.subbeat_at(t).floor();
+            self.timestamp_at_subbeat(subbeat + 1.0) - self.timestamp_at_subbeat(subbeat)
+        }
     }
 
-    /// How much time before or after the actual beat a pose can be to be
-    /// considered on beat
-    pub(crate) fn beat_tolerance(&self) -> f64 {
+    /// How much time before or after the actual beat at `t` a pose can be to
+    /// be considered on beat
+    pub(crate) fn beat_tolerance(&self, t: Timestamp) -> f64 {
         // TODO: vary this by current pace
         // (This is somewhat high to make it easier to get good scores)
-        self.subbeat_time() * 1.5
+        self.subbeat_time(t) * 1.5
     }
 
     pub(crate) fn recorded_subbeats(&self) -> u32 {
         self.detected.cursor.subbeat
     }
 
-    fn start_countdown_delay(&self) -> Timestamp {
-        self.teacher.subbeats_before_tracking() as f64 * self.subbeat_time()
+    /// The section that ended and the section that started, if the teacher
+    /// moved on since the last call.
+    pub(crate) fn section_change(&mut self, subbeat: u32) -> Option<(usize, usize)> {
+        let section = self.teacher.cursor_at_subbeat(subbeat).section_index;
+        let previous = self.time_section.replace(section)?;
+        (previous < section).then_some((previous, section))
     }
 
     /// Attention: This is not from the start of tracking but from the beat alignment
     fn timestamp_to_subbeat_from_zero(&self, t: Timestamp) -> u32 {
-        let t0 = self.beat_zero.unwrap_or(
-            self.beat_alignment
-                .unwrap_or(self.next_subbeat_timestamp(0.0)),
-        );
-        let pose_duration = self.subbeat_time();
-        ((t - t0) / pose_duration).floor().max(0.0) as u32
+        let t0 = self.beat_zero.unwrap_or(self.tempo.first_beat());
+        self.subbeats_between(t0, t).floor().max(0.0) as u32
     }
 
     pub(crate) fn next_subbeat_timestamp(&self, not_before: Timestamp) -> Timestamp {
-        let t0 = self.beat_alignment.unwrap_or(0.0);
-        let subbeat_duration = self.subbeat_time();
-        let subbeats = ((not_before - t0) / subbeat_duration).ceil();
-        t0 + subbeats * subbeat_duration
+        self.timestamp_at_subbeat(self.subbeat_at(not_before).ceil())
     }
 
-    pub(crate) fn emit_countdown_audio(&mut self, not_before: Timestamp) {
-        let beat = self.subbeat_time();
-        let next_beat = self.next_subbeat_timestamp(not_before);
-        // long enough to not clear too early
-        let text_dur = 8.0 * beat;
-
-        self.ui_events.add_audio(next_beat, "and".to_owned());
-
-        self.ui_events.add_audio(next_beat + beat, "one".to_owned());
-        self.ui_events
-            .add_text(next_beat + beat, "4".to_owned(), text_dur);
+    /// How long the tracked activity takes, following the tempo from the
+    /// start of tracking or the first beat.
+    pub(crate) fn tracked_duration(&self) -> f64 {
+        let t0 = self.beat_zero.unwrap_or(self.tempo.first_beat());
+        let subbeats = self.teacher.tracked_subbeats() as f64;
+        self.add_subbeats(t0, subbeats) - t0
+    }
 
-        self.ui_events
-            .add_audio(next_beat + 5.0 * beat, "two".to_owned());
-        self.ui_events
-            .add_text(next_beat + 5.0 * beat, "3".to_owned(), text_dur);
+    /// The first count of a countdown, on the first full beat after
+    /// `not_before` that leaves room for an "and" on the subbeat before.
+    fn countdown_start(&self, not_before: Timestamp) -> Timestamp {
+        let beat_len = self.meter().subdivisions() as f64;
+        let earliest = self.subbeat_at(not_before).ceil() + 1.0;
+        self.timestamp_at_subbeat((earliest / beat_len).ceil() * beat_len)
+    }
 
-        self.ui_events
-            .add_audio(next_beat + 9.0 * beat, "one".to_owned());
-        self.ui_events
-            .add_text(next_beat + 9.0 * beat, "2".to_owned(), text_dur);
+    /// Count in over two bars from `start`, with an "and" on the subbeat
+    /// before.
+    ///
+    /// The first bar counts half bars ("one", "two") if possible, the second
+    /// bar counts every beat. Text counts down on half bars, or on bars for an
+    /// odd number of beats per bar.
+    pub(crate) fn emit_countdown_audio(&mut self, start: Timestamp) {
+        const COUNTS: [&str; 4] = ["one", "two", "three", "four"];
+        let meter = *self.meter();
+        let beat_len = meter.subdivisions() as f64;
+        let bar_len = meter.subbeats_per_bar() as f64;
+        let half_bar = (meter.beats_per_bar() % 2 == 0).then_some(bar_len / 2.0);
+
+        let and = self.add_subbeats(start, -1.0);
+        let at = |subbeats: f64| self.add_subbeats(start, subbeats);
+        let mut audio = vec![(and, "and"), (start, COUNTS[0])];
+        if let Some(half_bar) = half_bar {
+            audio.push((at(half_bar), COUNTS[1]));
+        }
+        for (beat, count) in COUNTS
+            .iter()
+            .take(meter.beats_per_bar() as usize)
+            .enumerate()
+        {
+            audio.push((at(bar_len + beat as f64 * beat_len), count));
+        }
 
-        self.ui_events
-            .add_audio(next_beat + 11.0 * beat, "two".to_owned());
-        self.ui_events
-            .add_audio(next_beat + 13.0 * beat, "three".to_owned());
-        self.ui_events
-            .add_text(next_beat + 13.0 * beat, "1".to_owned(), beat);
+        let text_step = half_bar.unwrap_or(bar_len);
+        let num_texts = (2.0 * bar_len / text_step) as u32;
+        let texts: Vec<_> = (0..num_texts)
+            .map(|i| (at(i as f64 * text_step), num_texts - i))
+            .collect();
+        // long enough to not clear too early
+        let text_dur = bar_len * self.subbeat_time(start);
+        let last_text_dur = self.subbeat_time(texts[texts.len() - 1].0);
 
-        self.ui_events
-            .add_audio(next_beat + 15.0 * beat, "four".to_owned());
+        for (t, sound) in audio {
+            self.ui_events.add_audio(t, sound.to_owned());
+        }
+        for (i, (t, count)) in texts.iter().enumerate() {
+            let duration = if i + 1 == texts.len() {
+                last_text_dur
+            } else {
+                text_dur
+            };
+            self.ui_events.add_text(*t, count.to_string(), duration);
+        }
     }
 
     /// For debugging pruposes, set the state directly.
@@ -480,3 +759,167 @@ impl DanceDetector {
         self.transition_to_state(state, t);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn check_countdown(meter: Meter, expected: &[(f64, &str)], expected_texts: &[(f64, &str)]) {
+        let mut detector = DanceDetector {
+            tempo: TempoMap::Constant {
+                bpm: 60.0,
+                first_beat: 0.0,
+            },
+            ..Default::default()
+        };
+        detector.teacher.set_meter(meter);
+        detector.transition_to_state(DetectionState::CountDown, 0.0);
+
+        let mut audio = vec![];
+        while let Some(effect) = detector.ui_events.next_audio() {
+            audio.push((effect.timestamp.round(), effect.sound_id()));
+        }
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(t, sound)| (*t, sound.to_string()))
+            .collect();
+        assert_eq!(audio, expected);
+
+        let mut texts = vec![];
+        while let Some(effect) = detector.ui_events.next_text(f64::INFINITY) {
+            texts.push((effect.timestamp.round(), effect.text()));
+        }
+        let expected_texts: Vec<_> = expected_texts
+            .iter()
+            .map(|(t, text)| (*t, text.to_string()))
+            .collect();
+        assert_eq!(texts, expected_texts);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let mut detector = DanceDetector {
+            tempo: TempoMap::Constant {
+                bpm: 60.0,
+                first_beat: 0.0,
+            },
+            ..Default::default()
+        };
+        detector.teacher.add_freestyle(16);
+        detector.transition_to_state(DetectionState::LiveTracking, 0.0);
+        detector.detected.cursor = detector.teacher.cursor_at_subbeat(6);
+        assert_eq!(detector.subbeat(3500.0), 7);
+
+        detector.pause(3500.0);
+        assert!(matches!(detector.detection_state, DetectionState::Paused));
+        assert_eq!(detector.subbeat(3500.0), 6);
+        assert_eq!(detector.subbeat(60_000.0), 6);
+
+        detector.resume(60_000.0);
+        assert!(matches!(
+            detector.detection_state,
+            DetectionState::Positioning
+        ));
+        // skip the positioning check, which needs a skeleton
+        detector.transition_to_state(DetectionState::CountDown, 60_000.0);
+        let first_audio = detector.ui_events.next_audio().unwrap();
+        assert_eq!(first_audio.timestamp.round(), 60_500.0);
+
+        let db = TrackerDanceCollection::default();
+        let mut now = 60_000.0;
+        while !matches!(detector.detection_state, DetectionState::LiveTracking) {
+            now += 100.0;
+            detector.tick(now, &db, &[], &[], &[]);
+            assert!(now < 70_000.0, "countdown did not end");
+        }
+        // continue at the pose before the pause
+        let start = detector.detection_state_start;
+        assert_eq!(detector.subbeat(start), 6);
+        assert_eq!(detector.subbeat(start + 1000.0), 8);
+    }
+
+    #[test]
+    fn test_countdown_common_time() {
+        check_countdown(
+            Meter::default(),
+            &[
+                (500.0, "and"),
+                (1000.0, "one"),
+                (3000.0, "two"),
+                (5000.0, "one"),
+                (6000.0, "two"),
+                (7000.0, "three"),
+                (8000.0, "four"),
+            ],
+            &[(1000.0, "4"), (3000.0, "3"), (5000.0, "2"), (7000.0, "1")],
+        );
+    }
+
+    /// However long positioning took, tracking starts exactly two bars after
+    /// the first count.
+    #[test]
+    fn test_countdown_not_on_beat() {
+        for t0 in [0.0, 500.0, 700.0] {
+            let mut detector = DanceDetector {
+                tempo: TempoMap::Constant {
+                    bpm: 60.0,
+                    first_beat: 0.0,
+                },
+                ..Default::default()
+            };
+            detector.teacher.add_freestyle(16);
+            detector.transition_to_state(DetectionState::CountDown, t0);
+            let one = loop {
+                let effect = detector.ui_events.next_audio().unwrap();
+                if effect.sound_id() == "one" {
+                    break effect.timestamp;
+                }
+            };
+            assert_eq!(one % 1000.0, 0.0, "first count off beat at t0={t0}");
+
+            let db = TrackerDanceCollection::default();
+            let mut now = t0;
+            while !matches!(detector.detection_state, DetectionState::LiveTracking) {
+                now += 100.0;
+                detector.tick(now, &db, &[], &[], &[]);
+            }
+            let first_pose = detector.detection_state_start;
+            assert_eq!(first_pose - one, 8000.0, "t0={t0}");
+            assert_eq!(detector.subbeat(first_pose), 0);
+        }
+    }
+
+    #[test]
+    fn test_countdown_waltz() {
+        check_countdown(
+            Meter::new(3, 3, 0.5).unwrap(),
+            &[
+                (667.0, "and"),
+                (1000.0, "one"),
+                (4000.0, "one"),
+                (5000.0, "two"),
+                (6000.0, "three"),
+            ],
+            &[(1000.0, "2"), (4000.0, "1")],
+        );
+    }
+
+    #[test]
+    fn test_countdown_swing() {
+        // the "and" is late, at 2/3 of the beat before
+        check_countdown(
+            Meter::new(4, 2, 2.0 / 3.0).unwrap(),
+            &[
+                (667.0, "and"),
+                (1000.0, "one"),
+                (3000.0, "two"),
+                (5000.0, "one"),
+                (6000.0, "two"),
+                (7000.0, "three"),
+                (8000.0, "four"),
+            ],
+            &[(1000.0, "4"), (3000.0, "3"), (5000.0, "2"), (7000.0, "1")],
+        );
+    }
+}
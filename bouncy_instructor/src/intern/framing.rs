@@ -0,0 +1,199 @@
+//! Checking how the dancer is placed in the camera frame before tracking.
+//!
+//! Keypoints are in image coordinates, where the frame goes from 0.0 to 1.0
+//! in both directions. Distance and centering checks are relative to the frame
+//! size on each axis. Angles in the image depend on the aspect ratio, which
+//! the tilt check corrects for.
+
+use super::skeleton_3d::{Direction, Skeleton3d};
+use crate::tracker::FramingHint;
+use crate::Keypoints;
+
+/// Shoulder to ankle height above which the head or feet get cut off.
+const MAX_BODY_HEIGHT: f32 = 0.75;
+/// Shoulder to ankle height below which the dancer is too small to track well.
+const MIN_BODY_HEIGHT: f32 = 0.3;
+/// How far the hips may be from the middle of the frame.
+const MAX_CENTER_OFFSET: f32 = 0.25;
+/// How far the body may lean in the image, in degrees, before blaming the
+/// camera.
+const MAX_TILT: f32 = 15.0;
+
+/// The most important correction to the placement of the dancer, or `None`
+/// if the dancer is placed well for tracking.
+///
+/// `sideway` says whether the first tracked pose is seen from the side.
+/// `aspect_ratio` is the width divided by the height of the image.
+pub(crate) fn framing_hint(
+    kp: &Keypoints,
+    skeleton: &Skeleton3d,
+    sideway: bool,
+    aspect_ratio: f32,
+) -> Option<FramingHint> {
+    let points = [kp.left, kp.right].into_iter().flat_map(|side| {
+        [
+            side.shoulder,
+            side.hip,
+            side.knee,
+            side.ankle,
+            side.heel,
+            side.toes,
+        ]
+    });
+    let (top, bottom) = points.fold((f32::INFINITY, f32::NEG_INFINITY), |(top, bottom), p| {
+        (top.min(p.y), bottom.max(p.y))
+    });
+    let body_height = bottom - top;
+
+    if !kp.fully_visible {
+        if body_height >= MAX_BODY_HEIGHT {
+            return Some(FramingHint::TooClose);
+        }
+        return Some(FramingHint::NotFullyVisible);
+    }
+    if body_height > MAX_BODY_HEIGHT {
+        return Some(FramingHint::TooClose);
+    }
+    if body_height < MIN_BODY_HEIGHT {
+        return Some(FramingHint::TooFar);
+    }
+
+    let hips_x = (kp.left.hip.x + kp.right.hip.x) / 2.0;
+    if hips_x > 0.5 + MAX_CENTER_OFFSET {
+        return Some(FramingHint::MoveLeft);
+    }
+    if hips_x < 0.5 - MAX_CENTER_OFFSET {
+        return Some(FramingHint::MoveRight);
+    }
+
+    let shoulders = (kp.left.shoulder + kp.right.shoulder) * 0.5;
+    let ankles = (kp.left.ankle + kp.right.ankle) * 0.5;
+    let tilt = ((shoulders.x - ankles.x) * aspect_ratio).atan2(ankles.y - shoulders.y);
+    if tilt.to_degrees().abs() > MAX_TILT {
+        return Some(FramingHint::CameraTilted);
+    }
+
+    match (skeleton.direction(), sideway) {
+        (Direction::North | Direction::South, true) => Some(FramingHint::TurnSideways),
+        (Direction::East | Direction::West, false) => Some(FramingHint::FaceCamera),
+        _ => None,
+    }
+}
+
+impl FramingHint {
+    /// Short instruction to show to the dancer.
+    ///
+    /// Directions are mirrored from the camera's view to the dancer's view,
+    /// assuming the dancer faces the camera. Moving to the left of the camera
+    /// is moving to the right of the dancer.
+    pub(crate) fn text(&self) -> &'static str {
+        match self {
+            FramingHint::NotFullyVisible => "Show your whole body",
+            FramingHint::TooClose => "Step back",
+            FramingHint::TooFar => "Come closer",
+            FramingHint::MoveLeft => "Move to your right",
+            FramingHint::MoveRight => "Move to your left",
+            FramingHint::CameraTilted => "Hold the camera straight",
+            FramingHint::FaceCamera => "Face the camera",
+            FramingHint::TurnSideways => "Turn sideways",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intern::tracker_dance_collection::TrackerDanceCollection;
+    use crate::test_utils::standing_keypoints;
+
+    #[track_caller]
+    fn check(kp: Keypoints, expected: Option<FramingHint>) {
+        check_framing(kp, false, 1.0, expected);
+    }
+
+    #[track_caller]
+    fn check_framing(
+        kp: Keypoints,
+        sideway: bool,
+        aspect_ratio: f32,
+        expected: Option<FramingHint>,
+    ) {
+        let db = TrackerDanceCollection::default();
+        let skeleton = Skeleton3d::from_keypoints(&kp, &db);
+        assert_eq!(
+            framing_hint(&kp, &skeleton, sideway, aspect_ratio),
+            expected
+        );
+    }
+
+    /// Seen from the side, the left and right body points overlap in the
+    /// image and differ in depth.
+    fn sideways(kp: Keypoints) -> Keypoints {
+        let mut out = kp;
+        for (left, right) in [
+            (&mut out.left.shoulder, kp.right.shoulder),
+            (&mut out.left.hip, kp.right.hip),
+        ] {
+            left.x = right.x;
+            left.z = 0.1;
+        }
+        out
+    }
+
+    #[test]
+    fn test_well_placed() {
+        check(standing_keypoints(0.5, 0.5, 0.6), None);
+    }
+
+    #[test]
+    fn test_distance() {
+        check(
+            standing_keypoints(0.5, 0.5, 0.9),
+            Some(FramingHint::TooClose),
+        );
+        check(standing_keypoints(0.5, 0.5, 0.2), Some(FramingHint::TooFar));
+        let mut cut_off = standing_keypoints(0.5, 0.7, 0.9);
+        cut_off.fully_visible = false;
+        check(cut_off, Some(FramingHint::TooClose));
+        let mut outside = standing_keypoints(0.9, 0.5, 0.5);
+        outside.fully_visible = false;
+        check(outside, Some(FramingHint::NotFullyVisible));
+    }
+
+    #[test]
+    fn test_centering() {
+        check(
+            standing_keypoints(0.8, 0.5, 0.6),
+            Some(FramingHint::MoveLeft),
+        );
+        check(
+            standing_keypoints(0.2, 0.5, 0.6),
+            Some(FramingHint::MoveRight),
+        );
+        // standing on the right of the camera image, facing it, means the
+        // dancer stands too far to their left
+        assert_eq!(FramingHint::MoveLeft.text(), "Move to your right");
+        assert_eq!(FramingHint::MoveRight.text(), "Move to your left");
+    }
+
+    #[test]
+    fn test_tilt() {
+        let mut kp = standing_keypoints(0.5, 0.5, 0.6);
+        kp.left.shoulder.x += 0.2;
+        kp.right.shoulder.x += 0.2;
+        check(kp, Some(FramingHint::CameraTilted));
+
+        // in a portrait video, the same offset on x is a smaller distance
+        check_framing(kp, false, 9.0 / 16.0, None);
+    }
+
+    #[test]
+    fn test_direction() {
+        let front = standing_keypoints(0.5, 0.5, 0.6);
+        check_framing(front, true, 1.0, Some(FramingHint::TurnSideways));
+
+        let side = sideways(front);
+        check_framing(side, false, 1.0, Some(FramingHint::FaceCamera));
+        check_framing(side, true, 1.0, None);
+    }
+}
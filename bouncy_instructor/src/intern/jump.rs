@@ -0,0 +1,139 @@
+//! Measuring how high a dancer jumps into a pose.
+//!
+//! Steps define jump heights per keyframe, in units the avatar animation uses.
+//! To compare, keypoints between two poses are checked for how far the body
+//! rises above the ground, relative to the body height.
+
+use crate::Keypoints;
+
+type Timestamp = f64;
+
+/// Body height share of one unit of a step's jump height, the same factor the
+/// avatar animation uses.
+const JUMP_HEIGHT_UNIT: f32 = 0.025;
+
+/// How well the dancer jumped into a pose.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct JumpScore {
+    /// Jump height defined in the step.
+    pub expected: f32,
+    /// Jump height of the dancer, in the same units as `expected`.
+    pub measured: f32,
+    /// Between 0.0 and 1.0, how much lower than expected the jump was.
+    pub error: f32,
+}
+
+impl JumpScore {
+    /// Jumping higher than expected is not an error, only jumps that fall
+    /// short count.
+    pub(crate) fn new(expected: f32, measured: f32) -> Self {
+        let error = if expected > 0.0 {
+            (1.0 - measured / expected).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        Self {
+            expected,
+            measured,
+            error,
+        }
+    }
+
+    /// Score the frames between `start` and `end` against an expected jump
+    /// height, or `None` if there is nothing to measure.
+    pub(crate) fn between(
+        expected: f32,
+        timestamps: &[Timestamp],
+        keypoints: &[Keypoints],
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Option<Self> {
+        let first = timestamps.partition_point(|t| *t < start);
+        let last = timestamps.partition_point(|t| *t <= end);
+        let measured = measured_jump(keypoints.get(first..last)?)?;
+        Some(Self::new(expected, measured))
+    }
+}
+
+/// How high the body rose between the first and the last frame, in step jump
+/// height units.
+///
+/// The ground is where the lower foot is in the first and last frame. Both the
+/// hips and the lower foot must rise, which rules out kicks and standing up
+/// from a squat.
+///
+/// Returns `None` with less than two frames or an invisible body.
+pub(crate) fn measured_jump(keypoints: &[Keypoints]) -> Option<f32> {
+    let (first, last) = (keypoints.first()?, keypoints.last()?);
+    if keypoints.len() < 2 {
+        return None;
+    }
+    // y grows downwards
+    let lower_foot = |kp: &Keypoints| kp.left.ankle.y.max(kp.right.ankle.y);
+    let hips = |kp: &Keypoints| (kp.left.hip.y + kp.right.hip.y) / 2.0;
+    let shoulders = |kp: &Keypoints| (kp.left.shoulder.y + kp.right.shoulder.y) / 2.0;
+
+    let body_height = keypoints
+        .iter()
+        .map(|kp| lower_foot(kp) - shoulders(kp))
+        .sum::<f32>()
+        / keypoints.len() as f32;
+    if body_height <= 0.0 {
+        return None;
+    }
+
+    let rise = |point: &dyn Fn(&Keypoints) -> f32| {
+        let ground = (point(first) + point(last)) / 2.0;
+        let highest = keypoints.iter().map(point).fold(f32::INFINITY, f32::min);
+        ground - highest
+    };
+    let rise = rise(&lower_foot).min(rise(&hips)).max(0.0);
+    Some(rise / body_height / JUMP_HEIGHT_UNIT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::standing_keypoints;
+
+    /// Standing keypoints with a body height of 1.0, lifted by `lift`.
+    fn standing(lift: f32) -> Keypoints {
+        standing_keypoints(0.0, 0.5 - lift, 1.0)
+    }
+
+    #[test]
+    fn test_measured_jump() {
+        let frames = [0.0, 0.025, 0.05, 0.025, 0.0].map(standing);
+        let measured = measured_jump(&frames).unwrap();
+        assert!((measured - 2.0).abs() < 1e-4, "{measured}");
+
+        let score = JumpScore::new(2.0, measured);
+        assert!(score.error < 1e-4, "{score:?}");
+        assert_eq!(JumpScore::new(2.0, 0.5).error, 0.75);
+        assert_eq!(JumpScore::new(1.0, 3.0).error, 0.0);
+    }
+
+    #[test]
+    fn test_jump_between() {
+        let frames = [0.0, 0.0, 0.05, 0.0].map(standing);
+        let timestamps = [0.0, 100.0, 200.0, 300.0];
+        let score = JumpScore::between(2.0, &timestamps, &frames, 100.0, 300.0).unwrap();
+        assert!(score.error < 1e-4, "{score:?}");
+        // keypoints can lag behind timestamps, frames out of range are unknown
+        let score = JumpScore::between(2.0, &timestamps, &frames[..2], 100.0, 300.0);
+        assert_eq!(score, None);
+    }
+
+    #[test]
+    fn test_kick_is_no_jump() {
+        let mut frames = [0.0; 3].map(standing);
+        frames[1].right.ankle.y -= 0.3;
+        assert_eq!(measured_jump(&frames), Some(0.0));
+    }
+
+    #[test]
+    fn test_no_frames() {
+        assert_eq!(measured_jump(&[]), None);
+        assert_eq!(measured_jump(&[standing(0.0)]), None);
+    }
+}
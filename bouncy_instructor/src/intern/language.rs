@@ -0,0 +1,42 @@
+//! The display language, shared between a collection and everything that was
+//! loaded from it.
+
+use crate::parsing::course_file::TranslatedString;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Handle to the language in which translated strings are displayed.
+///
+/// Clones share the same underlying value. Collections hand out clones to the
+/// steps, poses and lessons they load, which resolve their translations only
+/// when they are read. Hence, changing the language on a collection takes
+/// effect immediately on all existing objects, without reloading anything.
+#[derive(Clone, Debug)]
+pub(crate) struct Language(Rc<RefCell<String>>);
+
+impl Language {
+    pub(crate) fn new(lang: String) -> Self {
+        Self(Rc::new(RefCell::new(lang)))
+    }
+
+    pub(crate) fn set(&self, lang: String) {
+        *self.0.borrow_mut() = lang;
+    }
+
+    /// Pick the translation for the current language, with the fallbacks of
+    /// [`TranslatedString::get`].
+    pub(crate) fn translate(&self, translations: &TranslatedString) -> Option<String> {
+        translations.get(&self.0.borrow())
+    }
+
+    /// Like [`Language::translate`] but for optional translations.
+    pub(crate) fn translate_opt(&self, translations: Option<&TranslatedString>) -> Option<String> {
+        translations.and_then(|t| self.translate(t))
+    }
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Self::new("en".to_owned())
+    }
+}
@@ -0,0 +1,299 @@
+//! Repeating a segment of a dance or lesson until the dancer gets it.
+//!
+//! Each loop adds the same steps to the teacher again, so a loop is a fixed
+//! number of consecutive teacher sections. The detector scores every loop
+//! when the teacher moves on to the next one.
+
+use super::dance_detector::DanceDetector;
+use super::step_pace::StepPace;
+use super::teacher::Teacher;
+use crate::{LoopConfig, Meter, StepInfo};
+use std::ops::Range;
+
+/// How many loops it takes with a tempo ramp until the target pace is
+/// reached, each loop before that is twice as slow as the next.
+const RAMP_LOOPS: u32 = 2;
+
+/// A step of the looped segment, as it is danced at the target tempo.
+pub(crate) struct LoopPart {
+    pub step: StepInfo,
+    pub repeat: u32,
+    pub pace: StepPace,
+    /// False for steps the teacher only shows.
+    pub tracked: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LoopPractice {
+    sections_per_loop: usize,
+    /// Stop after a loop with at least this share of matched poses.
+    mastery_hit_rate: Option<f32>,
+}
+
+impl LoopPractice {
+    /// A teacher dancing `parts` in a loop, as configured.
+    ///
+    /// Returns `None` if there is nothing to loop.
+    pub(crate) fn teacher(
+        parts: &[LoopPart],
+        config: &LoopConfig,
+        meter: Meter,
+    ) -> Option<(Teacher, LoopPractice)> {
+        if parts.is_empty() || config.repetitions == 0 {
+            return None;
+        }
+        let mut teacher = Teacher::default();
+        teacher.set_meter(meter);
+        for loop_index in 0..config.repetitions {
+            for part in parts {
+                let pace = if config.tempo_ramp {
+                    ramped_pace(part.pace, loop_index)
+                } else {
+                    part.pace
+                };
+                if part.tracked {
+                    teacher.add_step(part.step.clone(), part.repeat, pace);
+                } else {
+                    teacher.show_step(part.step.clone(), part.repeat, pace);
+                }
+            }
+        }
+        let practice = LoopPractice {
+            sections_per_loop: parts.len(),
+            mastery_hit_rate: config.mastery_hit_rate,
+        };
+        Some((teacher, practice))
+    }
+
+    /// Sections of the loop that ended when the teacher moved from section
+    /// `previous` to `section`, if any.
+    pub(crate) fn finished_loop(&self, previous: usize, section: usize) -> Option<Range<usize>> {
+        let loop_index = previous / self.sections_per_loop;
+        (loop_index < section / self.sections_per_loop).then(|| {
+            let start = loop_index * self.sections_per_loop;
+            start..start + self.sections_per_loop
+        })
+    }
+
+    /// Which loop a section belongs to, starting at 0.
+    pub(crate) fn loop_index(&self, section: usize) -> u32 {
+        (section / self.sections_per_loop) as u32
+    }
+}
+
+impl DanceDetector {
+    /// Stop once the dancer matched enough poses in a loop that just ended.
+    pub(crate) fn check_loop_mastery(&mut self, previous: usize, section: usize) {
+        let Some(practice) = self.loop_practice else {
+            return;
+        };
+        let (Some(mastery), Some(sections)) = (
+            practice.mastery_hit_rate,
+            practice.finished_loop(previous, section),
+        ) else {
+            return;
+        };
+        let mut expected = 0;
+        let mut matched = 0;
+        for section_index in sections.clone() {
+            if let Some(poses) = self.teacher.tracked_poses_in_section(section_index) {
+                expected += poses;
+                matched += self.session.matched_poses(section_index);
+            }
+        }
+        if expected > 0 && matched as f32 / expected as f32 >= mastery {
+            self.mastered_loop = Some(practice.loop_index(sections.start));
+        }
+    }
+}
+
+/// The pace of a loop with a tempo ramp, slower in the first loops.
+fn ramped_pace(target: StepPace, loop_index: u32) -> StepPace {
+    let mut pace = target;
+    for _ in loop_index..RAMP_LOOPS {
+        match pace.slower() {
+            Some(slower) => pace = slower,
+            None => break,
+        }
+    }
+    pace
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intern::dance_detector::DetectionState;
+    use crate::keypoints::Cartesian3d;
+    use crate::{load_pose_str, load_step_str, Keypoints, Tracker};
+    use std::rc::Rc;
+
+    /// A step swinging the left shin out and back, looped twice per loop.
+    fn swing_loop(config: &LoopConfig) -> Tracker {
+        load_pose_str(
+            r#"
+            (
+              version: 0,
+              poses: [
+                (
+                  name: "shin-down",
+                  direction: Front,
+                  limbs: [(limb: LeftShin, angle: 0, tolerance: 5, weight: 1.0)],
+                ),
+                (
+                  name: "shin-out",
+                  direction: Front,
+                  limbs: [(limb: LeftShin, angle: 90, tolerance: 5, weight: 1.0)],
+                ),
+              ],
+            )"#,
+        )
+        .unwrap();
+        load_step_str(
+            r#"
+            (
+              version: 0,
+              steps: [
+                (
+                  id: "swing",
+                  name: "Swing",
+                  keyframes: [
+                    (pose: "shin-down", orientation: ToCamera),
+                    (pose: "shin-out", orientation: ToCamera),
+                  ],
+                ),
+              ],
+            )"#,
+            "test".to_owned(),
+        )
+        .unwrap();
+        let db = crate::STATE.with_borrow(|state| Rc::clone(&state.global_db.tracker_view));
+        let part = || LoopPart {
+            step: StepInfo::from_step(db.step("swing").unwrap().clone(), &db),
+            repeat: 1,
+            pace: StepPace::normal(),
+            tracked: true,
+        };
+        let parts = [part(), part()];
+        Tracker::new_loop(db, &parts, config, Meter::default()).unwrap()
+    }
+
+    /// Facing the camera, the left shin rotated outwards by `degree`.
+    fn shin(degree: f32) -> Keypoints {
+        let mut kp = Keypoints::default();
+        kp.left.shoulder = Cartesian3d::new(1.0, -2.0, 0.0);
+        kp.right.shoulder = Cartesian3d::new(-1.0, -2.0, 0.0);
+        kp.left.hip = Cartesian3d::new(1.0, -1.0, 0.0);
+        kp.right.hip = Cartesian3d::new(-1.0, -1.0, 0.0);
+        kp.left.knee = Cartesian3d::new(1.0, 0.0, 0.0);
+        let angle = degree.to_radians();
+        kp.left.ankle = Cartesian3d::new(1.0 - angle.sin(), angle.cos(), 0.0);
+        kp
+    }
+
+    #[test]
+    fn test_ramped_pace() {
+        let normal = StepPace::normal();
+        assert_eq!(ramped_pace(normal, 0), StepPace::quarter_speed());
+        assert_eq!(ramped_pace(normal, 1), StepPace::half_speed());
+        assert_eq!(ramped_pace(normal, 2), normal);
+        // never slower than quarter speed
+        let half = StepPace::half_speed();
+        assert_eq!(ramped_pace(half, 0), StepPace::quarter_speed());
+        assert_eq!(ramped_pace(half, 1), StepPace::quarter_speed());
+    }
+
+    #[test]
+    fn test_finished_loop() {
+        let practice = LoopPractice {
+            sections_per_loop: 3,
+            mastery_hit_rate: None,
+        };
+        assert_eq!(practice.finished_loop(1, 2), None);
+        assert_eq!(practice.finished_loop(2, 3), Some(0..3));
+        // sections of a loop can be skipped while the instructor demos
+        assert_eq!(practice.finished_loop(4, 7), Some(3..6));
+        assert_eq!(practice.loop_index(7), 2);
+    }
+
+    #[test]
+    fn test_stop_after_mastered_loop() {
+        let config = LoopConfig {
+            repetitions: 3,
+            tempo_ramp: false,
+            mastery_hit_rate: Some(0.8),
+        };
+        let mut tracker = swing_loop(&config);
+        tracker.set_bpm(120.0);
+        tracker.enforce_beat(true);
+        tracker
+            .detector
+            .transition_to_state(DetectionState::LiveTracking, 0.0);
+
+        let mut t = 0.0;
+        while !matches!(
+            tracker.detector.detection_state,
+            DetectionState::TrackingDone
+        ) && t < 60_000.0
+        {
+            t += 50.0;
+            // the dancer gets the step wrong in the first loop, then dances
+            // the pose expected next
+            let cursor = tracker.detector.detected.cursor.clone();
+            let degree = match tracker.detector.step(&cursor) {
+                _ if cursor.section_index < 2 => -90.0,
+                Some(step) if step.pose_ids[cursor.pose_index] == "shin-out" => 90.0,
+                _ => 0.0,
+            };
+            tracker.add_keypoints(shin(degree), t);
+            tracker.run_detection();
+        }
+
+        let detector = &tracker.detector;
+        assert_eq!(detector.session.matched_poses(0), 0);
+        assert_eq!(detector.session.matched_poses(2), 2);
+        assert_eq!(detector.mastered_loop, Some(1));
+        assert!(matches!(
+            detector.detection_state,
+            DetectionState::TrackingDone
+        ));
+        // stopped before the third loop was danced
+        assert_eq!(detector.time_section, Some(4));
+    }
+
+    #[test]
+    fn test_keep_looping_without_mastery() {
+        let config = LoopConfig {
+            repetitions: 2,
+            tempo_ramp: false,
+            mastery_hit_rate: Some(0.8),
+        };
+        let mut tracker = swing_loop(&config);
+        tracker.set_bpm(120.0);
+        tracker.enforce_beat(true);
+        tracker
+            .detector
+            .transition_to_state(DetectionState::LiveTracking, 0.0);
+
+        // only ever dancing the first pose hits half of the poses
+        let mut t = 0.0;
+        while !matches!(
+            tracker.detector.detection_state,
+            DetectionState::TrackingDone
+        ) && t < 60_000.0
+        {
+            t += 50.0;
+            tracker.add_keypoints(shin(0.0), t);
+            tracker.run_detection();
+        }
+
+        let detector = &tracker.detector;
+        assert_eq!(detector.session.matched_poses(0), 1);
+        assert_eq!(detector.mastered_loop, None);
+        // done after dancing all loops
+        assert!(matches!(
+            detector.detection_state,
+            DetectionState::TrackingDone
+        ));
+        assert_eq!(detector.time_section, Some(4));
+    }
+}
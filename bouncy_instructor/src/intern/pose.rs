@@ -14,6 +14,7 @@ use super::tracker_dance_collection::{LimbIndex, TrackerDanceCollection};
 use crate::intern::geom::Angle3d;
 use crate::public::keypoints::Cartesian3d;
 use crate::skeleton::Cartesian2d;
+use crate::step_file::BodyRegion;
 use crate::Keypoints;
 use std::collections::HashMap;
 use strum::IntoEnumIterator;
@@ -81,6 +82,17 @@ pub enum PoseDirection {
     Right,
 }
 
+impl BodyPart {
+    pub(crate) fn region(&self) -> BodyRegion {
+        match self {
+            BodyPart::Shoulder | BodyPart::Elbow | BodyPart::Wrist => BodyRegion::UpperBody,
+            BodyPart::Hip | BodyPart::Knee | BodyPart::Ankle | BodyPart::Heel | BodyPart::Toes => {
+                BodyRegion::LowerBody
+            }
+        }
+    }
+}
+
 impl BodyPoint {
     pub(crate) fn keypoint(&self, kp: &Keypoints) -> Cartesian3d {
         let side = match self.side {
@@ -168,6 +180,11 @@ impl Limb {
         -(start.z + end.z) / 2.0
     }
 
+    /// The region of the body part the limb ends in.
+    pub(crate) fn region(&self) -> BodyRegion {
+        self.end.part.region()
+    }
+
     pub(crate) fn mirror(&self) -> Self {
         Self {
             start: self.start.mirror(),
@@ -29,12 +29,14 @@ impl Tracker {
                 history_index = i;
             }
         }
+        error_details.resolve_regions(&self.db);
         Some(PoseApproximation {
             id: self.db.pose_id(pose_index).to_owned(),
-            name: self.db.pose_name(pose_index).to_owned(),
+            name: self.db.pose_name(pose_index),
             error,
             timestamp: self.timestamps[history_index],
             error_details,
+            jump: None,
         })
     }
 
@@ -78,16 +80,38 @@ impl Tracker {
             // pose not even close to be found
             None
         } else {
+            best_details.resolve_regions(db);
             Some(PoseApproximation {
                 id: db.pose_id(pose_index).to_owned(),
-                name: db.pose_name(pose_index).to_owned(),
+                name: db.pose_name(pose_index),
                 error: best_error,
                 timestamp: self.timestamps[history_index],
                 error_details: best_details,
+                jump: None,
             })
         }
     }
 
+    /// Fit the frame at the given time against a single pose.
+    pub(crate) fn pose_approximation(
+        &self,
+        pose_index: usize,
+        timestamp: Timestamp,
+    ) -> Option<PoseApproximation> {
+        let i = self.timestamps.partition_point(|t| *t < timestamp);
+        let skeleton = self.skeletons.get(i).or(self.skeletons.last())?;
+        let mut details = self.db.poses()[pose_index].skeleton_error(skeleton);
+        details.resolve_regions(&self.db);
+        Some(PoseApproximation {
+            id: self.db.pose_id(pose_index).to_owned(),
+            name: self.db.pose_name(pose_index),
+            error: details.error_score(),
+            timestamp,
+            error_details: details,
+            jump: None,
+        })
+    }
+
     /// Fit a single frame against all poses and return all errors
     pub(crate) fn all_pose_approximations(&self, timestamp: Timestamp) -> Vec<PoseApproximation> {
         if self.skeletons.is_empty() {
@@ -113,13 +137,15 @@ impl Tracker {
             .iter()
             .enumerate()
             .map(|(pose_index, pose)| {
-                let details = pose.skeleton_error(skeleton);
+                let mut details = pose.skeleton_error(skeleton);
+                details.resolve_regions(&self.db);
                 PoseApproximation {
                     id: self.db.pose_id(pose_index).to_owned(),
-                    name: self.db.pose_name(pose_index).to_owned(),
+                    name: self.db.pose_name(pose_index),
                     error: details.error_score(),
                     timestamp,
                     error_details: details,
+                    jump: None,
                 }
             })
             .collect()
@@ -309,6 +309,45 @@ impl pose_file::Pose {
             turn_hip: skeleton.turn_hip.to_degrees().round() as i16,
         }
     }
+
+    /// Definition of a pose that only exists in a tracker collection, such as
+    /// the merged poses of layered steps.
+    pub(crate) fn from_intern(id: String, pose: &Pose, db: &TrackerDanceCollection) -> Self {
+        let limbs = pose
+            .limbs
+            .iter()
+            .map(|position| crate::pose_file::LimbPosition {
+                limb: (*db.limb(position.limb)).into(),
+                weight: position.target.weight(),
+                angle: position.target.angle().as_degree().round() as i16,
+                tolerance: position.target.tolerance().as_degree().round() as u8,
+            })
+            .collect();
+        Self {
+            id,
+            names: None,
+            direction: pose.direction.into(),
+            limbs,
+            x_shift: -pose.shift.x,
+            y_shift: -pose.shift.y,
+            turn_shoulder: pose.turn_shoulder.as_degree().round() as i16,
+            turn_hip: pose.turn_hip.as_degree().round() as i16,
+            z: pose_file::PoseZ {
+                absolute: pose
+                    .z_absolute
+                    .iter()
+                    .map(|(&point, &z)| (point.into(), z))
+                    .collect(),
+                order: pose
+                    .z_order
+                    .iter()
+                    .map(|&ordering| ordering.into())
+                    .collect(),
+            },
+            mirror_of: String::new(),
+            no_mirror_x: false,
+        }
+    }
 }
 
 impl From<Limb> for pose_file::Limb {
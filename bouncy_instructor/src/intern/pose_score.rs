@@ -3,9 +3,10 @@
 use super::geom::SignedAngle;
 use super::pose::{BodyPartOrdering, BodyPoint, Pose};
 use super::skeleton_3d::Skeleton3d;
-use super::tracker_dance_collection::LimbIndex;
+use super::tracker_dance_collection::{LimbIndex, TrackerDanceCollection};
 use crate::intern::pose::PoseDirection;
 use crate::keypoints::Cartesian3d;
+use crate::step_file::BodyRegion;
 use std::collections::HashMap;
 
 /// Describe the target angle and how to compute an error score from it.
@@ -33,6 +34,12 @@ pub(crate) struct ErrorDetails {
     pub errors: Vec<f32>,
     /// weights to compute full error score
     pub weights: Vec<f32>,
+    /// measured angle per limb
+    pub angles: Vec<SignedAngle>,
+    /// angle per limb defined in the pose
+    pub target_angles: Vec<SignedAngle>,
+    /// The pose is seen from the side, rather than from the front.
+    pub sideway: bool,
     /// an extra penalty on the error score if the direction is wrong
     pub direction_error: f32,
     /// Ordering of body parts is off
@@ -44,6 +51,8 @@ pub(crate) struct ErrorDetails {
     pub z_absolute_errors: Vec<f32>,
     /// Is the body part in the expected quadrant?
     pub quadrant_errors: Vec<bool>,
+    /// Body region per limb, see `resolve_regions`
+    pub regions: Vec<BodyRegion>,
 }
 
 /// Find the pose with the lowest error score.
@@ -105,6 +114,10 @@ impl AngleTarget {
         self.angle
     }
 
+    pub(crate) fn tolerance(&self) -> SignedAngle {
+        self.tolerance
+    }
+
     /// Mirrors the angle keeping all else the same
     pub(crate) fn mirror(&self) -> AngleTarget {
         Self {
@@ -133,12 +146,16 @@ impl Pose {
         let mut errors = Vec::with_capacity(2 * self.limbs.len());
         let mut weights = Vec::with_capacity(2 * self.limbs.len());
         let mut limbs = Vec::with_capacity(2 * self.limbs.len());
+        let mut measured_angles = Vec::with_capacity(2 * self.limbs.len());
+        let mut target_angles = Vec::with_capacity(2 * self.limbs.len());
         for limb in &self.limbs {
             limbs.push(limb.limb);
             let angle = angles[limb.limb.as_usize()];
 
             errors.push(limb.target.target_error(angle));
             weights.push(limb.weight());
+            measured_angles.push(angle);
+            target_angles.push(limb.target.angle());
         }
         let z_order_errors = self
             .z_order
@@ -165,11 +182,15 @@ impl Pose {
             limbs,
             errors,
             weights,
+            angles: measured_angles,
+            target_angles,
+            sideway: self.direction == PoseDirection::Right,
             direction_error,
             body_points,
             z_order_errors,
             z_absolute_errors,
             quadrant_errors,
+            regions: vec![],
         }
     }
 }
@@ -191,6 +212,32 @@ impl ErrorDetails {
         }
     }
 
+    /// Look up the body region of each limb in the collection the pose is
+    /// defined in. Must be called before `region_error_score`.
+    pub(crate) fn resolve_regions(&mut self, db: &TrackerDanceCollection) {
+        self.regions = self
+            .limbs
+            .iter()
+            .map(|limb| db.limb(*limb).region())
+            .collect();
+    }
+
+    /// Error of only the limbs in `region`, between 0.0 and 1.0.
+    ///
+    /// Returns `None` if the pose has no weighted limbs in the region.
+    pub(crate) fn region_error_score(&self, region: BodyRegion) -> Option<f32> {
+        debug_assert_eq!(self.regions.len(), self.limbs.len());
+        let (total_err, total_weight) = self
+            .regions
+            .iter()
+            .zip(self.errors.iter().zip(&self.weights))
+            .filter(|(limb_region, _)| **limb_region == region)
+            .fold((0.0, 0.0), |(e_acc, w_acc), (_, (e, w))| {
+                (e_acc + e * w, w_acc + w)
+            });
+        (total_weight > 0.0).then(|| total_err / total_weight)
+    }
+
     /// returns indices of limbs in increasing order of how much they contribute to the total error
     pub(crate) fn sorted_by_error(&self, increasing: bool, weighted: bool) -> Vec<usize> {
         let mut indices = (0..self.errors.len()).collect::<Vec<_>>();
@@ -0,0 +1,263 @@
+//! Counting repetitions of a step, danced at the speed of the dancer.
+//!
+//! Without a beat there is no time at which a pose is due. Instead, the
+//! counter waits for the poses of the step in order and counts a repetition
+//! each time the last pose is matched. Taking too long for the next pose
+//! abandons the repetition and the counter waits for the first pose again.
+
+use super::dance_detector::{DanceDetector, DetectionState};
+use super::skeleton_3d::Skeleton3d;
+use super::tracker_dance_collection::TrackerDanceCollection;
+use crate::tracker::Repetition;
+use crate::{DetectionResult, StepInfo};
+
+type Timestamp = f64;
+
+/// Max time between two poses of a repetition, in ms.
+const MAX_POSE_GAP: Timestamp = 3000.0;
+
+#[derive(Debug, Clone)]
+pub(crate) struct RepCounter {
+    step: StepInfo,
+    /// Index of the pose to match next.
+    next_pose: usize,
+    /// Error and quality of each pose matched in the current repetition.
+    matched: Vec<(f32, f32)>,
+    rep_start: Timestamp,
+    last_match: Timestamp,
+    /// The dancer still holds the last matched pose, which is also the next
+    /// one. It only counts again after leaving it.
+    holding: bool,
+    pub(crate) repetitions: Vec<Repetition>,
+    /// Repetitions started but not finished in time.
+    pub(crate) abandoned: u32,
+}
+
+impl RepCounter {
+    pub(crate) fn new(step: StepInfo) -> Self {
+        assert_ne!(step.num_poses(), 0, "must have poses to count");
+        Self {
+            step,
+            next_pose: 0,
+            matched: vec![],
+            rep_start: 0.0,
+            last_match: 0.0,
+            holding: false,
+            repetitions: vec![],
+            abandoned: 0,
+        }
+    }
+
+    pub(crate) fn next_pose(&self) -> usize {
+        self.next_pose
+    }
+
+    pub(crate) fn expected_pose_id(&self) -> &str {
+        &self.step.pose_ids[self.next_pose]
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self::new(self.step.clone());
+    }
+
+    /// Start over if the next pose takes too long. Must be checked before
+    /// looking up the expected pose of a new frame.
+    pub(crate) fn abandon_if_late(&mut self, t: Timestamp) {
+        if self.next_pose > 0 && t - self.last_match > MAX_POSE_GAP {
+            self.abandoned += 1;
+            self.next_pose = 0;
+            self.matched.clear();
+            self.holding = false;
+        }
+    }
+
+    /// Add the error of the expected pose in a new frame, matching it if the
+    /// error is below `threshold`.
+    pub(crate) fn add_pose_error(&mut self, t: Timestamp, error: f32, threshold: f32) {
+        if error >= threshold {
+            self.holding = false;
+            return;
+        }
+        if self.holding {
+            return;
+        }
+
+        if self.next_pose == 0 {
+            self.rep_start = t;
+        }
+        let matched_id = self.expected_pose_id().to_owned();
+        self.matched.push((error, 1.0 - error / threshold));
+        self.last_match = t;
+        self.next_pose = (self.next_pose + 1) % self.step.num_poses();
+        self.holding = self.expected_pose_id() == matched_id;
+
+        if self.next_pose == 0 {
+            let n = self.matched.len() as f32;
+            self.repetitions.push(Repetition {
+                number: self.repetitions.len() as u32 + 1,
+                start: self.rep_start,
+                end: t,
+                error: self.matched.iter().map(|(error, _)| error).sum::<f32>() / n,
+                quality: self.matched.iter().map(|(_, quality)| quality).sum::<f32>() / n,
+            });
+            self.matched.clear();
+        }
+    }
+}
+
+impl DanceDetector {
+    /// Tick in repetition mode, which starts tracking right away.
+    pub(crate) fn tick_repetitions(
+        &mut self,
+        now: Timestamp,
+        db: &TrackerDanceCollection,
+        skeleton: Option<&Skeleton3d>,
+    ) -> DetectionResult {
+        match self.detection_state {
+            DetectionState::Init | DetectionState::Positioning => {
+                self.transition_to_state(DetectionState::LiveTracking, now);
+            }
+            DetectionState::LiveTracking => {
+                if let (Some(counter), Some(skeleton)) = (&mut self.rep_counter, skeleton) {
+                    if self.last_evaluation != now {
+                        counter.abandon_if_late(now);
+                        let pose_id = counter.expected_pose_id();
+                        let pose_idx = db.pose_by_id(pose_id).expect("step pose must exist");
+                        let error = db.poses()[pose_idx].skeleton_error(skeleton).error_score();
+                        let threshold = self.config.pose_error_threshold(pose_id);
+                        counter.add_pose_error(now, error, threshold);
+                    }
+                }
+                self.last_evaluation = now;
+            }
+            _ => (),
+        }
+        self.detected.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keypoints::Cartesian3d;
+    use crate::{load_pose_str, load_step_str, Keypoints, Tracker};
+
+    fn setup() {
+        load_pose_str(
+            r#"
+            (
+              version: 0,
+              poses: [
+                (
+                  name: "shin-down",
+                  direction: Front,
+                  limbs: [(limb: LeftShin, angle: 0, tolerance: 5, weight: 1.0)],
+                ),
+                (
+                  name: "shin-out",
+                  direction: Front,
+                  limbs: [(limb: LeftShin, angle: 90, tolerance: 5, weight: 1.0)],
+                ),
+              ],
+            )"#,
+        )
+        .unwrap();
+        load_step_str(
+            r#"
+            (
+              version: 0,
+              steps: [
+                (
+                  id: "swing",
+                  name: "Swing",
+                  keyframes: [
+                    (pose: "shin-down", orientation: ToCamera),
+                    (pose: "shin-out", orientation: ToCamera),
+                  ],
+                ),
+                (
+                  id: "stand",
+                  name: "Stand",
+                  keyframes: [(pose: "shin-down", orientation: ToCamera)],
+                ),
+              ],
+            )"#,
+            "test".to_owned(),
+        )
+        .unwrap();
+    }
+
+    /// Facing the camera, the left shin rotated outwards by `degree`.
+    fn shin(degree: f32) -> Keypoints {
+        let mut kp = Keypoints::default();
+        kp.left.shoulder = Cartesian3d::new(1.0, -2.0, 0.0);
+        kp.right.shoulder = Cartesian3d::new(-1.0, -2.0, 0.0);
+        kp.left.hip = Cartesian3d::new(1.0, -1.0, 0.0);
+        kp.right.hip = Cartesian3d::new(-1.0, -1.0, 0.0);
+        kp.left.knee = Cartesian3d::new(1.0, 0.0, 0.0);
+        let angle = degree.to_radians();
+        kp.left.ankle = Cartesian3d::new(1.0 - angle.sin(), angle.cos(), 0.0);
+        kp
+    }
+
+    /// Count repetitions of a step, the dancer's shin at a degree per
+    /// timestamp. The first frame only starts tracking.
+    fn count(step_id: &str, frames: &[(f64, f32)]) -> Tracker {
+        setup();
+        let mut tracker = Tracker::new_repetition_tracker(step_id.to_owned()).unwrap();
+        for &(t, degree) in frames {
+            tracker.add_keypoints(shin(degree), t);
+            tracker.run_detection();
+        }
+        tracker
+    }
+
+    #[test]
+    fn test_count_repetitions() {
+        let frames = [
+            (0.0, 0.0),
+            // waiting for the shin to go down first
+            (100.0, 90.0),
+            (200.0, 0.0),
+            // halfway is neither pose
+            (300.0, 45.0),
+            (400.0, 90.0),
+            (500.0, 0.0),
+            // not quite out, but within the threshold
+            (600.0, 80.0),
+            (700.0, 0.0),
+        ];
+        let tracker = count("swing", &frames);
+        let repetitions = tracker.repetitions();
+        assert_eq!(repetitions.len(), 2);
+        let (first, second) = (repetitions[0], repetitions[1]);
+        assert_eq!((first.number, first.start, first.end), (1, 200.0, 400.0));
+        assert!(first.error < 1e-6, "{first:?}");
+        assert!((first.quality - 1.0).abs() < 1e-6, "{first:?}");
+        assert_eq!((second.number, second.start, second.end), (2, 500.0, 600.0));
+        assert!(second.error > 0.0, "{second:?}");
+        assert!(second.quality < first.quality, "{second:?}");
+        assert_eq!(tracker.repetition_pose_index(), Some(1));
+    }
+
+    #[test]
+    fn test_abandon_slow_repetition() {
+        let tracker = count("swing", &[(0.0, 0.0), (100.0, 0.0), (5100.0, 0.0)]);
+        assert_eq!(tracker.abandoned_repetitions(), 1);
+        assert!(tracker.repetitions().is_empty());
+        // the late pose starts a new repetition
+        assert_eq!(tracker.repetition_pose_index(), Some(1));
+    }
+
+    #[test]
+    fn test_hold_single_pose() {
+        let frames = [
+            (0.0, 0.0),
+            (100.0, 0.0),
+            (200.0, 0.0),
+            (300.0, 90.0),
+            (400.0, 0.0),
+        ];
+        let tracker = count("stand", &frames);
+        assert_eq!(tracker.repetitions().len(), 2);
+    }
+}
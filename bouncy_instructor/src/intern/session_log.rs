@@ -0,0 +1,288 @@
+//! Recording what happened during a tracking session, for a report after
+//! tracking is done.
+//!
+//! The detector only keeps what it needs to continue detecting. This log keeps
+//! each added pose with its place in the activity and its timing, as well as
+//! the hints given to the dancer along the way.
+
+use super::tracker_dance_collection::LimbIndex;
+use crate::tracker::{
+    AccuracyScore, Adaptation, DanceCursor, HintCount, HistogramError, PoseApproximation, TimingBin,
+};
+use crate::PoseHint;
+use std::collections::BTreeMap;
+
+type Timestamp = f64;
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SessionLog {
+    /// All poses added to the detection, in order.
+    poses: Vec<LoggedPose>,
+    /// Hints given at a subbeat, each hint at most once per subbeat.
+    hints: Vec<(u32, PoseHint)>,
+    /// Changes to the difficulty in adaptive mode, in order.
+    pub(crate) adaptations: Vec<Adaptation>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct LoggedPose {
+    /// Where in the activity the pose was expected.
+    pub cursor: DanceCursor,
+    pub step_id: String,
+    pub step_name: String,
+    pub pose: PoseApproximation,
+    /// When the pose should have been seen on camera.
+    pub expected_timestamp: Timestamp,
+    /// Added without matching, because the beat passed.
+    pub missed: bool,
+}
+
+impl SessionLog {
+    pub(crate) fn add_pose(&mut self, pose: LoggedPose) {
+        self.poses.push(pose);
+    }
+
+    /// Remember a hint, ignoring repetitions while the dancer tries to match
+    /// the same pose.
+    pub(crate) fn add_hint(&mut self, subbeat: u32, hint: PoseHint) {
+        if !self.hints.contains(&(subbeat, hint)) {
+            self.hints.push((subbeat, hint));
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.poses.clear();
+        self.hints.clear();
+        self.adaptations.clear();
+    }
+
+    /// Poses matched in a teacher section, not counting missed poses.
+    pub(crate) fn matched_poses(&self, section_index: usize) -> u32 {
+        self.poses
+            .iter()
+            .filter(|logged| !logged.missed && logged.cursor.section_index == section_index)
+            .count() as u32
+    }
+
+    /// Pose matches and misses per teacher section, in order of the activity.
+    pub(crate) fn section_accuracy(&self) -> Vec<AccuracyScore> {
+        self.accuracy(true)
+    }
+
+    /// Pose matches and misses per step, in order of first appearance.
+    pub(crate) fn step_accuracy(&self) -> Vec<AccuracyScore> {
+        self.accuracy(false)
+    }
+
+    fn accuracy(&self, by_section: bool) -> Vec<AccuracyScore> {
+        let mut out: Vec<AccuracyScore> = vec![];
+        for logged in &self.poses {
+            let section_index = by_section.then_some(logged.cursor.section_index);
+            let existing = out
+                .iter()
+                .position(|s| s.step_id == logged.step_id && s.section_index == section_index);
+            let i = existing.unwrap_or_else(|| {
+                out.push(AccuracyScore::new(
+                    logged.step_id.clone(),
+                    logged.step_name.clone(),
+                    section_index,
+                ));
+                out.len() - 1
+            });
+            out[i].add_pose(logged.pose.error, logged.missed);
+        }
+        out
+    }
+
+    /// How many ms each matched pose was before (negative) or after
+    /// (positive) the beat.
+    pub(crate) fn timing_offsets(&self) -> impl Iterator<Item = f64> + '_ {
+        self.poses
+            .iter()
+            .filter(|logged| !logged.missed)
+            .map(|logged| logged.pose.timestamp - logged.expected_timestamp)
+    }
+
+    /// Count timing offsets in bins of `bin_width` ms, aligned to the beat.
+    ///
+    /// Only bins with at least one pose are listed, ordered from early to
+    /// late. Fails unless `bin_width` is positive and finite.
+    pub(crate) fn timing_histogram(
+        &self,
+        bin_width: f64,
+    ) -> Result<Vec<TimingBin>, HistogramError> {
+        if !(bin_width > 0.0 && bin_width.is_finite()) {
+            return Err(HistogramError::BinWidth(bin_width));
+        }
+        let mut counts = BTreeMap::<i64, u32>::new();
+        for offset in self.timing_offsets() {
+            *counts
+                .entry((offset / bin_width).floor() as i64)
+                .or_default() += 1;
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(bin, count)| TimingBin {
+                start: bin as f64 * bin_width,
+                end: (bin + 1) as f64 * bin_width,
+                count,
+            })
+            .collect())
+    }
+
+    /// All hints given, most frequent first.
+    pub(crate) fn hint_counts(&self) -> Vec<HintCount> {
+        let mut out: Vec<HintCount> = vec![];
+        for (_subbeat, hint) in &self.hints {
+            match out.iter_mut().find(|count| count.hint == *hint) {
+                Some(count) => count.count += 1,
+                None => out.push(HintCount {
+                    hint: *hint,
+                    count: 1,
+                }),
+            }
+        }
+        // stable sort keeps the first given hint first on equal counts
+        out.sort_by_key(|count| std::cmp::Reverse(count.count));
+        out
+    }
+
+    /// Average error and weight per limb over all logged poses, sorted by
+    /// weighted error, worst limb first.
+    pub(crate) fn limb_errors(&self) -> Vec<(LimbIndex, f32, f32)> {
+        // (limb, error sum, weight sum, count)
+        let mut sums: Vec<(LimbIndex, f32, f32, u32)> = vec![];
+        for logged in &self.poses {
+            let details = &logged.pose.error_details;
+            for ((limb, error), weight) in details
+                .limbs
+                .iter()
+                .zip(&details.errors)
+                .zip(&details.weights)
+            {
+                match sums.iter_mut().find(|sum| sum.0 == *limb) {
+                    Some(sum) => {
+                        sum.1 += error;
+                        sum.2 += weight;
+                        sum.3 += 1;
+                    }
+                    None => sums.push((*limb, *error, *weight, 1)),
+                }
+            }
+        }
+        let mut out: Vec<_> = sums
+            .into_iter()
+            .map(|(limb, error, weight, n)| (limb, error / n as f32, weight / n as f32))
+            .collect();
+        out.sort_by(|a, b| f32::total_cmp(&(b.1 * b.2), &(a.1 * a.2)));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intern::pose::Limb;
+    use crate::intern::pose_score::ErrorDetails;
+
+    fn logged(section_index: usize, step: &str, error: f32, offset: f64) -> LoggedPose {
+        let expected_timestamp = 1000.0;
+        LoggedPose {
+            cursor: DanceCursor {
+                section_index,
+                ..Default::default()
+            },
+            step_id: step.to_owned(),
+            step_name: step.to_owned(),
+            pose: PoseApproximation {
+                id: "pose".to_owned(),
+                name: "pose".to_owned(),
+                error,
+                timestamp: expected_timestamp + offset,
+                error_details: ErrorDetails {
+                    limbs: vec![Limb::LEFT_THIGH, Limb::RIGHT_SHIN],
+                    errors: vec![error, 2.0 * error],
+                    weights: vec![1.0, 1.0],
+                    ..Default::default()
+                },
+                jump: None,
+            },
+            expected_timestamp,
+            missed: false,
+        }
+    }
+
+    fn example_log() -> SessionLog {
+        let mut log = SessionLog::default();
+        log.add_pose(logged(0, "a", 0.1, -30.0));
+        log.add_pose(logged(0, "a", 0.3, 20.0));
+        log.add_pose(logged(1, "b", 0.2, 80.0));
+        log.add_pose(LoggedPose {
+            missed: true,
+            ..logged(2, "a", 0.5, 500.0)
+        });
+        log
+    }
+
+    #[test]
+    fn test_accuracy() {
+        let log = example_log();
+        let sections = log.section_accuracy();
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].section_index, Some(0));
+        assert_eq!(sections[0].pose_matches, 2);
+        assert!((sections[0].average_error() - 0.2).abs() < 1e-6);
+        assert_eq!(sections[2].pose_misses, 1);
+        assert_eq!(sections[2].accuracy(), 0.0);
+
+        let steps = log.step_accuracy();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].step_id, "a");
+        assert_eq!((steps[0].pose_matches, steps[0].pose_misses), (2, 1));
+        assert!((steps[0].accuracy() - 2.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_timing_histogram() {
+        let log = example_log();
+        let bins = |log: &SessionLog, width: f64| -> Vec<(f64, u32)> {
+            log.timing_histogram(width)
+                .unwrap()
+                .iter()
+                .map(|bin| (bin.start, bin.count))
+                .collect()
+        };
+        // the missed pose is not counted
+        assert_eq!(bins(&log, 50.0), [(-50.0, 1), (0.0, 1), (50.0, 1)]);
+        // empty bins are left out
+        assert_eq!(bins(&log, 10.0), [(-30.0, 1), (20.0, 1), (80.0, 1)]);
+        assert!(bins(&SessionLog::default(), 50.0).is_empty());
+
+        for width in [0.0, -10.0, f64::NAN, f64::INFINITY] {
+            assert!(log.timing_histogram(width).is_err(), "{width}");
+        }
+    }
+
+    #[test]
+    fn test_hint_counts() {
+        let mut log = SessionLog::default();
+        log.add_hint(0, PoseHint::ZOrder);
+        log.add_hint(0, PoseHint::ZOrder);
+        log.add_hint(1, PoseHint::LeftRight);
+        log.add_hint(2, PoseHint::LeftRight);
+        let counts: Vec<_> = log
+            .hint_counts()
+            .iter()
+            .map(|count| (count.hint, count.count))
+            .collect();
+        assert_eq!(counts, [(PoseHint::LeftRight, 2), (PoseHint::ZOrder, 1)]);
+    }
+
+    #[test]
+    fn test_limb_errors() {
+        let limbs = example_log().limb_errors();
+        assert_eq!(limbs.len(), 2);
+        assert_eq!(limbs[0].0, Limb::RIGHT_SHIN);
+        assert!((limbs[0].1 - 0.55).abs() < 1e-6, "{limbs:?}");
+    }
+}
@@ -3,6 +3,7 @@ use std::rc::Rc;
 
 use super::pose::BodyPoint;
 use super::skeleton_3d::Direction;
+use crate::parsing::course_file::TranslatedString;
 
 mod detection;
 
@@ -10,11 +11,17 @@ mod detection;
 pub(crate) struct Step {
     pub id: String,
     pub name: String,
+    /// Translations of `name`, for display.
+    pub names: Option<TranslatedString>,
     pub variation: Option<String>,
+    /// Translated descriptions of the variation.
+    pub variation_descriptions: Option<TranslatedString>,
     pub poses: Vec<usize>,
     pub directions: Vec<Direction>,
     pub pivots: Vec<BodyPoint>,
     pub jump_heights: Vec<Option<f32>>,
+    /// How many pose slots each pose is held.
+    pub durations: Vec<f32>,
     pub source: StepSource,
 }
 
@@ -34,7 +41,9 @@ impl Step {
         Step {
             id: self.id,
             name: self.name,
+            names: self.names,
             variation: self.variation,
+            variation_descriptions: self.variation_descriptions,
             poses: self.poses,
             directions: self
                 .directions
@@ -49,6 +58,7 @@ impl Step {
                 .collect(),
             pivots: self.pivots,
             jump_heights: self.jump_heights,
+            durations: self.durations,
             source: self.source,
         }
     }
@@ -1,3 +1,5 @@
+use super::Step;
+use crate::intern::jump::JumpScore;
 use crate::intern::tracker_dance_collection::TrackerDanceCollection;
 use crate::public::tracker::{DetectionResult, PoseApproximation};
 use crate::tracker::DetectedStep;
@@ -11,6 +13,8 @@ impl DetectedStep {
             end: poses.last().map(|p| p.timestamp).unwrap_or(0.0),
             error: poses.iter().map(|p| p.error).sum::<f32>() / poses.len() as f32,
             poses,
+            travel: None,
+            travel_hint: None,
         }
     }
 
@@ -53,13 +57,7 @@ impl Tracker {
             return None;
         }
         let end = end.min(self.timestamps.len());
-        let dt = 60_000.0 / self.detector.bpm;
-        let min_dt = (dt * 0.5).round() as f64;
-        let max_dt = (dt * 1.5).round() as f64;
-
-        let threshold = 0.2;
-        let pose_window_ms = max_dt;
-        // let step_window_ms = dt;
+        let threshold = self.detector.config.first_pose_threshold;
 
         // hack: only return idle if nothing else was found
         let mut idle_result = None;
@@ -75,6 +73,7 @@ impl Tracker {
         let mut start_t = self.timestamps[start];
         // i0 holds because of above assignment, i1 because of the pre-condition
         while start < self.timestamps.len() {
+            let (_, pose_window_ms) = self.pose_intervals(start_t);
             let end_t = (start_t + pose_window_ms).min(self.timestamps[end - 1]);
             let first_pose_candidate = self.best_fit_pose(start_t, end_t);
             if first_pose_candidate
@@ -85,6 +84,7 @@ impl Tracker {
                 let step_start_t = first_pose_candidate.unwrap().timestamp;
                 let step_start_index =
                     start + self.timestamps[start..end].partition_point(|t| *t < step_start_t);
+                let (min_dt, max_dt) = self.pose_intervals(step_start_t);
                 if let Some(step) = self.detect_step(step_start_index, min_dt, max_dt, &self.db) {
                     if !step.step_name.contains("Idle") {
                         return Some(step);
@@ -113,6 +113,15 @@ impl Tracker {
         idle_result
     }
 
+    /// Min and max time between two poses of a step, for the tempo at `t`.
+    fn pose_intervals(&self, t: f64) -> (f64, f64) {
+        let config = &self.detector.config;
+        let dt = 60_000.0 / self.detector.tempo.bpm_at(t);
+        let min_dt = (dt * config.min_pose_interval).round() as f64;
+        let max_dt = (dt * config.max_pose_interval).round() as f64;
+        (min_dt, max_dt)
+    }
+
     /// Try to find a step after `start` with the given minimum and maximum beat
     /// durations.
     ///
@@ -139,10 +148,12 @@ impl Tracker {
             let mut pose_matches = vec![];
             let mut start_t = self.timestamps[start];
             let mut end_t = start_t + max_dt;
-            for pose in &step.poses {
+            for (pose, duration) in step.poses.iter().zip(&step.durations) {
                 if let Some(pose_match) = self.find_pose(*pose, start_t, end_t, db) {
-                    start_t = pose_match.timestamp + min_dt;
-                    end_t = pose_match.timestamp + max_dt;
+                    // held poses push the next pose back
+                    let duration = *duration as f64;
+                    start_t = pose_match.timestamp + min_dt * duration;
+                    end_t = pose_match.timestamp + max_dt * duration;
                     pose_matches.push(pose_match);
                 } else {
                     pose_matches.clear();
@@ -150,6 +161,7 @@ impl Tracker {
                 }
             }
             if !pose_matches.is_empty() {
+                self.score_jumps(step, &mut pose_matches, max_dt);
                 let detection = DetectedStep::new(step.name.clone(), pose_matches);
                 if detection.error < best_error {
                     // hack: only overwrite with non-idle step with idle step if we are sure
@@ -167,6 +179,23 @@ impl Tracker {
         }
         result
     }
+
+    /// Score the jump into each pose, measured from the previous pose.
+    fn score_jumps(&self, step: &Step, poses: &mut [PoseApproximation], max_dt: f64) {
+        let mut start = poses.first().map_or(0.0, |pose| pose.timestamp - max_dt);
+        for (pose, expected) in poses.iter_mut().zip(&step.jump_heights) {
+            if let Some(expected) = expected.filter(|height| *height > 0.0) {
+                pose.jump = JumpScore::between(
+                    expected,
+                    &self.timestamps,
+                    &self.keypoints,
+                    start,
+                    pose.timestamp,
+                );
+            }
+            start = pose.timestamp;
+        }
+    }
 }
 
 // Tests below check basic step detection has no major bugs. They are not
@@ -275,7 +304,7 @@ mod tests {
         setup();
 
         let mut tracker = Tracker::new_from_global_collection();
-        tracker.detector.bpm = 60.0;
+        tracker.set_bpm(60.0);
 
         for (degree, time) in degrees.iter().zip(times) {
             match degree {
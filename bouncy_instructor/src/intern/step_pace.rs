@@ -2,8 +2,9 @@
 ///
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) struct StepPace {
-    /// The normal pace is one pose per subbeat, meaning 2 poses on a full
-    /// beat. This gives 8 poses on a full 4/4 count.
+    /// The normal pace is one pose per subbeat. Subbeats are defined by the
+    /// meter, in 4/4 with two subdivisions this gives 2 poses on a full beat
+    /// and 8 poses on a full count.
     ///
     /// Invariant: never zero
     subbeats_per_pose: u32,
@@ -16,32 +17,131 @@ impl StepPace {
         Self { subbeats_per_pose }
     }
 
-    // Two poses per full beat.
+    // One pose per subbeat, two per full beat in a straight meter.
     pub(crate) fn normal() -> Self {
         Self::new(1)
     }
 
-    // One pose per full beat.
+    // One pose per two subbeats, one per full beat in a straight meter.
     pub(crate) fn half_speed() -> Self {
         Self::new(2)
     }
 
-    // One pose per two full beats.
+    // One pose per four subbeats, one per two full beats in a straight meter.
     pub(crate) fn quarter_speed() -> Self {
         Self::new(4)
     }
 
-    pub(crate) fn subbeats_per_pose(&self) -> u32 {
-        self.subbeats_per_pose
+    /// Half the speed, down to quarter speed.
+    pub(crate) fn slower(&self) -> Option<Self> {
+        (self.subbeats_per_pose < Self::quarter_speed().subbeats_per_pose)
+            .then(|| Self::new(self.subbeats_per_pose * 2))
     }
 
-    /// Get the pose index after N mini-beats on the given pace.
+    /// Double the speed, up to normal speed.
+    pub(crate) fn faster(&self) -> Option<Self> {
+        (self.subbeats_per_pose > Self::normal().subbeats_per_pose)
+            .then(|| Self::new(self.subbeats_per_pose / 2))
+    }
+
+    /// Subbeat within one repetition at which each pose starts, followed by
+    /// the length of the repetition.
+    ///
+    /// `durations` are in pose slots, as defined per keyframe of a step.
+    /// Every pose lasts at least one subbeat.
+    pub(crate) fn pose_starts(&self, durations: &[f32]) -> Vec<u32> {
+        let mut starts = Vec::with_capacity(durations.len() + 1);
+        let mut slots = 0.0;
+        let mut start = 0;
+        starts.push(start);
+        for duration in durations {
+            slots += duration;
+            let end = (slots * self.subbeats_per_pose as f32).round() as u32;
+            start = end.max(start + 1);
+            starts.push(start);
+        }
+        starts
+    }
+
+    /// Subbeats of one repetition of a step.
+    pub(crate) fn step_subbeats(&self, durations: &[f32]) -> u32 {
+        *self.pose_starts(durations).last().expect("never empty")
+    }
+
+    /// Subbeats the pose with the given index is held.
+    pub(crate) fn pose_subbeats(&self, durations: &[f32], pose: usize) -> u32 {
+        if durations.is_empty() {
+            return self.subbeats_per_pose;
+        }
+        let starts = self.pose_starts(durations);
+        let i = pose % durations.len();
+        starts[i + 1] - starts[i]
+    }
+
+    /// Get the pose index after N subbeats on the given pace, counting poses
+    /// of previous repetitions.
     ///
     /// Full beat: only count 1,2,3,4 (used for bpm calculation)
     /// Subbeat: also count the "and" between
-    pub(crate) fn pose_at_subbeat(&self, subbeat: u32) -> u32 {
-        // Safe division due to invariant (I1)
-        // rounding down
-        subbeat / self.subbeats_per_pose
+    pub(crate) fn pose_at_subbeat(&self, durations: &[f32], subbeat: u32) -> u32 {
+        let starts = self.pose_starts(durations);
+        let len = *starts.last().expect("never empty");
+        if len == 0 {
+            // Safe division due to invariant (I1)
+            // rounding down
+            return subbeat / self.subbeats_per_pose;
+        }
+        let repetitions = subbeat / len;
+        let within = starts.partition_point(|start| *start <= subbeat % len) - 1;
+        repetitions * durations.len() as u32 + within as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pace_limits() {
+        assert_eq!(StepPace::normal().slower(), Some(StepPace::half_speed()));
+        assert_eq!(StepPace::quarter_speed().slower(), None);
+        assert_eq!(
+            StepPace::quarter_speed().faster(),
+            Some(StepPace::half_speed())
+        );
+        assert_eq!(StepPace::normal().faster(), None);
+    }
+
+    #[test]
+    fn test_uniform_poses() {
+        let pace = StepPace::half_speed();
+        let durations = [1.0; 4];
+        assert_eq!(pace.pose_starts(&durations), vec![0, 2, 4, 6, 8]);
+        assert_eq!(pace.pose_at_subbeat(&durations, 3), 1);
+        assert_eq!(pace.pose_at_subbeat(&durations, 9), 4);
+        assert_eq!(pace.pose_subbeats(&durations, 5), 2);
+    }
+
+    #[test]
+    fn test_hold() {
+        let pace = StepPace::half_speed();
+        let durations = [2.0, 1.0, 1.0];
+        assert_eq!(pace.step_subbeats(&durations), 8);
+        assert_eq!(pace.pose_at_subbeat(&durations, 3), 0);
+        assert_eq!(pace.pose_at_subbeat(&durations, 4), 1);
+        assert_eq!(pace.pose_at_subbeat(&durations, 8), 3);
+        assert_eq!(pace.pose_subbeats(&durations, 3), 4);
+    }
+
+    #[test]
+    fn test_syncopation() {
+        // With four subbeats per beat, the third pose lands on the "a" of 2.
+        let pace = StepPace::quarter_speed();
+        let durations = [1.0, 0.75, 0.25, 2.0];
+        assert_eq!(pace.pose_starts(&durations), vec![0, 4, 7, 8, 16]);
+
+        // too short durations are stretched to one subbeat
+        let pace = StepPace::normal();
+        assert_eq!(pace.pose_starts(&[0.5, 0.5, 1.0]), vec![0, 1, 2, 3]);
     }
 }
@@ -2,7 +2,7 @@ use super::body_shift::BodyShift;
 use super::step_pace::StepPace;
 use crate::skeleton::Cartesian2d;
 use crate::tracker::{DanceCursor, TeacherView};
-use crate::StepInfo;
+use crate::{Meter, StepInfo};
 
 /// Dynamically switch between steps, switch between views for showing the next
 /// step and going back to full camera mode for dancers to see themselves.
@@ -10,6 +10,7 @@ use crate::StepInfo;
 pub(crate) struct Teacher {
     sections: Vec<Section>,
     total_subbeats: u32,
+    meter: Meter,
     body_shift: BodyShift,
     teacher_display_mode: TeacherDisplayMode,
     // TODO: audio hints by the teacher
@@ -21,7 +22,7 @@ enum Section {
     /// The instructor shows the step, the student can watch.
     ShowStep(StepSection),
     /// Any move allowed from the student.
-    Freestyle { subbeats: u32 },
+    Freestyle { beats: u32 },
     /// The instructor shows some move but the student doesn't have to follow
     /// exactly. Just make sure to always move.
     Warmup(StepSection),
@@ -29,10 +30,17 @@ enum Section {
 
 struct StepSection {
     step: StepInfo,
-    subbeats: u32,
+    length: SectionLength,
     pace: StepPace,
 }
 
+/// How long a step is shown, independent of the meter.
+enum SectionLength {
+    /// Repeat the step, rounded down to full beats.
+    Repeat(u32),
+    Beats(u32),
+}
+
 /// What to display while the user dances.
 #[derive(Default)]
 enum TeacherDisplayMode {
@@ -53,17 +61,26 @@ impl Teacher {
         }
     }
 
+    /// Switch to a different meter, which changes the length of all sections
+    /// in subbeats.
+    pub(crate) fn set_meter(&mut self, meter: Meter) {
+        self.meter = meter;
+        self.update_tracked_subbeats();
+    }
+
+    pub(crate) fn meter(&self) -> &Meter {
+        &self.meter
+    }
+
     /// A step to dance by the student.
     pub(crate) fn add_step(&mut self, step: StepInfo, repeat: u32, pace: StepPace) {
-        let beats = repeat * step.num_poses() as u32 * pace.subbeats_per_pose() / 2;
-
-        assert_ne!(beats, 0);
+        assert_ne!(repeat, 0);
         for _ in 0..repeat {
             self.body_shift.add(step.body_shift.clone());
         }
         self.sections.push(Section::Step(StepSection {
             step,
-            subbeats: beats * 2,
+            length: SectionLength::Repeat(repeat),
             pace,
         }));
         self.update_tracked_subbeats();
@@ -74,7 +91,7 @@ impl Teacher {
         assert_ne!(beats, 0);
         self.sections.push(Section::ShowStep(StepSection {
             step,
-            subbeats: beats * 2,
+            length: SectionLength::Beats(beats),
             pace,
         }));
         self.update_tracked_subbeats();
@@ -83,9 +100,7 @@ impl Teacher {
     /// Any move allowed from the student.
     pub(crate) fn add_freestyle(&mut self, beats: u32) {
         assert_ne!(beats, 0);
-        self.sections.push(Section::Freestyle {
-            subbeats: beats * 2,
-        });
+        self.sections.push(Section::Freestyle { beats });
         self.update_tracked_subbeats();
     }
 
@@ -94,7 +109,7 @@ impl Teacher {
         assert_ne!(beats, 0);
         self.sections.push(Section::Warmup(StepSection {
             step,
-            subbeats: beats * 2,
+            length: SectionLength::Beats(beats),
             pace,
         }));
         self.update_tracked_subbeats();
@@ -113,12 +128,14 @@ impl Teacher {
     pub(crate) fn step_at_subbeat(&self, subbeat: u32) -> Option<(&StepInfo, u32)> {
         self.section_at_subbeat(subbeat)
             .and_then(Section::step)
-            .map(|StepSection { step, pace, .. }| (step, pace.pose_at_subbeat(subbeat)))
+            .map(|StepSection { step, pace, .. }| {
+                (step, pace.pose_at_subbeat(&step.durations, subbeat))
+            })
     }
 
     pub(crate) fn pose_duration(&self, cursor: &DanceCursor) -> Option<u32> {
         self.section(cursor)
-            .and_then(|section| section.pose_duration())
+            .and_then(|section| section.pose_duration(cursor.pose_index))
     }
 
     pub(crate) fn cursor_at_subbeat(&self, subbeat: u32) -> DanceCursor {
@@ -126,9 +143,9 @@ impl Teacher {
         let section = self.sections.get(section_index);
         let pose_index = section
             .and_then(|section| {
-                section
-                    .step()
-                    .map(|StepSection { pace, .. }| pace.pose_at_subbeat(remainder as u32) as usize)
+                section.step().map(|StepSection { step, pace, .. }| {
+                    pace.pose_at_subbeat(&step.durations, remainder as u32) as usize
+                })
             })
             .unwrap_or(0);
 
@@ -136,11 +153,11 @@ impl Teacher {
         let steps_current_section = self
             .sections
             .get(section_index)
-            .map(|sec| remainder / sec.single_step_subbeats() as usize)
+            .map(|sec| remainder / sec.single_step_subbeats(&self.meter) as usize)
             .unwrap_or(0);
         let steps_before: usize = self.sections[..section_index]
             .iter()
-            .map(|sec| sec.num_steps() as usize)
+            .map(|sec| sec.num_steps(&self.meter) as usize)
             .sum();
         let step_index = steps_current_section + steps_before;
 
@@ -152,13 +169,44 @@ impl Teacher {
         }
     }
 
+    /// Number of poses in a section the student is tracked on, or `None` for
+    /// sections without a tracked step.
+    pub(crate) fn tracked_poses_in_section(&self, section_index: usize) -> Option<u32> {
+        let section = self.sections.get(section_index)?;
+        if !section.is_tracked() {
+            return None;
+        }
+        section.step()?;
+        Some(section.poses_at_subbeat(section.subbeats(&self.meter)))
+    }
+
+    /// Slow down or speed up a section, returning the new pace if it changed.
+    ///
+    /// Only change sections that have not started, or else the cursor jumps.
+    pub(crate) fn adapt_section_pace(
+        &mut self,
+        section_index: usize,
+        easier: bool,
+    ) -> Option<StepPace> {
+        let step_section = self.sections.get_mut(section_index)?.step_mut()?;
+        let pace = if easier {
+            step_section.pace.slower()?
+        } else {
+            step_section.pace.faster()?
+        };
+        step_section.pace = pace;
+        self.update_tracked_subbeats();
+        Some(pace)
+    }
+
     fn index_at_subbeat(&self, subbeat: u32) -> (usize, usize) {
         let mut subbeat_remainder = subbeat;
         for (index, section) in self.sections.iter().enumerate() {
-            if subbeat_remainder < section.subbeats() {
+            let section_subbeats = section.subbeats(&self.meter);
+            if subbeat_remainder < section_subbeats {
                 return (index, subbeat_remainder as usize);
             }
-            subbeat_remainder -= section.subbeats();
+            subbeat_remainder -= section_subbeats;
         }
         (self.sections.len(), 0)
     }
@@ -169,10 +217,11 @@ impl Teacher {
 
     fn section_at_subbeat(&self, mut subbeat: u32) -> Option<&Section> {
         for section in &self.sections {
-            if subbeat < section.subbeats() {
+            let section_subbeats = section.subbeats(&self.meter);
+            if subbeat < section_subbeats {
                 return Some(section);
             }
-            subbeat -= section.subbeats();
+            subbeat -= section_subbeats;
         }
         None
     }
@@ -188,13 +237,13 @@ impl Teacher {
         let mut poses = 0;
         let cursor = self.cursor_at_subbeat(subbeat);
         for section in &self.sections[0..cursor.section_index] {
-            let section_duration = section.subbeats();
-            poses += section_duration / section.pose_duration().unwrap_or(1);
+            let section_duration = section.subbeats(&self.meter);
+            poses += section.poses_at_subbeat(section_duration);
             subbeat -= section_duration;
         }
 
         if let Some(section) = self.section(&cursor) {
-            poses += subbeat / section.pose_duration().unwrap_or(1);
+            poses += section.poses_at_subbeat(subbeat);
         }
         poses as usize
     }
@@ -229,7 +278,11 @@ impl Teacher {
     }
 
     fn update_tracked_subbeats(&mut self) {
-        self.total_subbeats = self.sections.iter().map(|section| section.subbeats()).sum();
+        self.total_subbeats = self
+            .sections
+            .iter()
+            .map(|section| section.subbeats(&self.meter))
+            .sum();
     }
 
     /// How many beats to track for in total
@@ -244,41 +297,38 @@ impl Teacher {
             if section.is_tracked() {
                 break;
             }
-            delay += section.subbeats();
+            delay += section.subbeats(&self.meter);
         }
         delay
     }
 }
 
 impl Section {
-    fn subbeats(&self) -> u32 {
+    fn subbeats(&self, meter: &Meter) -> u32 {
         match self {
             Section::Step(step_section)
             | Section::ShowStep(step_section)
-            | Section::Warmup(step_section) => {
-                let StepSection { subbeats, .. } = step_section;
-                *subbeats
-            }
-            Section::Freestyle { subbeats } => *subbeats,
+            | Section::Warmup(step_section) => step_section.subbeats(meter),
+            Section::Freestyle { beats } => beats * meter.subdivisions(),
         }
     }
 
-    fn single_step_subbeats(&self) -> u32 {
+    fn single_step_subbeats(&self, meter: &Meter) -> u32 {
         match self {
             Section::Step(step_section)
             | Section::ShowStep(step_section)
             | Section::Warmup(step_section) => {
                 let StepSection { step, pace, .. } = step_section;
-                pace.subbeats_per_pose() * step.num_poses() as u32
+                pace.step_subbeats(&step.durations)
             }
             // TODO: This doesn't quite make sense
-            Section::Freestyle { subbeats } => *subbeats,
+            Section::Freestyle { .. } => self.subbeats(meter),
         }
     }
 
-    fn num_steps(&self) -> u32 {
-        let per_step = self.single_step_subbeats();
-        self.subbeats().div_ceil(per_step)
+    fn num_steps(&self, meter: &Meter) -> u32 {
+        let per_step = self.single_step_subbeats(meter);
+        self.subbeats(meter).div_ceil(per_step)
     }
 
     fn step(&self) -> Option<&StepSection> {
@@ -290,9 +340,27 @@ impl Section {
         }
     }
 
-    fn pose_duration(&self) -> Option<u32> {
+    fn step_mut(&mut self) -> Option<&mut StepSection> {
+        match self {
+            Section::Step(step_section)
+            | Section::ShowStep(step_section)
+            | Section::Warmup(step_section) => Some(step_section),
+            Section::Freestyle { .. } => None,
+        }
+    }
+
+    fn pose_duration(&self, pose_index: usize) -> Option<u32> {
         self.step()
-            .map(|StepSection { pace, .. }| pace.subbeats_per_pose())
+            .map(|StepSection { step, pace, .. }| pace.pose_subbeats(&step.durations, pose_index))
+    }
+
+    /// How many poses start within the first `subbeat` subbeats of the
+    /// section, one per subbeat in freestyle.
+    fn poses_at_subbeat(&self, subbeat: u32) -> u32 {
+        match self.step() {
+            Some(StepSection { step, pace, .. }) => pace.pose_at_subbeat(&step.durations, subbeat),
+            None => subbeat,
+        }
     }
 
     fn is_tracked(&self) -> bool {
@@ -302,3 +370,17 @@ impl Section {
         }
     }
 }
+
+impl StepSection {
+    fn subbeats(&self, meter: &Meter) -> u32 {
+        let subdivisions = meter.subdivisions();
+        match self.length {
+            SectionLength::Repeat(repeat) => {
+                let step_subbeats = self.pace.step_subbeats(&self.step.durations);
+                let beats = repeat * step_subbeats / subdivisions;
+                beats.max(1) * subdivisions
+            }
+            SectionLength::Beats(beats) => beats * subdivisions,
+        }
+    }
+}
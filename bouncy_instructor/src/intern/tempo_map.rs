@@ -0,0 +1,212 @@
+//! Maps between timestamps and beats, for music with a steady or a drifting
+//! tempo.
+
+use crate::BeatsError;
+
+type Timestamp = f64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TempoMap {
+    /// A steady tempo, with beats aligned to `first_beat`.
+    Constant { bpm: f32, first_beat: Timestamp },
+    /// Explicit beat timestamps, usually detected in or annotated on a song.
+    ///
+    /// Outside the listed beats, the tempo of the closest interval continues.
+    ///
+    /// invariant: at least two beats, strictly increasing
+    Beats(Vec<Timestamp>),
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        Self::Constant {
+            bpm: 120.0,
+            first_beat: 0.0,
+        }
+    }
+}
+
+impl TempoMap {
+    /// Fails if there are less than two beats or they are not strictly
+    /// increasing.
+    pub(crate) fn from_beats(beats: Vec<Timestamp>) -> Result<Self, BeatsError> {
+        if beats.len() < 2 {
+            return Err(BeatsError::TooFew(beats.len()));
+        }
+        if let Some(pair) = beats.windows(2).find(|pair| pair[0] >= pair[1]) {
+            return Err(BeatsError::NotIncreasing {
+                previous: pair[0],
+                next: pair[1],
+            });
+        }
+        Ok(Self::Beats(beats))
+    }
+
+    /// Same tempo with beats moved to be aligned to `first_beat`.
+    pub(crate) fn aligned(&self, first_beat: Timestamp) -> Self {
+        match self {
+            Self::Constant { bpm, .. } => Self::Constant {
+                bpm: *bpm,
+                first_beat,
+            },
+            Self::Beats(beats) => {
+                let offset = first_beat - beats[0];
+                Self::Beats(beats.iter().map(|t| t + offset).collect())
+            }
+        }
+    }
+
+    /// Same beat alignment with a steady tempo of `bpm`.
+    pub(crate) fn with_bpm(&self, bpm: f32) -> Self {
+        Self::Constant {
+            bpm,
+            first_beat: self.first_beat(),
+        }
+    }
+
+    pub(crate) fn first_beat(&self) -> Timestamp {
+        match self {
+            Self::Constant { first_beat, .. } => *first_beat,
+            Self::Beats(beats) => beats[0],
+        }
+    }
+
+    /// Tempo of the beat around `t`.
+    pub(crate) fn bpm_at(&self, t: Timestamp) -> f32 {
+        match self {
+            Self::Constant { bpm, .. } => *bpm,
+            Self::Beats(_) => (60_000.0 / self.beat_duration_at(t)) as f32,
+        }
+    }
+
+    /// Duration of the beat around `t`, in ms.
+    pub(crate) fn beat_duration_at(&self, t: Timestamp) -> f64 {
+        match self {
+            Self::Constant { bpm, .. } => 60_000.0 / *bpm as f64,
+            Self::Beats(beats) => {
+                let i = Self::interval_index(beats, t);
+                beats[i + 1] - beats[i]
+            }
+        }
+    }
+
+    /// Fractional number of beats since the first beat, negative before.
+    pub(crate) fn beat_at(&self, t: Timestamp) -> f64 {
+        match self {
+            Self::Constant { first_beat, .. } => (t - first_beat) / self.beat_duration_at(t),
+            Self::Beats(beats) => {
+                let i = Self::interval_index(beats, t);
+                i as f64 + (t - beats[i]) / (beats[i + 1] - beats[i])
+            }
+        }
+    }
+
+    /// Inverse of `beat_at`.
+    pub(crate) fn timestamp_at(&self, beat: f64) -> Timestamp {
+        match self {
+            Self::Constant { first_beat, .. } => first_beat + beat * self.beat_duration_at(0.0),
+            Self::Beats(beats) => {
+                let i = (beat.floor().max(0.0) as usize).min(beats.len() - 2);
+                beats[i] + (beat - i as f64) * (beats[i + 1] - beats[i])
+            }
+        }
+    }
+
+    /// The timestamp `beats` after `t`, following tempo changes.
+    pub(crate) fn add_beats(&self, t: Timestamp, beats: f64) -> Timestamp {
+        match self {
+            Self::Constant { .. } => t + beats * self.beat_duration_at(t),
+            Self::Beats(_) => self.timestamp_at(self.beat_at(t) + beats),
+        }
+    }
+
+    /// Fractional number of beats from `start` to `end`.
+    pub(crate) fn beats_between(&self, start: Timestamp, end: Timestamp) -> f64 {
+        match self {
+            Self::Constant { .. } => (end - start) / self.beat_duration_at(start),
+            Self::Beats(_) => self.beat_at(end) - self.beat_at(start),
+        }
+    }
+
+    /// Index of the beat interval used for `t`, the first or last interval
+    /// outside the listed beats.
+    fn interval_index(beats: &[Timestamp], t: Timestamp) -> usize {
+        beats
+            .partition_point(|beat| *beat <= t)
+            .saturating_sub(1)
+            .min(beats.len() - 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_tempo() {
+        let tempo = TempoMap::Constant {
+            bpm: 120.0,
+            first_beat: 100.0,
+        };
+        assert_eq!(tempo.beat_duration_at(5000.0), 500.0);
+        assert_eq!(tempo.beat_at(1100.0), 2.0);
+        assert_eq!(tempo.timestamp_at(1.5), 850.0);
+        assert_eq!(tempo.add_beats(700.0, 2.0), 1700.0);
+    }
+
+    #[test]
+    fn test_drifting_tempo() {
+        // 120 bpm, slowing down to 60 bpm
+        let tempo = TempoMap::from_beats(vec![0.0, 500.0, 1000.0, 2000.0, 3000.0]).unwrap();
+        assert_eq!(tempo.bpm_at(250.0), 120.0);
+        assert_eq!(tempo.bpm_at(1500.0), 60.0);
+        assert_eq!(tempo.beat_at(750.0), 1.5);
+        assert_eq!(tempo.beat_at(1500.0), 2.5);
+        assert_eq!(tempo.timestamp_at(2.5), 1500.0);
+        assert_eq!(tempo.add_beats(750.0, 1.0), 1500.0);
+        assert_eq!(tempo.beats_between(250.0, 2500.0), 3.0);
+    }
+
+    #[test]
+    fn test_tempo_continues_outside_beats() {
+        let tempo = TempoMap::from_beats(vec![1000.0, 1500.0, 2500.0]).unwrap();
+        assert_eq!(tempo.beat_at(750.0), -0.5);
+        assert_eq!(tempo.timestamp_at(-1.0), 500.0);
+        assert_eq!(tempo.beat_at(3500.0), 3.0);
+        assert_eq!(tempo.timestamp_at(4.0), 4500.0);
+        assert_eq!(tempo.bpm_at(5000.0), 60.0);
+    }
+
+    #[test]
+    fn test_aligned() {
+        let tempo = TempoMap::from_beats(vec![1000.0, 1500.0, 2500.0]).unwrap();
+        assert_eq!(
+            tempo.aligned(0.0),
+            TempoMap::Beats(vec![0.0, 500.0, 1500.0])
+        );
+        assert_eq!(
+            tempo.with_bpm(90.0),
+            TempoMap::Constant {
+                bpm: 90.0,
+                first_beat: 1000.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_beats() {
+        assert_eq!(TempoMap::from_beats(vec![]), Err(BeatsError::TooFew(0)));
+        assert_eq!(
+            TempoMap::from_beats(vec![1000.0]),
+            Err(BeatsError::TooFew(1))
+        );
+        assert!(TempoMap::from_beats(vec![1000.0, 1000.0]).is_err());
+        assert_eq!(
+            TempoMap::from_beats(vec![0.0, 1000.0, 500.0]),
+            Err(BeatsError::NotIncreasing {
+                previous: 1000.0,
+                next: 500.0
+            })
+        );
+    }
+}
@@ -8,13 +8,15 @@
 
 use super::dance::Dance;
 use super::geom::SignedAngle;
+use super::language::Language;
 use super::pose::{BodyPartOrdering, BodyPoint, Limb, LimbPosition, Pose, PoseDirection};
 use super::skeleton_3d::Direction;
 use super::step::{Step, StepSource};
+use crate::parsing::course_file::TranslatedString;
 use crate::parsing::ParseFileError;
 use crate::pose_file::PoseZ;
 use crate::skeleton::Cartesian2d;
-use crate::step_file::{self, Orientation};
+use crate::step_file::{self, BodyRegion, Orientation};
 use crate::{dance_file, pose_file, AddDanceError, AddStepError};
 
 /// List of registered poses/steps/dances to recognize during tracking.
@@ -32,8 +34,8 @@ pub(crate) struct TrackerDanceCollection {
     poses: Vec<Pose>,
     /// Pose ids, shares the index with `.poses`
     pose_ids: Vec<String>,
-    /// Translated pose names, shares the index with `.poses`
-    pose_names: Vec<String>,
+    /// Pose names in all available languages, shares the index with `.poses`
+    pose_names: Vec<Option<TranslatedString>>,
 
     /// list of limbs to track, referenced by `LimbPosition.limb`.
     ///
@@ -45,10 +47,9 @@ pub(crate) struct TrackerDanceCollection {
     steps: Vec<Step>,
     dances: Vec<Dance>,
 
-    /// Language for displayed strings, all names in this collection are in this
-    /// language, if available. To change the language, create a new collection
-    /// and load everything again from the definitions.
-    lang: String,
+    /// Language for displayed strings. All translations are kept, names are
+    /// resolved in this language when they are read.
+    lang: Language,
 }
 
 /// For accessing LimbPositionDatabase::limbs
@@ -70,13 +71,13 @@ impl Default for TrackerDanceCollection {
             limb_names: Limb::base_limb_names(),
             steps: Default::default(),
             dances: Default::default(),
-            lang: "en".to_owned(),
+            lang: Language::default(),
         }
     }
 }
 
 impl TrackerDanceCollection {
-    pub(crate) fn new(lang: String) -> Self {
+    pub(crate) fn new(lang: Language) -> Self {
         Self {
             lang,
             ..Default::default()
@@ -105,12 +106,7 @@ impl TrackerDanceCollection {
                 )
             };
             self.poses.push(new_pose);
-            self.pose_names.push(
-                pose.names
-                    .as_ref()
-                    .and_then(|translations| translations.get(&self.lang))
-                    .unwrap_or_else(|| pose.id.clone()),
-            );
+            self.pose_names.push(pose.names.clone());
             self.pose_ids.push(pose.id.clone());
         }
         Ok(())
@@ -153,7 +149,7 @@ impl TrackerDanceCollection {
         self.pose_ids
             .push(other.pose_id(foreign_pose_index).to_owned());
         self.pose_names
-            .push(other.pose_name(foreign_pose_index).to_owned());
+            .push(other.pose_names[foreign_pose_index].clone());
         new_index
     }
 
@@ -264,8 +260,16 @@ impl TrackerDanceCollection {
         &self.pose_ids[i]
     }
 
-    pub(crate) fn pose_name(&self, i: usize) -> &str {
-        &self.pose_names[i]
+    /// The pose name in the current language, falls back to the id.
+    pub(crate) fn pose_name(&self, i: usize) -> String {
+        self.lang
+            .translate_opt(self.pose_names[i].as_ref())
+            .unwrap_or_else(|| self.pose_ids[i].clone())
+    }
+
+    /// Language in which translated names of this collection are displayed.
+    pub(crate) fn lang(&self) -> &Language {
+        &self.lang
     }
 
     pub(crate) fn limbs(&self) -> impl Iterator<Item = (LimbIndex, &Limb)> {
@@ -317,6 +321,9 @@ impl TrackerDanceCollection {
         source: StepSource,
     ) -> Result<(), AddStepError> {
         for def in steps {
+            if !def.layers.is_empty() {
+                self.add_layered_poses(def)?;
+            }
             let new_step = self.load_step(def, &source)?;
             self.steps.push(new_step);
         }
@@ -333,6 +340,9 @@ impl TrackerDanceCollection {
         def: &step_file::Step,
         source: &StepSource,
     ) -> Result<Step, AddStepError> {
+        if !def.layers.is_empty() {
+            return self.load_layered_step(def, source);
+        }
         let poses = def
             .keyframes
             .iter()
@@ -362,19 +372,186 @@ impl TrackerDanceCollection {
             .iter()
             .map(|frame| frame.jump_height)
             .collect();
+        let durations = def
+            .keyframes
+            .iter()
+            .map(|frame| match frame.duration {
+                None => Ok(1.0),
+                Some(duration) if duration > 0.0 && duration.is_finite() => Ok(duration),
+                Some(duration) => Err(AddStepError::InvalidDuration {
+                    step: def.id.clone(),
+                    duration,
+                }),
+            })
+            .collect::<Result<_, _>>()?;
         let new_step = Step {
             id: def.id.clone(),
             name: def.name.clone(),
+            names: def.names.clone(),
             variation: def.variation.clone(),
+            variation_descriptions: def.variation_descriptions.clone(),
             poses,
             directions,
             pivots,
             jump_heights,
+            durations,
+            source: source.clone(),
+        };
+        Ok(new_step)
+    }
+
+    /// Load a step that merges the poses of its layers, which must have been
+    /// added with `add_layered_poses` before.
+    ///
+    /// Directions, pivots and jumps are those of the first layer, the region
+    /// that moves the body as a whole is expected there.
+    fn load_layered_step(
+        &self,
+        def: &step_file::Step,
+        source: &StepSource,
+    ) -> Result<Step, AddStepError> {
+        let layers = self.step_layers(def)?;
+        let (first, _) = layers[0];
+        let n = Self::num_layered_poses(&layers);
+        let poses = (0..n)
+            .map(|i| {
+                let id = self.layered_pose_id(&layers, i);
+                self.pose_by_id(&id).ok_or(AddStepError::MissingPose(id))
+            })
+            .collect::<Result<_, _>>()?;
+        let from_first = |i: usize| i % first.poses.len();
+        let new_step = Step {
+            id: def.id.clone(),
+            name: def.name.clone(),
+            names: def.names.clone(),
+            variation: def.variation.clone(),
+            variation_descriptions: def.variation_descriptions.clone(),
+            poses,
+            directions: (0..n).map(|i| first.directions[from_first(i)]).collect(),
+            pivots: (0..n).map(|i| first.pivots[from_first(i)]).collect(),
+            jump_heights: (0..n).map(|i| first.jump_heights[from_first(i)]).collect(),
+            durations: (0..n).map(|i| first.durations[from_first(i)]).collect(),
             source: source.clone(),
         };
         Ok(new_step)
     }
 
+    /// Create the merged poses needed by a layered step, unless they exist
+    /// already from another step with the same layers.
+    fn add_layered_poses(&mut self, def: &step_file::Step) -> Result<(), AddStepError> {
+        let layers = self.step_layers(def)?;
+        let mut new_poses = vec![];
+        for i in 0..Self::num_layered_poses(&layers) {
+            let id = self.layered_pose_id(&layers, i);
+            if self.pose_by_id(&id).is_some() || new_poses.iter().any(|(known, _)| *known == id) {
+                continue;
+            }
+            let parts: Vec<(usize, BodyRegion)> = layers
+                .iter()
+                .map(|(step, region)| (step.poses[i % step.poses.len()], *region))
+                .collect();
+            new_poses.push((id, self.merged_pose(&parts)));
+        }
+        for (id, pose) in new_poses {
+            self.poses.push(pose);
+            self.pose_names.push(None);
+            self.pose_ids.push(id);
+        }
+        Ok(())
+    }
+
+    /// Referenced steps of all layers, with the region they contribute.
+    ///
+    /// Poses are merged by their index, hence all layers must hold their poses
+    /// equally long. Timing then comes from the first layer.
+    fn step_layers(&self, def: &step_file::Step) -> Result<Vec<(&Step, BodyRegion)>, AddStepError> {
+        if !def.keyframes.is_empty() {
+            return Err(AddStepError::LayersWithKeyframes(def.id.clone()));
+        }
+        let layers = def
+            .layers
+            .iter()
+            .map(|layer| {
+                self.step(&layer.step)
+                    .filter(|step| !step.poses.is_empty())
+                    .map(|step| (step, layer.region))
+                    .ok_or_else(|| AddStepError::MissingLayerStep(layer.step.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let duration = |step: &Step, i: usize| step.durations[i % step.durations.len()];
+        let (first, _) = layers[0];
+        let aligned = (0..Self::num_layered_poses(&layers)).all(|i| {
+            layers
+                .iter()
+                .all(|(step, _)| duration(step, i) == duration(first, i))
+        });
+        if !aligned {
+            return Err(AddStepError::LayerDurations(def.id.clone()));
+        }
+        Ok(layers)
+    }
+
+    /// Layers repeat until all of them end on the same beat.
+    fn num_layered_poses(layers: &[(&Step, BodyRegion)]) -> usize {
+        fn gcd(a: usize, b: usize) -> usize {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+        layers
+            .iter()
+            .map(|(step, _)| step.poses.len())
+            .fold(1, |lcm, n| lcm / gcd(lcm, n) * n)
+    }
+
+    fn layered_pose_id(&self, layers: &[(&Step, BodyRegion)], i: usize) -> String {
+        layers
+            .iter()
+            .map(|(step, region)| {
+                let pose = step.poses[i % step.poses.len()];
+                format!("{}[{region:?}]", self.pose_id(pose))
+            })
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    /// Combine the limbs of each pose in its region into one pose.
+    ///
+    /// Direction, shift and hip turn come from the first pose, the shoulder
+    /// turn from the pose for the upper body.
+    fn merged_pose(&self, parts: &[(usize, BodyRegion)]) -> Pose {
+        let mut merged = self.poses[parts[0].0].clone();
+        merged.limbs.clear();
+        merged.z_absolute.clear();
+        merged.z_order.clear();
+        for &(pose_index, region) in parts {
+            let pose = &self.poses[pose_index];
+            merged.limbs.extend(
+                pose.limbs
+                    .iter()
+                    .filter(|position| self.limbs[position.limb.0].region() == region)
+                    .cloned(),
+            );
+            merged.z_absolute.extend(
+                pose.z_absolute
+                    .iter()
+                    .filter(|(point, _)| point.part.region() == region),
+            );
+            merged.z_order.extend(
+                pose.z_order
+                    .iter()
+                    .filter(|ordering| ordering.forward.part.region() == region)
+                    .cloned(),
+            );
+            if region == BodyRegion::UpperBody {
+                merged.turn_shoulder = pose.turn_shoulder;
+            }
+        }
+        merged
+    }
+
     /// Copies a step from a different dance collection, including poses.
     pub(crate) fn add_foreign_step(
         &mut self,
@@ -396,11 +573,14 @@ impl TrackerDanceCollection {
         let new_step = Step {
             id: step.id.clone(),
             name: step.name.clone(),
+            names: step.names.clone(),
             variation: step.variation.clone(),
+            variation_descriptions: step.variation_descriptions.clone(),
             poses,
             directions: step.directions.clone(),
             pivots: step.pivots.clone(),
             jump_heights: step.jump_heights.clone(),
+            durations: step.durations.clone(),
             source: step.source.clone(),
         };
         self.steps.push(new_step);
@@ -457,11 +637,13 @@ impl TrackerDanceCollection {
             Default::default(),
             Default::default(),
         )];
+        let mut name = TranslatedString::default();
+        name.set("en".to_owned(), "Test Pose".to_owned());
 
         Self {
             poses,
             pose_ids: vec!["test_pose".into()],
-            pose_names: vec!["Test Pose".into()],
+            pose_names: vec![Some(name)],
             limbs: vec![pose_file::Limb::LeftThigh.into()],
             limb_names: vec!["test_limb".into()],
             ..Default::default()
@@ -0,0 +1,137 @@
+//! Comparing how far a dancer travels to how far a step moves the body.
+//!
+//! Expected movement comes from `BodyShift`, in the coordinates of rendered
+//! skeletons. Actual movement is the horizontal hip displacement in keypoints.
+//! Both are normalized by the body height before comparing them, for keypoints
+//! after converting the horizontal distance to the vertical scale of the image.
+
+use crate::renderable::RenderableSkeleton;
+use crate::tracker::TravelHint;
+use crate::Keypoints;
+
+type Timestamp = f64;
+
+/// Shoulder to ankle of a rendered skeleton.
+const RENDERED_BODY_HEIGHT: f32 =
+    RenderableSkeleton::TORSO_LEN + RenderableSkeleton::THIGH_LEN + RenderableSkeleton::SHIN_LEN;
+/// Deviation in body heights that counts as completely wrong.
+const FULL_ERROR_DISTANCE: f32 = 0.5;
+
+/// How well the dancer traveled during a step.
+///
+/// Distances are horizontal, in body heights, positive to the right as seen
+/// by the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TravelScore {
+    pub expected: f32,
+    pub measured: f32,
+    /// Between 0.0 and 1.0
+    pub error: f32,
+}
+
+impl TravelScore {
+    pub(crate) fn new(expected: f32, measured: f32) -> Self {
+        let error = ((measured - expected).abs() / FULL_ERROR_DISTANCE).min(1.0);
+        Self {
+            expected,
+            measured,
+            error,
+        }
+    }
+
+    /// Compare the hip movement between the frames at `start` and `end` to
+    /// `expected_shift`, given in rendered skeleton coordinates.
+    ///
+    /// Returns `None` if there are no frames or the body is invisible.
+    pub(crate) fn between(
+        expected_shift: f32,
+        aspect_ratio: f32,
+        timestamps: &[Timestamp],
+        keypoints: &[Keypoints],
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Option<Self> {
+        let first = timestamps.partition_point(|t| *t < start);
+        let last = timestamps.partition_point(|t| *t <= end);
+        let frames = keypoints.get(first..last)?;
+        let measured = measured_travel(frames, aspect_ratio)?;
+        Some(Self::new(expected_shift / RENDERED_BODY_HEIGHT, measured))
+    }
+
+    /// Which way to go to fix the error, if it is above `threshold`.
+    pub(crate) fn hint(&self, threshold: f32) -> Option<TravelHint> {
+        if self.error <= threshold {
+            None
+        } else if self.measured < self.expected {
+            Some(TravelHint::FurtherRight)
+        } else {
+            Some(TravelHint::FurtherLeft)
+        }
+    }
+}
+
+/// Horizontal hip movement from the first to the last frame, in body heights.
+///
+/// `aspect_ratio` is the width divided by the height of the image, keypoints
+/// are normalized by the width on x and by the height on y.
+///
+/// Returns `None` for no frames or an invisible body.
+pub(crate) fn measured_travel(keypoints: &[Keypoints], aspect_ratio: f32) -> Option<f32> {
+    let (first, last) = (keypoints.first()?, keypoints.last()?);
+    let hips_x = |kp: &Keypoints| (kp.left.hip.x + kp.right.hip.x) / 2.0;
+    let body_height = |kp: &Keypoints| {
+        let ankles = (kp.left.ankle.y + kp.right.ankle.y) / 2.0;
+        let shoulders = (kp.left.shoulder.y + kp.right.shoulder.y) / 2.0;
+        ankles - shoulders
+    };
+    let body_height = keypoints.iter().map(body_height).sum::<f32>() / keypoints.len() as f32;
+    if body_height <= 0.0 {
+        return None;
+    }
+    Some((hips_x(last) - hips_x(first)) * aspect_ratio / body_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::standing_keypoints;
+
+    /// Standing keypoints with a body height of 1.0, moved right by `x`.
+    fn standing(x: f32) -> Keypoints {
+        standing_keypoints(x, 0.5, 1.0)
+    }
+
+    #[test]
+    fn test_measured_travel() {
+        let frames = [0.0, 0.1, 0.3].map(standing);
+        assert_eq!(measured_travel(&frames, 1.0), Some(0.3));
+        assert_eq!(measured_travel(&[], 1.0), None);
+
+        // in a landscape video, x covers more pixels than y
+        let travel = measured_travel(&frames, 16.0 / 9.0).unwrap();
+        assert!((travel - 0.3 * 16.0 / 9.0).abs() < 1e-6, "{travel}");
+    }
+
+    #[test]
+    fn test_travel_hint() {
+        // expected to go right but stayed on the spot
+        let score = TravelScore::new(0.3, 0.0);
+        assert!((score.error - 0.6).abs() < 1e-6, "{score:?}");
+        assert_eq!(score.hint(0.5), Some(TravelHint::FurtherRight));
+        assert_eq!(score.hint(0.7), None);
+
+        let score = TravelScore::new(0.0, 0.6);
+        assert_eq!(score.error, 1.0);
+        assert_eq!(score.hint(0.5), Some(TravelHint::FurtherLeft));
+    }
+
+    #[test]
+    fn test_travel_between() {
+        let frames = [0.0, 0.1, 0.2, 0.3].map(standing);
+        let timestamps = [0.0, 100.0, 200.0, 300.0];
+        let shift = 0.2 * RENDERED_BODY_HEIGHT;
+        let score = TravelScore::between(shift, 1.0, &timestamps, &frames, 100.0, 300.0).unwrap();
+        assert!((score.measured - 0.2).abs() < 1e-6, "{score:?}");
+        assert!(score.error < 1e-5, "{score:?}");
+    }
+}
@@ -8,7 +8,9 @@ mod test_utils;
 pub use public::*;
 
 use intern::content_collection::ContentCollection;
+use intern::content_pack::{LoadedPack, PackRegistry};
 use intern::step::{Step, StepSource};
+use public::parsing::pack_file::PackFile;
 use public::parsing::ParseFileError;
 use std::cell::RefCell;
 
@@ -17,16 +19,22 @@ use std::cell::RefCell;
 struct State {
     /// The global collection of poses/steps/dances/courses.
     global_db: ContentCollection,
+    /// Content packs loaded into the global collection.
+    packs: PackRegistry,
+    /// Thresholds for new trackers.
+    detector_config: DetectorConfig,
 }
 thread_local! {
     static STATE: RefCell<State> =
         State {
             global_db: Default::default(),
+            packs: Default::default(),
+            detector_config: Default::default(),
         }.into();
 }
 
 impl State {
-    fn reset_language(&mut self, lang: String) {
+    fn set_language(&self, lang: String) {
         self.global_db.set_language(lang);
     }
 
@@ -49,6 +57,14 @@ impl State {
         self.global_db.add_dances(dances)
     }
 
+    fn load_pack(&mut self, pack: PackFile) -> Result<&LoadedPack, PackError> {
+        self.packs.load(pack, &mut self.global_db)
+    }
+
+    fn unload_pack(&mut self, pack_id: &str) -> Result<(), PackError> {
+        self.packs.unload(pack_id, &mut self.global_db)
+    }
+
     fn step(&self, id: &str) -> Option<&Step> {
         self.global_db.tracker_view.step(id)
     }
@@ -57,12 +73,22 @@ impl State {
 #[derive(Debug)]
 enum AddStepError {
     MissingPose(String),
+    InvalidDuration { step: String, duration: f32 },
+    MissingLayerStep(String),
+    LayersWithKeyframes(String),
+    LayerDurations(String),
 }
 
 impl From<AddStepError> for ParseFileError {
     fn from(error: AddStepError) -> Self {
         match error {
             AddStepError::MissingPose(id) => Self::UnknownPoseReference(id),
+            AddStepError::InvalidDuration { step, duration } => {
+                Self::InvalidDuration { step, duration }
+            }
+            AddStepError::MissingLayerStep(id) => Self::UnknownStepName(id),
+            AddStepError::LayersWithKeyframes(id) => Self::LayersWithKeyframes(id),
+            AddStepError::LayerDurations(id) => Self::LayerDurations(id),
         }
     }
 }
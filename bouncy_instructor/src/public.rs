@@ -1,10 +1,17 @@
 //! Wrapper module for all types and methods that are exported by the WASM
 //! module.
-mod course;
+pub(crate) mod beat_grid;
+pub(crate) mod content_pack;
+pub(crate) mod course;
 pub(crate) mod dance_info;
+pub(crate) mod detector_config;
 pub(crate) mod editor;
 pub(crate) mod keypoints;
+pub(crate) mod loop_config;
+pub(crate) mod meter;
 pub(crate) mod parsing;
+pub(crate) mod practice_scheduler;
+pub(crate) mod progress;
 pub(crate) mod renderable;
 pub(crate) mod skeleton;
 pub(crate) mod step_info;
@@ -15,11 +22,20 @@ pub(crate) mod wrapper;
 
 use crate::intern::step::StepSource;
 pub use crate::public::course::Course;
+pub use beat_grid::{BeatGrid, BeatsError};
+pub use content_pack::{ContentPackInfo, PackError};
 pub use dance_info::DanceInfo;
-pub use keypoints::{Keypoints, Side as KeypointsSide};
+pub use detector_config::DetectorConfig;
+pub use keypoints::{Cartesian3d, Keypoints, Side as KeypointsSide};
+pub use loop_config::LoopConfig;
+pub use meter::{Meter, MeterError};
+pub use practice_scheduler::PracticeScheduler;
+pub use progress::{UnlockStatus, UserProgress};
 pub use skeleton::Cartesian2d;
 pub use step_info::StepInfo;
-pub use tracker::{DetectionFailureReason, DetectionResult, PoseHint, Tracker};
+pub use tracker::{
+    DanceCursor, DetectionFailureReason, DetectionResult, PoseApproximation, PoseHint, Tracker,
+};
 pub use ui_event::{AudioEffect, TextEffect};
 pub use video_def::VideoDef;
 
@@ -31,6 +47,7 @@ use crate::intern::lfsr;
 use editor::dance_builder::DanceBuilder;
 use parsing::course_file::CourseFile;
 use parsing::dance_file::DanceFile;
+use parsing::pack_file::PackFile;
 use parsing::pose_file::PoseFile;
 use parsing::step_file::StepFile;
 use parsing::ParseFileError;
@@ -48,10 +65,19 @@ pub fn init(random_seed: u32, lang: String) -> Result<(), JsValue> {
         return Err("random seed must not be 0".into());
     }
     lfsr::init(random_seed);
-    STATE.with(|state| state.borrow_mut().reset_language(lang));
+    STATE.with_borrow(|state| state.set_language(lang));
     Ok(())
 }
 
+/// Switch the display language of all loaded poses, steps and dances.
+///
+/// Existing objects are updated in place, there is no need to reload any
+/// files. Courses have their own language, see `Course.setLanguage`.
+#[wasm_bindgen(js_name = setLanguage)]
+pub fn set_language(lang: String) {
+    STATE.with_borrow(|state| state.set_language(lang));
+}
+
 #[wasm_bindgen(js_name = loadPoseFile)]
 pub async fn load_pose_file(url: &str) -> Result<(), JsValue> {
     let text = load_text_file(url).await?;
@@ -140,6 +166,20 @@ pub fn dances() -> Vec<DanceWrapper> {
     STATE.with_borrow(|state| state.global_db.dances().cloned().collect::<Vec<_>>())
 }
 
+/// List all translations missing in the loaded poses, steps, and dances for
+/// the given languages, as human readable messages.
+#[wasm_bindgen(js_name = "lintTranslations")]
+pub fn lint_translations(langs: Vec<String>) -> Vec<String> {
+    STATE.with_borrow(|state| {
+        state
+            .global_db
+            .missing_translations(&langs)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    })
+}
+
 #[wasm_bindgen(js_name = "danceBuilderFromDance")]
 pub fn dance_builder_from_dance(dance_id: String) -> Result<DanceBuilder, String> {
     STATE.with_borrow(|state| {
@@ -174,6 +214,63 @@ pub fn load_dance_str(text: &str) -> Result<(), ParseFileError> {
     Ok(())
 }
 
+/// Set the detector config used by all trackers created afterwards.
+#[wasm_bindgen(js_name = loadDetectorConfig)]
+pub fn load_detector_config(text: &str) -> Result<(), ParseFileError> {
+    let config = DetectorConfig::from_ron(text)?;
+    STATE.with(|state| state.borrow_mut().detector_config = config);
+    Ok(())
+}
+
+/// Load a content pack into the global collection.
+///
+/// All ids of the pack are prefixed with the pack id and a `/`.
+#[wasm_bindgen(js_name = loadContentPack)]
+pub fn load_content_pack(text: &str) -> Result<ContentPackInfo, PackError> {
+    let parsed = PackFile::from_str(text)?;
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let pack = state.load_pack(parsed)?;
+        Ok(pack.clone().into())
+    })
+}
+
+/// Remove all content of a pack from the global collection again.
+#[wasm_bindgen(js_name = unloadContentPack)]
+pub fn unload_content_pack(pack_id: &str) -> Result<(), PackError> {
+    STATE.with(|state| state.borrow_mut().unload_pack(pack_id))
+}
+
+#[wasm_bindgen(js_name = contentPacks)]
+pub fn content_packs() -> Vec<ContentPackInfo> {
+    STATE.with_borrow(|state| {
+        state
+            .packs
+            .packs()
+            .iter()
+            .cloned()
+            .map(ContentPackInfo::from)
+            .collect()
+    })
+}
+
+/// Parse the courses of a loaded pack.
+#[wasm_bindgen(js_name = contentPackCourses)]
+pub fn content_pack_courses(pack_id: &str, lang: &str) -> Result<Vec<Course>, PackError> {
+    let courses = STATE.with_borrow(|state| {
+        state
+            .packs
+            .pack(pack_id)
+            .map(|pack| pack.courses.clone())
+            .ok_or_else(|| PackError::NotLoaded(pack_id.to_owned()))
+    })?;
+    let courses = courses
+        .into_iter()
+        .map(|course| course.into_course(lang))
+        .collect::<Result<_, _>>()?;
+    Ok(courses)
+}
+
 pub fn parse_course_str(text: &str, lang: &str) -> Result<Course, ParseFileError> {
     let parsed = CourseFile::from_str(text)?;
     parsed.into_course(lang)
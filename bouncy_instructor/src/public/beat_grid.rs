@@ -0,0 +1,116 @@
+//! Tempo and beats of a song or video, detected from its audio.
+
+use crate::editor::ExportError;
+use crate::intern::beat_tracker::{self, track_beats};
+use crate::parsing::video_def::{Marker, VideoDef};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+/// Beat timestamps that a tracker cannot follow.
+#[derive(Error, Debug, PartialEq)]
+pub enum BeatsError {
+    #[error("at least two beats are needed, found {0}")]
+    TooFew(usize),
+    #[error("beats must be strictly increasing, found {next} after {previous}")]
+    NotIncreasing { previous: f64, next: f64 },
+}
+
+impl From<BeatsError> for JsValue {
+    fn from(value: BeatsError) -> Self {
+        format!("{value}").into()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatGrid {
+    pub bpm: f32,
+    /// Time of the first beat in ms.
+    #[wasm_bindgen(js_name = firstBeat)]
+    pub first_beat: f64,
+    beats: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl BeatGrid {
+    /// Detect beats in mono PCM samples, as provided by
+    /// `AudioBuffer.getChannelData()`.
+    ///
+    /// Returns `undefined` if no steady beat was found.
+    #[wasm_bindgen(js_name = fromSamples)]
+    pub fn from_samples(samples: &[f32], sample_rate: f32) -> Option<BeatGrid> {
+        Self::from_samples_in_range(
+            samples,
+            sample_rate,
+            beat_tracker::MIN_BPM,
+            beat_tracker::MAX_BPM,
+        )
+    }
+
+    /// Like `fromSamples` but only considers tempi in the given range.
+    ///
+    /// Use this when the detected tempo is half or double of what it should
+    /// be.
+    #[wasm_bindgen(js_name = fromSamplesInRange)]
+    pub fn from_samples_in_range(
+        samples: &[f32],
+        sample_rate: f32,
+        min_bpm: f32,
+        max_bpm: f32,
+    ) -> Option<BeatGrid> {
+        let beats = track_beats(samples, sample_rate, min_bpm, max_bpm)?;
+        Some(Self {
+            bpm: beats.bpm,
+            first_beat: beats.beats[0],
+            beats: beats.beats,
+        })
+    }
+
+    /// All detected beats in ms.
+    pub fn beats(&self) -> Vec<f64> {
+        self.beats.clone()
+    }
+
+    /// RON definition of a video with these beats, in the format of course
+    /// files, with a start marker on the first beat.
+    #[wasm_bindgen(js_name = videoDefRon)]
+    pub fn video_def_ron(&self, path: String) -> Result<String, ExportError> {
+        let beats: Vec<u32> = self.beats.iter().map(|t| t.round() as u32).collect();
+        let markers = BTreeMap::from([(beats[0], Marker::Start)]);
+        let video = VideoDef::Full {
+            path,
+            beats,
+            markers,
+        };
+        let string = ron::ser::to_string_pretty(&video, ron::ser::PrettyConfig::default())?;
+        Ok(string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_video_def_ron() {
+        let grid = BeatGrid {
+            bpm: 120.0,
+            first_beat: 250.4,
+            beats: vec![250.4, 750.2, 1250.0],
+        };
+        let text = grid.video_def_ron("song.mp3".to_owned()).unwrap();
+        let VideoDef::Full {
+            path,
+            beats,
+            markers,
+        } = ron::from_str(&text).unwrap()
+        else {
+            panic!("expected full video definition, got {text}");
+        };
+        assert_eq!(path, "song.mp3");
+        assert_eq!(beats, vec![250, 750, 1250]);
+        assert_eq!(markers, BTreeMap::from([(250, Marker::Start)]));
+    }
+}
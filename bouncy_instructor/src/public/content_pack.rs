@@ -0,0 +1,108 @@
+//! WASM interface for content packs, see
+//! [`PackFile`](crate::parsing::pack_file::PackFile) for the format.
+
+use crate::intern::content_pack::LoadedPack;
+use crate::parsing::ParseFileError;
+use thiserror::Error;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+#[derive(Error, Debug)]
+pub enum PackError {
+    #[error(transparent)]
+    Parse(#[from] ParseFileError),
+    #[error("invalid id `{0}`, ids in packs must not contain `/`")]
+    InvalidId(String),
+    #[error("pack `{0}` is already loaded")]
+    AlreadyLoaded(String),
+    #[error("pack `{0}` is not loaded")]
+    NotLoaded(String),
+    #[error("pack `{pack}` requires `{dependency}` in version {min_version} or newer")]
+    MissingDependency {
+        pack: String,
+        dependency: String,
+        min_version: u32,
+    },
+    #[error("pack `{pack}` references `{id}` without declaring a dependency on its pack")]
+    UndeclaredDependency { pack: String, id: String },
+    #[error("pack `{pack}` is still required by `{dependent}`")]
+    StillRequired { pack: String, dependent: String },
+    #[error("pack `{pack}` is still referenced by other content: {error}")]
+    StillReferenced {
+        pack: String,
+        error: Box<ParseFileError>,
+    },
+    #[error("`{0}` is defined more than once")]
+    DuplicateId(String),
+}
+
+impl From<PackError> for JsValue {
+    fn from(value: PackError) -> Self {
+        format!("{value}").into()
+    }
+}
+
+/// Summary of a loaded content pack.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ContentPackInfo {
+    pack: LoadedPack,
+}
+
+#[wasm_bindgen]
+impl ContentPackInfo {
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.pack.id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u32 {
+        self.pack.version
+    }
+
+    /// Ids of the packs this pack depends on.
+    #[wasm_bindgen(getter)]
+    pub fn dependencies(&self) -> Vec<String> {
+        self.pack.dependencies.clone()
+    }
+
+    /// Qualified ids of all poses in the pack.
+    #[wasm_bindgen(getter, js_name = "poseIds")]
+    pub fn pose_ids(&self) -> Vec<String> {
+        self.pack.pose_ids.clone()
+    }
+
+    /// Qualified ids of all steps in the pack.
+    #[wasm_bindgen(getter, js_name = "stepIds")]
+    pub fn step_ids(&self) -> Vec<String> {
+        self.pack.step_ids.clone()
+    }
+
+    /// Qualified ids of all dances in the pack.
+    #[wasm_bindgen(getter, js_name = "danceIds")]
+    pub fn dance_ids(&self) -> Vec<String> {
+        self.pack.dance_ids.clone()
+    }
+
+    /// Qualified ids of all courses in the pack.
+    #[wasm_bindgen(getter, js_name = "courseIds")]
+    pub fn course_ids(&self) -> Vec<String> {
+        self.pack.courses.iter().map(|c| c.id.clone()).collect()
+    }
+
+    /// Ids defined by the pack which also exist outside of it.
+    ///
+    /// Those don't collide, thanks to the namespace of the pack, but they
+    /// are likely a mistake or at least confusing.
+    #[wasm_bindgen(getter, js_name = "shadowedIds")]
+    pub fn shadowed_ids(&self) -> Vec<String> {
+        self.pack.shadowed_ids.clone()
+    }
+}
+
+impl From<LoadedPack> for ContentPackInfo {
+    fn from(pack: LoadedPack) -> Self {
+        Self { pack }
+    }
+}
@@ -1,38 +1,45 @@
 use super::parsing::ParseFileError;
 use crate::intern::content_collection::ContentCollection;
+use crate::intern::language::Language;
+use crate::intern::loop_practice::LoopPart;
 use crate::intern::step_pace::StepPace;
 use crate::intern::teacher::Teacher;
 use crate::intern::tracker_dance_collection::TrackerDanceCollection;
+use crate::parsing::course_file::TranslatedString;
 use crate::wrapper::step_wrapper::StepWrapper;
-use crate::{parsing, Tracker, VideoDef};
+use crate::{parsing, LoopConfig, Meter, Tracker, VideoDef};
 use std::rc::Rc;
 use wasm_bindgen::prelude::wasm_bindgen;
 
-#[derive(Debug)]
 #[wasm_bindgen]
 pub struct Course {
     pub(crate) id: String,
-    pub(crate) name: String,
-    pub(crate) explanation: Option<String>,
+    pub(crate) names: TranslatedString,
+    pub(crate) explanations: Option<TranslatedString>,
     pub(crate) featured_step_id: String,
     pub(crate) video: Option<VideoDef>,
+    pub(crate) prerequisites: Vec<Prerequisite>,
     pub(crate) lessons: Vec<Lesson>,
     pub(crate) collection: ContentCollection,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 #[wasm_bindgen]
 pub struct Lesson {
-    pub(crate) name: String,
-    pub(crate) explanation: Option<String>,
+    pub(crate) names: TranslatedString,
+    pub(crate) explanations: Option<TranslatedString>,
+    /// Shared with the collection of the course.
+    pub(crate) lang: Language,
     pub(crate) explainer_video: Option<VideoDef>,
     pub(crate) front_video: Option<VideoDef>,
     pub(crate) back_video: Option<VideoDef>,
     pub(crate) song: Option<String>,
     pub(crate) song_timestamp: Option<f64>,
+    pub(crate) meter: Meter,
     pub energy: u8,
     pub difficulty: u8,
     pub(crate) parts: Vec<LessonPart>,
+    pub(crate) prerequisites: Vec<Prerequisite>,
 }
 
 #[derive(Clone)]
@@ -56,6 +63,20 @@ pub(crate) enum TrackingKind {
     Untracked,
 }
 
+/// Something that must be mastered before a course or lesson unlocks.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Prerequisite {
+    Lesson {
+        course_id: String,
+        lesson_index: usize,
+        min_hit_ratio: f32,
+    },
+    Step {
+        step_id: String,
+        min_hit_ratio: f32,
+    },
+}
+
 #[derive(Debug)]
 pub(crate) enum CourseError {
     MissingStep(String),
@@ -70,12 +91,24 @@ impl Course {
 
     #[wasm_bindgen(getter)]
     pub fn name(&self) -> String {
-        self.name.clone()
+        self.collection
+            .lang()
+            .translate(&self.names)
+            .expect("checked when parsing")
     }
 
     #[wasm_bindgen(getter)]
     pub fn explanation(&self) -> Option<String> {
-        self.explanation.clone()
+        self.collection
+            .lang()
+            .translate_opt(self.explanations.as_ref())
+    }
+
+    /// Switch the display language of the course, including its lessons and
+    /// steps, without parsing it again.
+    #[wasm_bindgen(js_name = "setLanguage")]
+    pub fn set_language(&self, lang: String) {
+        self.collection.set_language(lang);
     }
 
     #[wasm_bindgen(getter)]
@@ -106,6 +139,26 @@ impl Course {
         })
     }
 
+    /// Practice the parts `start..end` of a lesson in a loop.
+    ///
+    /// Returns `None` if the lesson does not exist or the range is empty or
+    /// out of bounds.
+    #[wasm_bindgen(js_name = "loopTracker")]
+    pub fn loop_tracker(
+        &self,
+        lesson_index: usize,
+        start: usize,
+        end: usize,
+        config: &LoopConfig,
+    ) -> Option<Tracker> {
+        self.lessons.get(lesson_index)?.loop_tracker(
+            self.collection.tracker_view.clone(),
+            start,
+            end,
+            config,
+        )
+    }
+
     /// WIP: Create a training session for the given course. At the moment, it
     /// is hard coded to give something for testing.
     #[wasm_bindgen(js_name = "trainingTracker")]
@@ -140,12 +193,14 @@ impl Course {
 impl Lesson {
     #[wasm_bindgen(getter)]
     pub fn name(&self) -> String {
-        self.name.clone()
+        self.lang
+            .translate(&self.names)
+            .expect("checked when parsing")
     }
 
     #[wasm_bindgen(getter)]
     pub fn explanation(&self) -> Option<String> {
-        self.explanation.clone()
+        self.lang.translate_opt(self.explanations.as_ref())
     }
 
     #[wasm_bindgen(getter, js_name = "explainerVideo")]
@@ -173,6 +228,11 @@ impl Lesson {
         self.song_timestamp.unwrap_or(0.0)
     }
 
+    #[wasm_bindgen(getter)]
+    pub fn meter(&self) -> Meter {
+        self.meter
+    }
+
     #[wasm_bindgen(getter)]
     pub fn parts(&self) -> Vec<LessonPart> {
         self.parts.clone()
@@ -183,6 +243,7 @@ impl Lesson {
     pub(crate) fn tracker(&self, db: impl Into<Rc<TrackerDanceCollection>>) -> crate::Tracker {
         let db = db.into();
         let mut teacher = Teacher::default();
+        teacher.set_meter(self.meter);
 
         for part in &self.parts {
             let step = &part.step_wrapper;
@@ -196,6 +257,27 @@ impl Lesson {
 
         Tracker::new_from_teacher(db, teacher)
     }
+
+    pub(crate) fn loop_tracker(
+        &self,
+        db: Rc<TrackerDanceCollection>,
+        start: usize,
+        end: usize,
+        config: &LoopConfig,
+    ) -> Option<crate::Tracker> {
+        let parts: Vec<LoopPart> = self
+            .parts
+            .get(start..end)?
+            .iter()
+            .map(|part| LoopPart {
+                step: part.step_wrapper.info(&db),
+                repeat: part.repeat,
+                pace: part.pace,
+                tracked: part.tracking == TrackingKind::Tracked,
+            })
+            .collect();
+        Tracker::new_loop(db, &parts, config, self.meter)
+    }
 }
 
 #[wasm_bindgen]
@@ -252,6 +334,42 @@ impl From<CourseError> for ParseFileError {
     }
 }
 
+// Debug output shows names in the current language, rather than all
+// translations in random order.
+impl std::fmt::Debug for Course {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Course")
+            .field("id", &self.id)
+            .field("name", &self.name())
+            .field("explanation", &self.explanation())
+            .field("featured_step_id", &self.featured_step_id)
+            .field("video", &self.video)
+            .field("prerequisites", &self.prerequisites)
+            .field("lessons", &self.lessons)
+            .field("collection", &self.collection)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for Lesson {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Lesson")
+            .field("name", &self.name())
+            .field("explanation", &self.explanation())
+            .field("explainer_video", &self.explainer_video)
+            .field("front_video", &self.front_video)
+            .field("back_video", &self.back_video)
+            .field("song", &self.song)
+            .field("song_timestamp", &self.song_timestamp)
+            .field("meter", &self.meter)
+            .field("energy", &self.energy)
+            .field("difficulty", &self.difficulty)
+            .field("parts", &self.parts)
+            .field("prerequisites", &self.prerequisites)
+            .finish()
+    }
+}
+
 impl std::fmt::Debug for LessonPart {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("LessonPart")
@@ -10,7 +10,7 @@ use crate::{StepInfo, STATE};
 #[derive(Debug, Clone)]
 pub struct DanceInfo {
     pub(crate) steps: Vec<StepInfo>,
-    /// invariant: total_beats is the sum of all steps beat lengths
+    /// invariant: total_subbeats is the sum of all steps subbeat lengths
     pub(crate) total_subbeats: usize,
     body_shift: BodyShift,
 }
@@ -25,6 +25,22 @@ impl DanceInfo {
     }
 
     pub fn skeleton(&self, beat: usize) -> Option<Skeleton> {
+        self.step_at_subbeat(beat)
+            .map(|(step, offset)| step.skeleton(step.pose_at_subbeat(offset)))
+    }
+
+    /// The number of subbeats the dance takes for one repetition.
+    pub fn subbeats(&self) -> usize {
+        self.total_subbeats
+    }
+
+    /// How much the body position deviates from the origin.
+    pub fn body_shift(&self, beat: usize) -> Cartesian2d {
+        self.body_shift.after_pose(self.pose_at_subbeat(beat))
+    }
+
+    /// The step shown on the given subbeat and the subbeat within it.
+    fn step_at_subbeat(&self, beat: usize) -> Option<(&StepInfo, usize)> {
         if self.steps.is_empty() {
             return None;
         }
@@ -32,23 +48,33 @@ impl DanceInfo {
         let mut offset = beat % self.total_subbeats;
 
         for step in &self.steps {
-            if step.num_poses() <= offset {
-                offset -= step.num_poses();
+            if step.subbeats() <= offset {
+                offset -= step.subbeats();
             } else {
-                return Some(step.skeleton(offset));
+                return Some((step, offset));
             }
         }
-        unreachable!("must find a skeleton");
-    }
-
-    /// The number of subbeats the dance takes for one repetition.
-    pub fn subbeats(&self) -> usize {
-        self.total_subbeats
+        unreachable!("must find a step");
     }
 
-    /// How much the body position deviates from the origin.
-    pub fn body_shift(&self, beat: usize) -> Cartesian2d {
-        self.body_shift.after_pose(beat)
+    /// The index of the pose shown on the given subbeat, counting the poses
+    /// of all steps and previous repetitions.
+    fn pose_at_subbeat(&self, beat: usize) -> usize {
+        if self.total_subbeats == 0 {
+            return 0;
+        }
+        let poses_per_repetition: usize = self.steps.iter().map(StepInfo::num_poses).sum();
+        let mut pose = beat / self.total_subbeats * poses_per_repetition;
+        let mut offset = beat % self.total_subbeats;
+        for step in &self.steps {
+            if step.subbeats() <= offset {
+                offset -= step.subbeats();
+                pose += step.num_poses();
+            } else {
+                return pose + step.pose_at_subbeat(offset);
+            }
+        }
+        unreachable!("must find a pose");
     }
 }
 
@@ -67,7 +93,7 @@ impl From<&Dance> for DanceInfo {
                 steps.push(step_info);
             }
         });
-        let total_subbeats = steps.iter().map(|step| step.num_poses()).sum();
+        let total_subbeats = steps.iter().map(StepInfo::subbeats).sum();
 
         Self {
             steps,
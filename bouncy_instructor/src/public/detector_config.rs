@@ -0,0 +1,151 @@
+//! Thresholds that decide when the instructor accepts a pose or a step.
+//!
+//! The defaults are hand-picked. The lab can tune them on annotated videos and
+//! export a config file the app loads with `loadDetectorConfig`.
+
+use crate::editor::ExportError;
+use crate::parsing::detector_config_file::DetectorConfigFile;
+use crate::parsing::ParseFileError;
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorConfig {
+    /// Max pose error to count a pose as hit during live tracking.
+    #[wasm_bindgen(js_name = errorThreshold)]
+    pub error_threshold: f32,
+    /// Max error of the first pose of a step when detecting steps in a
+    /// recording.
+    #[wasm_bindgen(js_name = firstPoseThreshold)]
+    pub first_pose_threshold: f32,
+    /// Min time between two poses of a step in a recording, in beats.
+    #[wasm_bindgen(js_name = minPoseInterval)]
+    pub min_pose_interval: f32,
+    /// Max time between two poses of a step in a recording, in beats.
+    #[wasm_bindgen(js_name = maxPoseInterval)]
+    pub max_pose_interval: f32,
+    /// When a pose is missed, hint at mixed up sides if the mirrored pose has
+    /// less than this ratio of the error.
+    #[wasm_bindgen(js_name = leftRightRatio)]
+    pub left_right_ratio: f32,
+    /// Max error of the resting pose to start the count down.
+    #[wasm_bindgen(js_name = positioningThreshold)]
+    pub positioning_threshold: f32,
+    /// Max jump error to count a pose with a jump as hit, 0.5 accepts jumps
+    /// of half the expected height.
+    #[wasm_bindgen(js_name = jumpErrorThreshold)]
+    pub jump_error_threshold: f32,
+    /// Max travel error of a step before hinting which way to travel.
+    #[wasm_bindgen(js_name = travelErrorThreshold)]
+    pub travel_error_threshold: f32,
+    /// Replaces `error_threshold` for specific poses, by pose ID.
+    pub(crate) pose_error_thresholds: BTreeMap<String, f32>,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            error_threshold: 0.075,
+            first_pose_threshold: 0.2,
+            min_pose_interval: 0.5,
+            max_pose_interval: 1.5,
+            left_right_ratio: 0.5,
+            positioning_threshold: 0.075,
+            jump_error_threshold: 0.5,
+            travel_error_threshold: 0.3,
+            pose_error_thresholds: BTreeMap::new(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl DetectorConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[wasm_bindgen(js_name = fromRon)]
+    pub fn from_ron(text: &str) -> Result<DetectorConfig, ParseFileError> {
+        Ok(DetectorConfigFile::from_str(text)?.into())
+    }
+
+    #[wasm_bindgen(js_name = toRon)]
+    pub fn to_ron(&self) -> Result<String, ExportError> {
+        let file = DetectorConfigFile::from(self.clone());
+        let string = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())?;
+        Ok(string)
+    }
+
+    /// The error threshold used for live tracking of the given pose.
+    #[wasm_bindgen(js_name = poseErrorThreshold)]
+    pub fn pose_error_threshold(&self, pose_id: &str) -> f32 {
+        self.pose_error_thresholds
+            .get(pose_id)
+            .copied()
+            .unwrap_or(self.error_threshold)
+    }
+
+    #[wasm_bindgen(js_name = setPoseErrorThreshold)]
+    pub fn set_pose_error_threshold(&mut self, pose_id: String, threshold: f32) {
+        self.pose_error_thresholds.insert(pose_id, threshold);
+    }
+
+    #[wasm_bindgen(js_name = clearPoseErrorThresholds)]
+    pub fn clear_pose_error_thresholds(&mut self) {
+        self.pose_error_thresholds.clear();
+    }
+}
+
+impl From<DetectorConfigFile> for DetectorConfig {
+    fn from(file: DetectorConfigFile) -> Self {
+        Self {
+            error_threshold: file.error_threshold,
+            first_pose_threshold: file.first_pose_threshold,
+            min_pose_interval: file.min_pose_interval,
+            max_pose_interval: file.max_pose_interval,
+            left_right_ratio: file.left_right_ratio,
+            positioning_threshold: file.positioning_threshold,
+            jump_error_threshold: file.jump_error_threshold,
+            travel_error_threshold: file.travel_error_threshold,
+            pose_error_thresholds: file.pose_error_thresholds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_partial_config() {
+        let config = DetectorConfig::from_ron(
+            r#"(
+                version: 0,
+                error_threshold: 0.1,
+                pose_error_thresholds: { "left-up": 0.12 },
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(config.error_threshold, 0.1);
+        assert_eq!(config.first_pose_threshold, 0.2);
+        assert_eq!(config.pose_error_threshold("left-up"), 0.12);
+        assert_eq!(config.pose_error_threshold("right-up"), 0.1);
+    }
+
+    #[test]
+    fn test_config_roundtrip() {
+        let mut config = DetectorConfig::new();
+        config.max_pose_interval = 2.0;
+        config.set_pose_error_threshold("left-up".to_owned(), 0.12);
+        let text = config.to_ron().unwrap();
+        assert_eq!(DetectorConfig::from_ron(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn test_config_version_mismatch() {
+        let err = DetectorConfig::from_ron("(version: 1)").unwrap_err();
+        assert!(matches!(err, ParseFileError::VersionMismatch { .. }));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::dance_file::DanceStep;
 use crate::intern::content_collection::ContentCollection;
+use crate::parsing::course_file::TranslatedString;
 use crate::wrapper::dance_wrapper::DanceWrapper;
 use crate::{dance_file, intern, STATE};
 use std::rc::Rc;
@@ -8,6 +9,8 @@ use wasm_bindgen::prelude::wasm_bindgen;
 #[wasm_bindgen]
 pub struct DanceBuilder {
     pub(crate) id: String,
+    names: Option<TranslatedString>,
+    descriptions: Option<TranslatedString>,
     step_ids: Vec<String>,
     flip_orientation: Vec<bool>,
     // hack to keep a copy around for easier creation of step wrappers, which
@@ -25,6 +28,8 @@ impl DanceBuilder {
         });
         Self {
             id,
+            names: None,
+            descriptions: None,
             step_ids: vec![],
             flip_orientation: vec![],
             global_collection,
@@ -107,6 +112,8 @@ impl DanceBuilder {
             .collect();
         dance_file::Dance {
             id: self.id.clone(),
+            names: self.names.clone(),
+            descriptions: self.descriptions.clone(),
             steps,
         }
     }
@@ -120,6 +127,8 @@ impl From<intern::dance::Dance> for DanceBuilder {
         });
         Self {
             id: dance.id,
+            names: dance.names,
+            descriptions: dance.descriptions,
             step_ids: dance.step_ids,
             flip_orientation: dance.flip_orientation,
             global_collection,
@@ -140,6 +149,8 @@ impl From<dance_file::Dance> for DanceBuilder {
         });
         Self {
             id: definition.id,
+            names: definition.names,
+            descriptions: definition.descriptions,
             step_ids,
             flip_orientation,
             global_collection,
@@ -20,6 +20,8 @@ pub struct StepPositionBuilder {
     /// How high to jump when transitioning into this step, relative to the full
     /// body size.
     pub(crate) jump_height: Option<f32>,
+    /// How long the pose is held, in pose slots of the step's pace.
+    pub(crate) duration: Option<f32>,
 }
 
 #[wasm_bindgen]
@@ -35,6 +37,7 @@ impl StepPositionBuilder {
             },
             pivot: BodyPoint::default_pivot(),
             jump_height: None,
+            duration: None,
         }
     }
 
@@ -56,6 +59,20 @@ impl StepPositionBuilder {
         self.jump_height = Some(height);
     }
 
+    #[wasm_bindgen(getter)]
+    pub fn duration(&self) -> Option<f32> {
+        self.duration
+    }
+
+    #[wasm_bindgen(js_name = "setDuration")]
+    pub fn set_duration(&mut self, duration: f32) -> Result<(), String> {
+        if !(duration > 0.0 && duration.is_finite()) {
+            return Err(format!("pose duration must be positive, found {duration}"));
+        }
+        self.duration = Some(duration);
+        Ok(())
+    }
+
     #[wasm_bindgen(getter)]
     pub fn orientation(&self) -> Orientation {
         self.orientation
@@ -74,6 +91,7 @@ impl From<&StepPositionBuilder> for StepPosition {
             orientation: position.orientation,
             pivot: position.pivot.clone(),
             jump_height: position.jump_height,
+            duration: position.duration,
         }
     }
 }
@@ -89,6 +107,7 @@ impl StepPositionBuilder {
             orientation: pose.orientation,
             pivot: pose.pivot.clone(),
             jump_height: pose.jump_height,
+            duration: pose.duration,
         })
     }
 }
@@ -0,0 +1,35 @@
+//! Options for practicing a segment of a dance or lesson in a loop.
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopConfig {
+    /// How many times the segment is danced, at most.
+    pub repetitions: u32,
+    /// Start slower and reach the tempo of the segment in the third loop.
+    #[wasm_bindgen(js_name = tempoRamp)]
+    pub tempo_ramp: bool,
+    /// Stop early after a loop with at least this share of poses matched,
+    /// between 0.0 and 1.0.
+    #[wasm_bindgen(js_name = masteryHitRate)]
+    pub mastery_hit_rate: Option<f32>,
+}
+
+impl Default for LoopConfig {
+    fn default() -> Self {
+        Self {
+            repetitions: 4,
+            tempo_ramp: false,
+            mastery_hit_rate: None,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl LoopConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
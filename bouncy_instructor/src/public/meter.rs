@@ -0,0 +1,187 @@
+//! How beats are grouped into bars and subdivided into subbeats.
+//!
+//! Poses are scheduled on subbeats. Most of our music is in 4/4 with two
+//! subbeats per beat, counted "1 and 2 and ...". A waltz has three beats per
+//! bar, triplet footwork three subbeats per beat. With swing, the first subbeat
+//! of each pair is longer than the second.
+
+use thiserror::Error;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+/// Countdown audio exists for "one" to "four".
+const MAX_BEATS_PER_BAR: u32 = 4;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Meter {
+    beats_per_bar: u32,
+    subdivisions: u32,
+    swing: f32,
+}
+
+#[derive(Error, Debug)]
+pub enum MeterError {
+    #[error("beats per bar must be between 2 and {MAX_BEATS_PER_BAR}, found {0}")]
+    BeatsPerBar(u32),
+    #[error("a beat must have at least one subdivision")]
+    NoSubdivisions,
+    #[error("swing must be at least 0.5 and less than 1.0, found {0}")]
+    SwingRange(f32),
+    #[error("swing needs an even number of subdivisions, found {0}")]
+    SwingSubdivisions(u32),
+}
+
+impl From<MeterError> for JsValue {
+    fn from(value: MeterError) -> Self {
+        format!("{value}").into()
+    }
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self {
+            beats_per_bar: 4,
+            subdivisions: 2,
+            swing: 0.5,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Meter {
+    /// `swing` is the share of the first subbeat in each pair of subbeats,
+    /// 0.5 is straight and 2/3 a triplet swing.
+    #[wasm_bindgen(constructor)]
+    pub fn new(beats_per_bar: u32, subdivisions: u32, swing: f32) -> Result<Meter, MeterError> {
+        if !(2..=MAX_BEATS_PER_BAR).contains(&beats_per_bar) {
+            return Err(MeterError::BeatsPerBar(beats_per_bar));
+        }
+        if subdivisions == 0 {
+            return Err(MeterError::NoSubdivisions);
+        }
+        if !(0.5..1.0).contains(&swing) {
+            return Err(MeterError::SwingRange(swing));
+        }
+        if swing != 0.5 && subdivisions % 2 != 0 {
+            return Err(MeterError::SwingSubdivisions(subdivisions));
+        }
+        Ok(Self {
+            beats_per_bar,
+            subdivisions,
+            swing,
+        })
+    }
+
+    #[wasm_bindgen(getter, js_name = beatsPerBar)]
+    pub fn beats_per_bar(&self) -> u32 {
+        self.beats_per_bar
+    }
+
+    /// Subbeats per beat.
+    #[wasm_bindgen(getter)]
+    pub fn subdivisions(&self) -> u32 {
+        self.subdivisions
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn swing(&self) -> f32 {
+        self.swing
+    }
+}
+
+impl Meter {
+    pub(crate) fn subbeats_per_bar(&self) -> u32 {
+        self.beats_per_bar * self.subdivisions
+    }
+
+    /// Fractional beat position of a fractional subbeat.
+    pub(crate) fn beat_at_subbeat(&self, subbeat: f64) -> f64 {
+        if self.swing == 0.5 {
+            return subbeat / self.subdivisions as f64;
+        }
+        let swing = self.swing as f64;
+        let pair = (subbeat / 2.0).floor();
+        let within = subbeat - 2.0 * pair;
+        let fraction = if within < 1.0 {
+            within * swing
+        } else {
+            swing + (within - 1.0) * (1.0 - swing)
+        };
+        (pair + fraction) * 2.0 / self.subdivisions as f64
+    }
+
+    /// Inverse of `beat_at_subbeat`.
+    pub(crate) fn subbeat_at_beat(&self, beat: f64) -> f64 {
+        if self.swing == 0.5 {
+            return beat * self.subdivisions as f64;
+        }
+        let swing = self.swing as f64;
+        let pairs = beat * self.subdivisions as f64 / 2.0;
+        let pair = pairs.floor();
+        let fraction = pairs - pair;
+        let within = if fraction < swing {
+            fraction / swing
+        } else {
+            1.0 + (fraction - swing) / (1.0 - swing)
+        };
+        2.0 * pair + within
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_straight_subbeats() {
+        let meter = Meter::default();
+        assert_eq!(meter.beat_at_subbeat(3.0), 1.5);
+        assert_eq!(meter.subbeat_at_beat(1.5), 3.0);
+
+        let triplets = Meter::new(3, 3, 0.5).unwrap();
+        assert_eq!(triplets.subbeats_per_bar(), 9);
+        assert_eq!(triplets.beat_at_subbeat(4.5), 1.5);
+        assert_eq!(triplets.subbeat_at_beat(2.0), 6.0);
+    }
+
+    #[test]
+    fn test_swing_subbeats() {
+        let meter = Meter::new(4, 2, 0.75).unwrap();
+        assert_eq!(meter.beat_at_subbeat(0.0), 0.0);
+        assert_eq!(meter.beat_at_subbeat(1.0), 0.75);
+        assert_eq!(meter.beat_at_subbeat(2.0), 1.0);
+        assert_eq!(meter.beat_at_subbeat(3.5), 1.875);
+        for subbeat in [0.0, 0.5, 1.0, 1.25, 2.0, 3.5, 7.0] {
+            assert_eq!(
+                meter.subbeat_at_beat(meter.beat_at_subbeat(subbeat)),
+                subbeat
+            );
+        }
+
+        // swing pairs of sixteenths
+        let meter = Meter::new(4, 4, 0.75).unwrap();
+        assert_eq!(meter.beat_at_subbeat(1.0), 0.375);
+        assert_eq!(meter.beat_at_subbeat(3.0), 0.875);
+    }
+
+    #[test]
+    fn test_invalid_meter() {
+        assert!(matches!(
+            Meter::new(5, 2, 0.5),
+            Err(MeterError::BeatsPerBar(5))
+        ));
+        assert!(matches!(
+            Meter::new(4, 0, 0.5),
+            Err(MeterError::NoSubdivisions)
+        ));
+        assert!(matches!(
+            Meter::new(4, 2, 0.4),
+            Err(MeterError::SwingRange(_))
+        ));
+        assert!(matches!(
+            Meter::new(3, 3, 0.6),
+            Err(MeterError::SwingSubdivisions(3))
+        ));
+    }
+}
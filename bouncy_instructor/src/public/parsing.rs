@@ -7,6 +7,8 @@
 
 pub(crate) mod course_file;
 pub(crate) mod dance_file;
+pub(crate) mod detector_config_file;
+pub(crate) mod pack_file;
 pub(crate) mod pose_file;
 pub(crate) mod step_file;
 pub(crate) mod video_def;
@@ -31,6 +33,16 @@ pub enum ParseFileError {
     UnknownStepName(String),
     #[error("missing translation for `{id}` with lang `{lang}`")]
     MissingTranslation { id: String, lang: String },
+    #[error("invalid prerequisite, {0}")]
+    InvalidPrerequisite(String),
+    #[error("invalid meter, {0}")]
+    InvalidMeter(#[from] crate::MeterError),
+    #[error("invalid pose duration {duration} in step `{step}`, must be positive")]
+    InvalidDuration { step: String, duration: f32 },
+    #[error("step `{0}` has layers and keyframes, only one is allowed")]
+    LayersWithKeyframes(String),
+    #[error("layers of step `{0}` hold their poses for different durations")]
+    LayerDurations(String),
 }
 
 impl From<ParseFileError> for JsValue {
@@ -42,7 +54,8 @@ impl From<ParseFileError> for JsValue {
 #[cfg(test)]
 mod tests {
     use crate::{
-        dances, load_dance_str, load_pose_str, load_step_str, parse_course_str, steps, STATE,
+        dances, lint_translations, load_dance_str, load_pose_str, load_step_str, parse_course_str,
+        set_language, step_by_id, steps, STATE,
     };
 
     use super::*;
@@ -95,6 +108,48 @@ mod tests {
       )
       "#;
 
+    const TRANSLATED_STEP_STR: &str = r#"
+      #![enable(implicit_some)]
+      (
+        version: 0,
+        steps: [
+          (
+            name: "Running Man",
+            names: {"en": "Running Man", "de": "Laufender Mann"},
+            id: "rm-0",
+            variation: "left-first",
+            variation_descriptions: {"en": "Left leg first", "de": "Linkes Bein zuerst"},
+            keyframes: [
+              (pose: "test-pose-left", orientation: Right),
+              (pose: "test-pose-right", orientation: Right),
+            ]
+          ),
+          (
+            name: "Reverse RM",
+            id: "rrm-0",
+            keyframes: [
+              (pose: "test-pose-right", orientation: Right),
+              (pose: "test-pose-left", orientation: Right),
+            ]
+          ),
+        ]
+      )
+      "#;
+
+    const TRANSLATED_DANCE_STR: &str = r#"
+      #![enable(implicit_some)]
+      (
+        version: 1,
+        dances: [
+          (
+            id: "test-dance-0",
+            names: {"en": "Test Dance", "de": "Testtanz"},
+            steps: [(id: "rm-0"), (id: "rrm-0")],
+          ),
+        ]
+      )
+      "#;
+
     const DANCE_STR: &str = r#"
       (
         version: 0,
@@ -284,6 +339,99 @@ mod tests {
         .assert_debug_eq(&dance_ids);
     }
 
+    #[test]
+    fn test_translated_step_names() {
+        set_language("de-CH".to_owned());
+        load_pose_str(POSE_STR).unwrap();
+        load_step_str(TRANSLATED_STEP_STR, "test".to_owned()).unwrap();
+        load_dance_str(TRANSLATED_DANCE_STR).unwrap();
+
+        let rm = step_by_id("rm-0".to_owned(), false).unwrap();
+        assert_eq!(rm.name(), "Running Man");
+        assert_eq!(rm.translated_name(), "Laufender Mann");
+        assert_eq!(rm.variation().as_deref(), Some("left-first"));
+        assert_eq!(
+            rm.variation_description().as_deref(),
+            Some("Linkes Bein zuerst")
+        );
+
+        // no translations available, fall back to the plain name
+        let rrm = step_by_id("rrm-0".to_owned(), false).unwrap();
+        assert_eq!(rrm.translated_name(), "Reverse RM");
+        assert_eq!(rrm.variation_description(), None);
+
+        let dance = &dances()[0];
+        assert_eq!(dance.name(), "Testtanz");
+    }
+
+    #[test]
+    fn test_translated_step_names_fallback() {
+        set_language("fr".to_owned());
+        load_pose_str(POSE_STR).unwrap();
+        load_step_str(TRANSLATED_STEP_STR, "test".to_owned()).unwrap();
+
+        // no French translation, English is the fallback
+        let rm = step_by_id("rm-0".to_owned(), false).unwrap();
+        assert_eq!(rm.translated_name(), "Running Man");
+        assert_eq!(
+            rm.variation_description().as_deref(),
+            Some("Left leg first")
+        );
+    }
+
+    #[test]
+    fn test_switch_language_in_place() {
+        load_pose_str(POSE_STR).unwrap();
+        load_step_str(TRANSLATED_STEP_STR, "test".to_owned()).unwrap();
+        load_dance_str(TRANSLATED_DANCE_STR).unwrap();
+
+        let rm = step_by_id("rm-0".to_owned(), false).unwrap();
+        let dance = dances()[0].clone();
+        assert_eq!(rm.translated_name(), "Running Man");
+        assert_eq!(dance.name(), "Test Dance");
+
+        // existing objects pick up the new language without reloading
+        set_language("de".to_owned());
+        assert_eq!(rm.translated_name(), "Laufender Mann");
+        assert_eq!(
+            rm.variation_description().as_deref(),
+            Some("Linkes Bein zuerst")
+        );
+        assert_eq!(dance.name(), "Testtanz");
+
+        set_language("en".to_owned());
+        assert_eq!(rm.translated_name(), "Running Man");
+    }
+
+    #[test]
+    fn test_lint_translations() {
+        load_pose_str(POSE_STR).unwrap();
+        load_step_str(TRANSLATED_STEP_STR, "test".to_owned()).unwrap();
+        load_dance_str(TRANSLATED_DANCE_STR).unwrap();
+
+        let missing = lint_translations(vec!["de".to_owned(), "fr".to_owned()]);
+        expect![[r#"
+            [
+                "missing translation for `name of pose test-pose-left` with lang `de`",
+                "missing translation for `name of pose test-pose-left` with lang `fr`",
+                "missing translation for `name of pose test-pose-right` with lang `de`",
+                "missing translation for `name of pose test-pose-right` with lang `fr`",
+                "missing translation for `name of step rm-0` with lang `fr`",
+                "missing translation for `variation description of step rm-0` with lang `fr`",
+                "missing translation for `name of step rrm-0` with lang `de`",
+                "missing translation for `name of step rrm-0` with lang `fr`",
+                "missing translation for `name of dance test-dance-0` with lang `fr`",
+            ]
+        "#]]
+        .assert_debug_eq(&missing);
+
+        // regions are ignored, tags that aren't valid language codes are
+        // reported as missing
+        let missing = lint_translations(vec!["de-CH".to_owned(), "日本".to_owned()]);
+        assert_eq!(missing.len(), 9, "{missing:?}");
+        assert!(missing[0].ends_with("with lang `de-CH`"), "{missing:?}");
+    }
+
     #[test]
     fn test_invalid_pose_reference() {
         let input = r#"
@@ -339,6 +487,150 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pose_durations() {
+        let held = STEP_STR.replacen(
+            r#"(pose: "test-pose-left", orientation: Right),"#,
+            r#"(pose: "test-pose-left", orientation: Right, duration: 2.0),"#,
+            1,
+        );
+        load_pose_str(POSE_STR).unwrap();
+        load_step_str(&held, "test".to_owned()).unwrap();
+        let step = step_by_id("rm-0".to_owned(), false).unwrap();
+        assert_eq!(step.pose_duration(0), 2.0);
+        assert_eq!(step.pose_duration(1), 2.0);
+        assert_eq!(step.pose_duration(2), 1.0);
+        assert_eq!(step.subbeats(), 3);
+
+        // the held pose is shown on the first two subbeats
+        assert_eq!(step.pose_at_subbeat(1), 0);
+        assert_eq!(step.pose_at_subbeat(2), 1);
+        assert_eq!(step.pose_at_subbeat(4), 2);
+        assert_eq!(step.skeleton(1).left.thigh, step.skeleton(0).left.thigh);
+
+        let invalid = STEP_STR.replacen(
+            r#"(pose: "test-pose-left", orientation: Right),"#,
+            r#"(pose: "test-pose-left", orientation: Right, duration: 0.0),"#,
+            1,
+        );
+        match load_step_str(&invalid, "test".to_owned()) {
+            Err(ParseFileError::InvalidDuration { step, .. }) if step == "rm-0" => (),
+            Err(other) => panic!("wrong error {other}"),
+            Ok(()) => panic!("expected an error when loading a zero duration"),
+        }
+    }
+
+    #[test]
+    fn test_layered_step() {
+        let arms_pose_str = r#"
+        (
+          version: 0,
+          poses: [
+            (
+              name: "arms-up",
+              direction: Right,
+              limbs: [
+                (limb: LeftArm, angle: 180, tolerance: 5, weight: 1.0),
+                (limb: LeftShin, angle: 45, tolerance: 5, weight: 1.0),
+              ]
+            ),
+          ]
+        )
+        "#;
+        let layered_step_str = r#"
+        (
+          version: 0,
+          steps: [
+            (
+              name: "Arms Up",
+              id: "arms-0",
+              keyframes: [(pose: "arms-up", orientation: Right)]
+            ),
+            (
+              name: "Running Man With Arms",
+              id: "rm-arms-0",
+              keyframes: [],
+              layers: [
+                (step: "rm-0", region: LowerBody),
+                (step: "arms-0", region: UpperBody),
+              ]
+            ),
+          ]
+        )
+        "#;
+        load_pose_str(POSE_STR).unwrap();
+        load_pose_str(arms_pose_str).unwrap();
+        load_step_str(STEP_STR, "test".to_owned()).unwrap();
+        load_step_str(layered_step_str, "test".to_owned()).unwrap();
+
+        let step = step_by_id("rm-arms-0".to_owned(), false).unwrap();
+        assert_eq!(step.subbeats(), 2);
+        let layer_ids: Vec<String> = step.layers().iter().map(|layer| layer.id()).collect();
+        assert_eq!(layer_ids, ["rm-0", "arms-0"]);
+        assert_eq!(step.layer_region(1), Some(step_file::BodyRegion::UpperBody));
+
+        // the shin comes from the footwork, the arm from the arms layer
+        let merged_limbs = STATE.with_borrow(|state| {
+            let db = &state.global_db.tracker_view;
+            let pose = db.step("rm-arms-0").unwrap().poses[0];
+            let limbs: Vec<String> = db.poses()[pose]
+                .limbs
+                .iter()
+                .map(|position| db.limb_name(position.limb).to_owned())
+                .collect();
+            (db.pose_id(pose).to_owned(), limbs)
+        });
+        expect![[r#"
+            (
+                "test-pose-left[LowerBody]+arms-up[UpperBody]",
+                [
+                    "LeftShin",
+                    "LeftArm",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&merged_limbs);
+
+        let missing_layer = layered_step_str.replace(r#"step: "arms-0""#, r#"step: "fake-id""#);
+        match load_step_str(&missing_layer, "test".to_owned()) {
+            Err(ParseFileError::UnknownStepName(id)) if id == "fake-id" => (),
+            Err(other) => panic!("wrong error {other}"),
+            Ok(()) => panic!("expected an error when loading a missing layer"),
+        }
+
+        // a held arm pose would be merged with footwork happening later
+        let held_arms = layered_step_str
+            .replace(
+                r#"(pose: "arms-up", orientation: Right)"#,
+                r#"(pose: "arms-up", orientation: Right, duration: Some(2.0))"#,
+            )
+            .replace("arms-0", "held-arms-0");
+        match load_step_str(&held_arms, "test".to_owned()) {
+            Err(ParseFileError::LayerDurations(id)) if id == "rm-held-arms-0" => (),
+            Err(other) => panic!("wrong error {other}"),
+            Ok(()) => panic!("expected an error when layers are held differently"),
+        }
+    }
+
+    #[test]
+    fn test_course_meter() {
+        let waltz = COURSE_STR.replace(
+            "difficulty: 2,",
+            "difficulty: 2, meter: (beats_per_bar: 3, subdivisions: 2),",
+        );
+        let course = parse_course_str(&waltz, "en").unwrap();
+        let lesson = &course.lessons()[0];
+        assert_eq!(lesson.meter(), crate::Meter::new(3, 2, 0.5).unwrap());
+        assert_eq!(course.tracker(0).unwrap().meter(), lesson.meter());
+
+        let invalid = COURSE_STR.replace(
+            "difficulty: 2,",
+            "difficulty: 2, meter: (beats_per_bar: 3, subdivisions: 3, swing: 0.6),",
+        );
+        let err = parse_course_str(&invalid, "en").unwrap_err();
+        assert!(matches!(err, ParseFileError::InvalidMeter(_)), "{err}");
+    }
+
     #[test]
     fn test_basic_course_loading() {
         let en_course = parse_course_str(COURSE_STR, "en").unwrap();
@@ -349,6 +641,7 @@ mod tests {
                 explanation: None,
                 featured_step_id: "rm-0",
                 video: None,
+                prerequisites: [],
                 lessons: [
                     Lesson {
                         name: "Micro Bounce",
@@ -358,6 +651,11 @@ mod tests {
                         back_video: None,
                         song: None,
                         song_timestamp: None,
+                        meter: Meter {
+                            beats_per_bar: 4,
+                            subdivisions: 2,
+                            swing: 0.5,
+                        },
                         energy: 3,
                         difficulty: 2,
                         parts: [
@@ -378,6 +676,7 @@ mod tests {
                                 tracking: Tracked,
                             },
                         ],
+                        prerequisites: [],
                     },
                 ],
                 collection: ContentCollection {
@@ -409,6 +708,7 @@ mod tests {
                         ),
                     },
                 ),
+                prerequisites: [],
                 lessons: [
                     Lesson {
                         name: "Micro Bounce",
@@ -460,6 +760,11 @@ mod tests {
                         song_timestamp: Some(
                             1500.0,
                         ),
+                        meter: Meter {
+                            beats_per_bar: 4,
+                            subdivisions: 2,
+                            swing: 0.5,
+                        },
                         energy: 3,
                         difficulty: 2,
                         parts: [
@@ -472,6 +777,7 @@ mod tests {
                                 tracking: Untracked,
                             },
                         ],
+                        prerequisites: [],
                     },
                 ],
                 collection: ContentCollection {
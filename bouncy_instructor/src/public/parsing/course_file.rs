@@ -12,21 +12,24 @@ use super::{ParseFileError, VersionCheck};
 
 pub(crate) const CURRENT_VERSION: u16 = 0;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CourseFile {
     version: u8,
-    id: String,
+    pub(crate) id: String,
     names: TranslatedString,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     explanations: Option<TranslatedString>,
-    featured_step: String,
+    pub(crate) featured_step: String,
     video: Option<VideoDef>,
+    /// What must be mastered before the course unlocks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    prerequisites: Vec<Prerequisite>,
     lessons: Vec<Lesson>,
     poses: Vec<Pose>,
     steps: Vec<Step>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Lesson {
     names: TranslatedString,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -44,12 +47,19 @@ pub(crate) struct Lesson {
     song: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     song_timestamp: Option<u64>,
+    /// Meter of the song, 4/4 with two subbeats per beat if missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    meter: Option<Meter>,
+    /// What must be mastered before the lesson unlocks, in addition to the
+    /// prerequisites of the course.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    prerequisites: Vec<Prerequisite>,
     parts: Vec<Part>,
     energy: u8,
     difficulty: u8,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Part {
     pub(crate) step: String,
     /// How many times the step should be repeated.
@@ -61,6 +71,17 @@ pub(crate) struct Part {
     pub(crate) tracking: TrackingKind,
 }
 
+/// How beats are grouped in bars and subdivided in subbeats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Meter {
+    beats_per_bar: u32,
+    /// Subbeats per beat.
+    subdivisions: u32,
+    /// Share of the first subbeat in each pair, 0.5 is straight.
+    #[serde(default = "straight", skip_serializing_if = "is_straight")]
+    swing: f32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 pub(crate) enum TrackingKind {
     /// Normal tracking, used when nothing is specified.
@@ -70,6 +91,23 @@ pub(crate) enum TrackingKind {
     Untracked,
 }
 
+/// Something the user must have mastered, measured by the ratio of hits to
+/// all detected poses in their best session.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) enum Prerequisite {
+    Lesson {
+        /// The course of the lesson, defaults to the course in which the
+        /// prerequisite is defined.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        course: Option<String>,
+        /// Index of the lesson within the course.
+        lesson: usize,
+        min_hit_ratio: f32,
+    },
+    /// A step trained on its own, by step id.
+    Step { id: String, min_hit_ratio: f32 },
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 #[serde(transparent)]
 pub(crate) struct TranslatedString {
@@ -77,6 +115,15 @@ pub(crate) struct TranslatedString {
 }
 
 impl CourseFile {
+    /// Prerequisites of the course and all its lessons.
+    pub(crate) fn prerequisites_mut(&mut self) -> impl Iterator<Item = &mut Prerequisite> {
+        self.prerequisites.iter_mut().chain(
+            self.lessons
+                .iter_mut()
+                .flat_map(|lesson| lesson.prerequisites.iter_mut()),
+        )
+    }
+
     pub(crate) fn from_str(text: &str) -> Result<Self, ParseFileError> {
         let check: VersionCheck = ron::from_str(text)?;
         if check.version == CURRENT_VERSION {
@@ -92,40 +139,32 @@ impl CourseFile {
 
     pub(crate) fn into_course(self, lang: &str) -> Result<Course, ParseFileError> {
         // The course object uses its own collection of poses and steps.
-        let mut collection = ContentCollection::default();
+        let mut collection = ContentCollection::new(lang.to_owned());
         collection.add_poses(self.poses)?;
         collection.add_steps(self.steps, StepSource::new("course".to_owned()))?;
 
-        let name = self
-            .names
-            .take(lang)
-            .ok_or_else(|| ParseFileError::MissingTranslation {
-                id: self.id.clone(),
-                lang: lang.to_owned(),
-            })?;
-        let explanation = self
-            .explanations
-            .map(|translated| {
-                translated
-                    .take(lang)
-                    .ok_or_else(|| ParseFileError::MissingTranslation {
-                        id: self.id.clone(),
-                        lang: lang.to_owned(),
-                    })
-            })
-            .transpose()?;
+        // All translations are kept to allow switching languages later. But
+        // at least the requested language must be resolvable.
+        check_translation(Some(&self.names), &self.id, lang)?;
+        check_translation(self.explanations.as_ref(), &self.id, lang)?;
+        let prerequisites = self
+            .prerequisites
+            .into_iter()
+            .map(|p| p.unpack(&self.id, None))
+            .collect::<Result<_, _>>()?;
         let mut course = Course {
-            name,
-            explanation,
+            names: self.names,
+            explanations: self.explanations,
             id: self.id.clone(),
             featured_step_id: self.featured_step,
             video: self.video.map(From::from),
+            prerequisites,
             lessons: vec![],
             collection,
         };
 
-        for lesson in self.lessons {
-            let validated_lesson = lesson.unpack(lang, &course.id, &course.collection)?;
+        for (index, lesson) in self.lessons.into_iter().enumerate() {
+            let validated_lesson = lesson.unpack(lang, &course.id, index, &course.collection)?;
             course.add_lesson(validated_lesson);
         }
         Ok(course)
@@ -139,26 +178,19 @@ impl Lesson {
         self,
         lang: &str,
         course_id: &str,
+        lesson_index: usize,
         collection: &ContentCollection,
     ) -> Result<crate::public::course::Lesson, ParseFileError> {
-        let name = self
-            .names
-            .take(lang)
-            .ok_or_else(|| ParseFileError::MissingTranslation {
-                id: format!("lesson name of {}", course_id),
-                lang: lang.to_owned(),
-            })?;
-        let explanation = self
-            .explanations
-            .map(|translated| {
-                translated
-                    .take(lang)
-                    .ok_or_else(|| ParseFileError::MissingTranslation {
-                        id: format!("explanation of lesson in {}", course_id),
-                        lang: lang.to_owned(),
-                    })
-            })
-            .transpose()?;
+        check_translation(
+            Some(&self.names),
+            &format!("lesson name of {}", course_id),
+            lang,
+        )?;
+        check_translation(
+            self.explanations.as_ref(),
+            &format!("explanation of lesson in {}", course_id),
+            lang,
+        )?;
 
         let parts = self
             .parts
@@ -173,43 +205,87 @@ impl Lesson {
                 )
             })
             .collect::<Result<_, _>>()?;
+        let prerequisites = self
+            .prerequisites
+            .into_iter()
+            .map(|p| p.unpack(course_id, Some(lesson_index)))
+            .collect::<Result<_, _>>()?;
+        let meter = match self.meter {
+            Some(meter) => crate::Meter::new(meter.beats_per_bar, meter.subdivisions, meter.swing)?,
+            None => crate::Meter::default(),
+        };
 
         Ok(crate::public::course::Lesson {
-            name,
-            explanation,
+            names: self.names,
+            explanations: self.explanations,
+            lang: collection.lang().clone(),
             parts,
             explainer_video: self.explainer_video.map(From::from),
             front_video: self.front_video.map(From::from),
             back_video: self.back_video.map(From::from),
             song: self.song,
             song_timestamp: self.song_timestamp.map(|int| int as f64),
+            meter,
             difficulty: self.difficulty,
             energy: self.energy,
+            prerequisites,
         })
     }
 }
 
-impl TranslatedString {
-    pub fn take(mut self, lang: &str) -> Option<String> {
-        let key = if lang.len() > 2 { &lang[0..2] } else { lang };
-        let result = self
-            .inner
-            .remove(key)
-            .or_else(|| self.inner.remove("en"))
-            .or_else(|| self.inner.into_values().next());
-        #[cfg(target_arch = "wasm32")]
-        result.as_ref().inspect(|s| {
-            wasm_bindgen::intern(s);
-        });
-        #[allow(clippy::let_and_return)]
-        result
+impl Prerequisite {
+    /// Validate and resolve references relative to where the prerequisite is
+    /// defined. `lesson_index` is `None` for prerequisites of a whole course.
+    ///
+    /// Within the same course, only earlier lessons can be required, which
+    /// rules out cycles.
+    fn unpack(
+        self,
+        course_id: &str,
+        lesson_index: Option<usize>,
+    ) -> Result<crate::public::course::Prerequisite, ParseFileError> {
+        use crate::public::course::Prerequisite as Out;
+        let (out, min_hit_ratio) = match self {
+            Prerequisite::Lesson {
+                course,
+                lesson,
+                min_hit_ratio,
+            } => {
+                let course = course.unwrap_or_else(|| course_id.to_owned());
+                if course == course_id && !lesson_index.is_some_and(|own| lesson < own) {
+                    return Err(ParseFileError::InvalidPrerequisite(format!(
+                        "lesson {lesson} of {course_id} is not an earlier lesson of the same course"
+                    )));
+                }
+                let out = Out::Lesson {
+                    course_id: course,
+                    lesson_index: lesson,
+                    min_hit_ratio,
+                };
+                (out, min_hit_ratio)
+            }
+            Prerequisite::Step { id, min_hit_ratio } => (
+                Out::Step {
+                    step_id: id,
+                    min_hit_ratio,
+                },
+                min_hit_ratio,
+            ),
+        };
+        if !(0.0..=1.0).contains(&min_hit_ratio) {
+            return Err(ParseFileError::InvalidPrerequisite(format!(
+                "min_hit_ratio {min_hit_ratio} in {course_id} must be between 0.0 and 1.0"
+            )));
+        }
+        Ok(out)
     }
+}
 
+impl TranslatedString {
     pub fn get(&self, lang: &str) -> Option<String> {
-        let key = if lang.len() > 2 { &lang[0..2] } else { lang };
         let result = self
             .inner
-            .get(key)
+            .get(lang_key(lang))
             .or_else(|| self.inner.get("en"))
             .or_else(|| self.inner.values().next());
         #[cfg(target_arch = "wasm32")]
@@ -222,6 +298,47 @@ impl TranslatedString {
     pub fn set(&mut self, lang: String, name: String) {
         self.inner.insert(lang, name);
     }
+
+    /// Whether a translation exists for the language, without falling back
+    /// to English or any other language.
+    ///
+    /// Like [`TranslatedString::get`], only the first two characters of a
+    /// language tag are used, `de-CH` is satisfied by a `de` translation.
+    pub fn has_lang(&self, lang: &str) -> bool {
+        self.inner.contains_key(lang_key(lang))
+    }
+}
+
+/// The key of translations for a language tag, which is the two letter
+/// language code without the region.
+fn lang_key(lang: &str) -> &str {
+    lang.get(..2).unwrap_or(lang)
+}
+
+/// Fails if an existing translation cannot be resolved in the given language,
+/// not even through fallbacks.
+fn check_translation(
+    translations: Option<&TranslatedString>,
+    id: &str,
+    lang: &str,
+) -> Result<(), ParseFileError> {
+    match translations {
+        Some(translated) if translated.get(lang).is_none() => {
+            Err(ParseFileError::MissingTranslation {
+                id: id.to_owned(),
+                lang: lang.to_owned(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn straight() -> f32 {
+    0.5
+}
+
+fn is_straight(swing: &f32) -> bool {
+    *swing == 0.5
 }
 
 fn is_default<D: Default + PartialEq>(this: &D) -> bool {
@@ -1,6 +1,7 @@
 //! Defines the external format for defining dances, which are a sequence of
 //! steps.
 
+use super::course_file::TranslatedString;
 use super::VersionCheck;
 use crate::parsing::ParseFileError;
 use serde::{Deserialize, Serialize};
@@ -18,6 +19,12 @@ pub(crate) struct DanceFile {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct Dance {
     pub id: String,
+    /// Translated names of the dance, the id is shown if none is available.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub names: Option<TranslatedString>,
+    /// Translated text describing the dance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descriptions: Option<TranslatedString>,
     pub steps: Vec<DanceStep>,
 }
 
@@ -76,6 +83,8 @@ mod v0 {
                     .into_iter()
                     .map(|dance| super::Dance {
                         id: dance.id,
+                        names: None,
+                        descriptions: None,
                         steps: dance
                             .steps
                             .into_iter()
@@ -0,0 +1,66 @@
+//! Defines the external format for detector configurations, which hold the
+//! tunable thresholds of dance detection.
+
+use super::{ParseFileError, VersionCheck};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const CURRENT_VERSION: u16 = 0;
+
+/// Format for detector configuration files.
+///
+/// All fields except the version are optional and fall back to the defaults
+/// of the instructor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct DetectorConfigFile {
+    pub version: u16,
+    pub error_threshold: f32,
+    pub first_pose_threshold: f32,
+    pub min_pose_interval: f32,
+    pub max_pose_interval: f32,
+    pub left_right_ratio: f32,
+    pub positioning_threshold: f32,
+    pub jump_error_threshold: f32,
+    pub travel_error_threshold: f32,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub pose_error_thresholds: BTreeMap<String, f32>,
+}
+
+impl DetectorConfigFile {
+    pub(crate) fn from_str(text: &str) -> Result<Self, ParseFileError> {
+        let check: VersionCheck = ron::from_str(text)?;
+        if check.version == CURRENT_VERSION {
+            let parsed: DetectorConfigFile = ron::from_str(text)?;
+            Ok(parsed)
+        } else {
+            Err(ParseFileError::VersionMismatch {
+                expected: CURRENT_VERSION,
+                found: check.version,
+            })
+        }
+    }
+}
+
+impl Default for DetectorConfigFile {
+    fn default() -> Self {
+        crate::DetectorConfig::default().into()
+    }
+}
+
+impl From<crate::DetectorConfig> for DetectorConfigFile {
+    fn from(config: crate::DetectorConfig) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            error_threshold: config.error_threshold,
+            first_pose_threshold: config.first_pose_threshold,
+            min_pose_interval: config.min_pose_interval,
+            max_pose_interval: config.max_pose_interval,
+            left_right_ratio: config.left_right_ratio,
+            positioning_threshold: config.positioning_threshold,
+            jump_error_threshold: config.jump_error_threshold,
+            travel_error_threshold: config.travel_error_threshold,
+            pose_error_thresholds: config.pose_error_thresholds,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Defines the external format for content packs, which bundle poses, steps,
+//! dances and courses under one namespace.
+
+use super::course_file::CourseFile;
+use super::dance_file::Dance;
+use super::pose_file::Pose;
+use super::step_file::Step;
+use super::{ParseFileError, VersionCheck};
+use serde::{Deserialize, Serialize};
+
+const CURRENT_VERSION: u16 = 0;
+
+/// Format for content pack files.
+///
+/// All ids inside a pack are local to the pack. When loaded, they are prefixed
+/// with the pack id, e.g. step `rm-0` in pack `my-club` becomes `my-club/rm-0`.
+///
+/// References to unqualified ids resolve to the pack itself first, then to the
+/// built-in content. Content of other packs must be referenced by qualified id
+/// and the other pack must be listed as a dependency.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PackFile {
+    pub version: u16,
+    /// Namespace of the pack, must not contain `/`.
+    pub id: String,
+    /// Version of the content, increase it with each release of the pack.
+    pub pack_version: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<PackDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub poses: Vec<Pose>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<Step>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dances: Vec<Dance>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub courses: Vec<CourseFile>,
+}
+
+/// Another pack that must be loaded before this pack.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PackDependency {
+    pub id: String,
+    /// Oldest `pack_version` of the dependency that is compatible.
+    #[serde(default)]
+    pub min_version: u32,
+}
+
+impl PackFile {
+    pub(crate) fn from_str(text: &str) -> Result<Self, ParseFileError> {
+        let check: VersionCheck = ron::from_str(text)?;
+        if check.version == CURRENT_VERSION {
+            let parsed: PackFile = ron::from_str(text)?;
+            Ok(parsed)
+        } else {
+            Err(ParseFileError::VersionMismatch {
+                expected: CURRENT_VERSION,
+                found: check.version,
+            })
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! Defines the external format for defining steps, which are a combination of
 //! poses.
 
+use crate::parsing::course_file::TranslatedString;
 use crate::parsing::ParseFileError;
 use crate::pose_file::BodyPoint;
 use serde::{Deserialize, Serialize};
@@ -28,6 +29,10 @@ pub(crate) struct Step {
     /// of the same step. This name can also be shown to users if no translation
     /// is available.
     pub name: String,
+    /// Translated names of the step, shown to users instead of `name` when
+    /// available in the selected language.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub names: Option<TranslatedString>,
     /// Description identifier for the translated text which describes how the
     /// variation is different from the original.
     ///
@@ -36,8 +41,30 @@ pub(crate) struct Step {
     /// with the left foot first. The app shows a translated text like "Left Leg First".
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub variation: Option<String>,
+    /// Translated text which describes how the variation is different from
+    /// the original. Takes precedence over the generic text the app shows for
+    /// `variation`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variation_descriptions: Option<TranslatedString>,
     /// Poses per beat.
     pub keyframes: Vec<StepPosition>,
+    /// Build the step from other steps, each contributing the limbs of one
+    /// body region. For example, footwork of one step and the arms of another.
+    ///
+    /// A layered step has no keyframes of its own. Timing, orientation and
+    /// pivots come from the first layer. Layers of different lengths repeat
+    /// until they line up again.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<StepLayer>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct StepLayer {
+    /// Reference to the id of a step, usually with partial poses that only
+    /// define limbs of `region`.
+    pub step: String,
+    /// Which limbs of the referenced step are used.
+    pub region: BodyRegion,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -60,6 +87,15 @@ pub(crate) struct StepPosition {
     /// body size.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub jump_height: Option<f32>,
+    /// How long the pose is held, in pose slots of the step's pace. Defaults
+    /// to 1.0.
+    ///
+    /// Use 2.0 to hold a pose for two counts. Fractions move the following
+    /// poses off the beat, a 0.75 on a pose of one full beat makes the next
+    /// pose land on the "a" count. Fractions must resolve to full subbeats of
+    /// the meter, otherwise they are rounded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f32>,
 }
 
 /// Define in which direction a pose should be oriented.
@@ -75,6 +111,16 @@ pub enum Orientation {
     Any,
 }
 
+/// Limbs are grouped into regions by the body part they end in.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[wasm_bindgen]
+pub enum BodyRegion {
+    /// Shoulders, arms and hands.
+    UpperBody,
+    /// Hips, legs and feet.
+    LowerBody,
+}
+
 impl StepFile {
     pub(crate) fn new() -> Self {
         Self {
@@ -100,8 +146,11 @@ impl Step {
         Self {
             id,
             name,
+            names: None,
             variation: None,
+            variation_descriptions: None,
             keyframes: vec![],
+            layers: vec![],
         }
     }
 }
@@ -1,7 +1,7 @@
 use std::collections::BTreeMap;
 
 /// Define a video to display in frontend in one of several ways.
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(untagged)]
 pub enum VideoDef {
     /// Defined with additional meta data
@@ -14,7 +14,7 @@ pub enum VideoDef {
     Simple(String),
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub enum Marker {
     Start,
     Step(String),
@@ -0,0 +1,191 @@
+//! Spaced repetition of steps: Steps the user has practiced before come back
+//! for review in growing intervals, as long as the user keeps dancing them
+//! well.
+
+use crate::intern::step_pace::StepPace;
+use crate::intern::teacher::Teacher;
+use crate::intern::tracker_dance_collection::ForeignCollectionError;
+use crate::public::progress::{hit_ratio, ActivityId};
+use crate::tracker::local_collection;
+use crate::{StepInfo, Tracker};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+const DAY_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// Days between two reviews, indexed by the level of a step.
+const REVIEW_INTERVAL_DAYS: [f64; 6] = [1.0, 2.0, 4.0, 7.0, 14.0, 30.0];
+
+/// A session at or above this hit ratio moves a step to the next level.
+const PROMOTE_HIT_RATIO: f32 = 0.8;
+/// A session below this hit ratio moves a step back to the first level.
+const RESET_HIT_RATIO: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug)]
+struct StepSchedule {
+    /// Index into `REVIEW_INTERVAL_DAYS`
+    level: usize,
+    /// Timestamp in ms, like JS `Date.now()`
+    last_practice: f64,
+}
+
+/// Decides which steps are due for review, based on past results.
+///
+/// Only steps that have been practiced before are scheduled. Add results in
+/// chronological order, then create a practice tracker for today.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct PracticeScheduler {
+    steps: HashMap<String, StepSchedule>,
+}
+
+#[wasm_bindgen]
+impl PracticeScheduler {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a session result of a step, `timestamp` in ms since the epoch.
+    #[wasm_bindgen(js_name = "addStepResult")]
+    pub fn add_step_result(&mut self, step_id: String, timestamp: f64, hits: u32, misses: u32) {
+        let Some(hit_ratio) = hit_ratio(hits, misses) else {
+            return;
+        };
+        let schedule = self.steps.entry(step_id).or_insert(StepSchedule {
+            level: 0,
+            last_practice: timestamp,
+        });
+        if hit_ratio >= PROMOTE_HIT_RATIO {
+            schedule.level = (schedule.level + 1).min(REVIEW_INTERVAL_DAYS.len() - 1);
+        } else if hit_ratio < RESET_HIT_RATIO {
+            schedule.level = 0;
+        }
+        schedule.last_practice = schedule.last_practice.max(timestamp);
+    }
+
+    /// Add a result by its activity id, as stored by the backend.
+    ///
+    /// Returns false for activities other than steps, which are ignored.
+    #[wasm_bindgen(js_name = "addActivityResult")]
+    pub fn add_activity_result(
+        &mut self,
+        activity_id: &str,
+        timestamp: f64,
+        hits: u32,
+        misses: u32,
+    ) -> bool {
+        let Some(ActivityId::Step { step_id }) = ActivityId::parse(activity_id) else {
+            return false;
+        };
+        self.add_step_result(step_id.to_owned(), timestamp, hits, misses);
+        true
+    }
+
+    /// Step ids due for review at the given time, the most overdue first.
+    #[wasm_bindgen(js_name = "dueSteps")]
+    pub fn due_steps(&self, now: f64) -> Vec<String> {
+        let mut due: Vec<(&String, f64)> = self
+            .steps
+            .iter()
+            .map(|(id, schedule)| (id, schedule.overdue(now)))
+            .filter(|(_id, overdue)| *overdue >= 0.0)
+            .collect();
+        due.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        due.into_iter().map(|(id, _)| id.clone()).collect()
+    }
+
+    /// A tracker to practice up to `max_steps` due steps, or nothing if no
+    /// step is due.
+    ///
+    /// Steps the user struggles with are practiced slower and more often.
+    /// Steps that are no longer loaded are skipped.
+    #[wasm_bindgen(js_name = "practiceTracker")]
+    pub fn practice_tracker(
+        &self,
+        now: f64,
+        max_steps: usize,
+    ) -> Result<Option<Tracker>, ForeignCollectionError> {
+        let mut db = local_collection();
+        let mut teacher = Teacher::default();
+        let mut num_steps = 0;
+
+        crate::STATE.with_borrow(|state| {
+            let global = &state.global_db.tracker_view;
+            // add idle steps to DB, those should always be included in a tracker
+            for step in global.idle_steps() {
+                db.add_foreign_step(global, &step.id)?;
+            }
+            for step_id in self.due_steps(now) {
+                if num_steps >= max_steps {
+                    break;
+                }
+                if global.step(&step_id).is_none() {
+                    continue;
+                }
+                db.add_foreign_step(global, &step_id)?;
+                let step = db.step(&step_id).expect("just added step").clone();
+                let (pace, repeat) = self.steps[&step_id].pace_and_repeat();
+                teacher.add_step(StepInfo::from_step(step, &db), repeat, pace);
+                num_steps += 1;
+            }
+            Ok::<(), ForeignCollectionError>(())
+        })?;
+
+        if num_steps == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Tracker::new_from_teacher(db, teacher)))
+    }
+}
+
+impl StepSchedule {
+    /// How long the review is overdue, in review intervals. Negative if the
+    /// step is not due, yet.
+    fn overdue(&self, now: f64) -> f64 {
+        let interval = REVIEW_INTERVAL_DAYS[self.level] * DAY_MS;
+        (now - self.last_practice - interval) / interval
+    }
+
+    fn pace_and_repeat(&self) -> (StepPace, u32) {
+        match self.level {
+            0 => (StepPace::half_speed(), 8),
+            1 | 2 => (StepPace::normal(), 8),
+            _ => (StepPace::normal(), 4),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review_intervals() {
+        let mut scheduler = PracticeScheduler::new();
+        scheduler.add_step_result("rm-0".to_owned(), 0.0, 9, 1);
+        scheduler.add_step_result("rm-1".to_owned(), 0.0, 3, 7);
+
+        // rm-1 was bad, it is due again the next day
+        assert_eq!(scheduler.due_steps(DAY_MS), vec!["rm-1"]);
+        // rm-0 went well, it can wait another day
+        assert_eq!(scheduler.due_steps(2.0 * DAY_MS), vec!["rm-1", "rm-0"]);
+
+        // another good session pushes it further out
+        scheduler.add_step_result("rm-0".to_owned(), 2.0 * DAY_MS, 8, 2);
+        assert_eq!(scheduler.due_steps(5.0 * DAY_MS), vec!["rm-1"]);
+        assert_eq!(scheduler.due_steps(6.0 * DAY_MS), vec!["rm-1", "rm-0"]);
+    }
+
+    #[test]
+    fn test_bad_session_resets_level() {
+        let mut scheduler = PracticeScheduler::new();
+        for day in 0..4 {
+            scheduler.add_step_result("rm-0".to_owned(), day as f64 * DAY_MS, 10, 0);
+        }
+        assert_eq!(scheduler.steps["rm-0"].level, 4);
+        assert!(scheduler.add_activity_result("step/rm-0[120]", 4.0 * DAY_MS, 1, 9));
+        assert_eq!(scheduler.steps["rm-0"].level, 0);
+        assert!(!scheduler.add_activity_result("course/rm[0]", 4.0 * DAY_MS, 1, 9));
+    }
+}
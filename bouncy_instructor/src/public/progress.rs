@@ -0,0 +1,255 @@
+//! Tracking what a user has mastered, to decide which courses and lessons are
+//! unlocked and what to practice next.
+
+use crate::public::course::{Lesson, Prerequisite};
+use crate::Course;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Hit ratio at which a lesson counts as mastered for display purposes.
+///
+/// Prerequisites define their own minimum, this only decides between
+/// `Unlocked`, `Mastered` and `NeedsReview`.
+const MASTERED_HIT_RATIO: f32 = 0.8;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnlockStatus {
+    /// Some prerequisites are not met, yet.
+    Locked,
+    /// Available to practice, not mastered so far.
+    Unlocked,
+    /// The last session went well.
+    Mastered,
+    /// Mastered before but the last session fell short.
+    NeedsReview,
+}
+
+/// The parts of an activity id, as the frontend submits it to the backend.
+pub(crate) enum ActivityId<'a> {
+    /// `course/{course_id}[{lesson_index}]`
+    Lesson {
+        course_id: &'a str,
+        lesson_index: usize,
+    },
+    /// `step/{step_id}[{bpm}]`
+    Step { step_id: &'a str },
+}
+
+impl<'a> ActivityId<'a> {
+    pub(crate) fn parse(activity_id: &'a str) -> Option<Self> {
+        let (kind, rest) = activity_id.split_once('/')?;
+        let (id, param) = rest.strip_suffix(']')?.rsplit_once('[')?;
+        match kind {
+            "course" => Some(Self::Lesson {
+                course_id: id,
+                lesson_index: param.parse().ok()?,
+            }),
+            "step" => Some(Self::Step { step_id: id }),
+            _ => None,
+        }
+    }
+}
+
+/// Hit ratio of a session, `None` if nothing was detected at all.
+pub(crate) fn hit_ratio(hits: u32, misses: u32) -> Option<f32> {
+    if hits + misses == 0 {
+        None
+    } else {
+        Some(hits as f32 / (hits + misses) as f32)
+    }
+}
+
+/// Hit ratios of all sessions of one activity, summarized.
+#[derive(Clone, Copy, Debug)]
+struct ActivityStats {
+    best_hit_ratio: f32,
+    latest_hit_ratio: f32,
+}
+
+/// Results of past sessions of a user.
+///
+/// Fill it with results in chronological order, then ask for the status of
+/// courses and lessons.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct UserProgress {
+    lessons: HashMap<(String, usize), ActivityStats>,
+    steps: HashMap<String, ActivityStats>,
+}
+
+#[wasm_bindgen]
+impl UserProgress {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[wasm_bindgen(js_name = "addLessonResult")]
+    pub fn add_lesson_result(
+        &mut self,
+        course_id: String,
+        lesson_index: usize,
+        hits: u32,
+        misses: u32,
+    ) {
+        Self::add_result(self.lessons.entry((course_id, lesson_index)), hits, misses);
+    }
+
+    #[wasm_bindgen(js_name = "addStepResult")]
+    pub fn add_step_result(&mut self, step_id: String, hits: u32, misses: u32) {
+        Self::add_result(self.steps.entry(step_id), hits, misses);
+    }
+
+    /// Add a result by its activity id, as stored by the backend.
+    ///
+    /// Returns false for activities other than lessons and steps, which are
+    /// ignored.
+    #[wasm_bindgen(js_name = "addActivityResult")]
+    pub fn add_activity_result(&mut self, activity_id: &str, hits: u32, misses: u32) -> bool {
+        match ActivityId::parse(activity_id) {
+            Some(ActivityId::Lesson {
+                course_id,
+                lesson_index,
+            }) => self.add_lesson_result(course_id.to_owned(), lesson_index, hits, misses),
+            Some(ActivityId::Step { step_id }) => {
+                self.add_step_result(step_id.to_owned(), hits, misses)
+            }
+            None => return false,
+        }
+        true
+    }
+
+    #[wasm_bindgen(js_name = "courseStatus")]
+    pub fn course_status(&self, course: &Course) -> UnlockStatus {
+        if !self.all_met(&course.prerequisites) {
+            return UnlockStatus::Locked;
+        }
+        let statuses: Vec<_> = (0..course.lessons.len())
+            .map(|i| self.lesson_status(course, i))
+            .collect();
+        if statuses.iter().all(|s| *s == UnlockStatus::Mastered) {
+            UnlockStatus::Mastered
+        } else if statuses.contains(&UnlockStatus::NeedsReview) {
+            UnlockStatus::NeedsReview
+        } else {
+            UnlockStatus::Unlocked
+        }
+    }
+
+    /// Status of a lesson, including the prerequisites of its course.
+    #[wasm_bindgen(js_name = "lessonStatus")]
+    pub fn lesson_status(&self, course: &Course, lesson_index: usize) -> UnlockStatus {
+        let Some(lesson) = course.lessons.get(lesson_index) else {
+            return UnlockStatus::Locked;
+        };
+        if !self.lesson_unlocked(course, lesson) {
+            return UnlockStatus::Locked;
+        }
+        match self.lessons.get(&(course.id.clone(), lesson_index)) {
+            Some(stats) if stats.latest_hit_ratio >= MASTERED_HIT_RATIO => UnlockStatus::Mastered,
+            Some(stats) if stats.best_hit_ratio >= MASTERED_HIT_RATIO => UnlockStatus::NeedsReview,
+            _ => UnlockStatus::Unlocked,
+        }
+    }
+
+    /// The first lesson of the course that is unlocked but not mastered,
+    /// including those that need review.
+    #[wasm_bindgen(js_name = "nextLesson")]
+    pub fn next_lesson(&self, course: &Course) -> Option<usize> {
+        (0..course.lessons.len()).find(|&i| {
+            matches!(
+                self.lesson_status(course, i),
+                UnlockStatus::Unlocked | UnlockStatus::NeedsReview
+            )
+        })
+    }
+
+    #[wasm_bindgen(js_name = "lessonsToReview")]
+    pub fn lessons_to_review(&self, course: &Course) -> Vec<usize> {
+        (0..course.lessons.len())
+            .filter(|&i| self.lesson_status(course, i) == UnlockStatus::NeedsReview)
+            .collect()
+    }
+}
+
+impl UserProgress {
+    fn add_result<K>(
+        entry: std::collections::hash_map::Entry<'_, K, ActivityStats>,
+        hits: u32,
+        misses: u32,
+    ) {
+        // sessions without any detected poses say nothing about the skill
+        let Some(hit_ratio) = hit_ratio(hits, misses) else {
+            return;
+        };
+        entry
+            .and_modify(|stats| {
+                stats.best_hit_ratio = stats.best_hit_ratio.max(hit_ratio);
+                stats.latest_hit_ratio = hit_ratio;
+            })
+            .or_insert(ActivityStats {
+                best_hit_ratio: hit_ratio,
+                latest_hit_ratio: hit_ratio,
+            });
+    }
+
+    fn lesson_unlocked(&self, course: &Course, lesson: &Lesson) -> bool {
+        self.all_met(&course.prerequisites) && self.all_met(&lesson.prerequisites)
+    }
+
+    fn all_met(&self, prerequisites: &[Prerequisite]) -> bool {
+        prerequisites.iter().all(|p| self.is_met(p))
+    }
+
+    /// A prerequisite is met once the best session reached the required hit
+    /// ratio. A bad session later on does not lock content again.
+    fn is_met(&self, prerequisite: &Prerequisite) -> bool {
+        let (stats, min_hit_ratio) = match prerequisite {
+            Prerequisite::Lesson {
+                course_id,
+                lesson_index,
+                min_hit_ratio,
+            } => (
+                self.lessons.get(&(course_id.clone(), *lesson_index)),
+                *min_hit_ratio,
+            ),
+            Prerequisite::Step {
+                step_id,
+                min_hit_ratio,
+            } => (self.steps.get(step_id), *min_hit_ratio),
+        };
+        stats.is_some_and(|stats| stats.best_hit_ratio >= min_hit_ratio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_activity_result() {
+        let mut progress = UserProgress::new();
+        assert!(progress.add_activity_result("course/rm-basics[2]", 8, 2));
+        assert!(progress.add_activity_result("step/rm-0[120]", 1, 3));
+        assert!(!progress.add_activity_result("warmup/abc", 5, 0));
+        assert!(!progress.add_activity_result("course/rm-basics[x]", 5, 0));
+
+        let lesson = progress.lessons[&("rm-basics".to_owned(), 2)];
+        assert_eq!(lesson.best_hit_ratio, 0.8);
+        let step = progress.steps["rm-0"];
+        assert_eq!(step.latest_hit_ratio, 0.25);
+    }
+
+    #[test]
+    fn test_best_and_latest_ratio() {
+        let mut progress = UserProgress::new();
+        progress.add_step_result("rm-0".to_owned(), 9, 1);
+        progress.add_step_result("rm-0".to_owned(), 0, 0);
+        progress.add_step_result("rm-0".to_owned(), 1, 1);
+
+        let step = progress.steps["rm-0"];
+        assert_eq!(step.best_hit_ratio, 0.9);
+        assert_eq!(step.latest_hit_ratio, 0.5);
+    }
+}
@@ -1,9 +1,12 @@
 use super::renderable::RenderableSkeleton;
 use crate::intern::body_shift::BodyShift;
+use crate::intern::language::Language;
 use crate::intern::pose::{BodyPart, BodyPoint};
 use crate::intern::skeleton_3d::Skeleton3d;
 use crate::intern::step::Step;
+use crate::intern::step_pace::StepPace;
 use crate::intern::tracker_dance_collection::TrackerDanceCollection;
+use crate::parsing::course_file::TranslatedString;
 use crate::skeleton::{Cartesian2d, Side, Skeleton};
 use crate::STATE;
 
@@ -15,9 +18,16 @@ pub struct StepInfo {
     // TODO: stronger typing
     id: String,
     name: String,
+    names: Option<TranslatedString>,
     step_variation: Option<String>,
+    variation_descriptions: Option<TranslatedString>,
+    lang: Language,
     pub(crate) skeletons: Vec<Skeleton>,
+    /// Pose ID for each skeleton
+    pub(crate) pose_ids: Vec<String>,
     pub(crate) jump_heights: Vec<Option<f32>>,
+    /// How many pose slots each pose is held.
+    pub(crate) durations: Vec<f32>,
     pub(crate) body_shift: BodyShift,
 }
 
@@ -33,6 +43,14 @@ impl StepInfo {
         self.name.clone()
     }
 
+    /// The name in the current language of the collection the step was loaded
+    /// in. Falls back to the untranslated name.
+    pub fn translated_name(&self) -> String {
+        self.lang
+            .translate_opt(self.names.as_ref())
+            .unwrap_or_else(|| self.name.clone())
+    }
+
     pub fn skeleton(&self, beat: usize) -> Skeleton {
         if self.skeletons.is_empty() {
             Skeleton::resting(false)
@@ -66,6 +84,15 @@ impl StepInfo {
         self.jump_heights[beat % self.jump_heights.len()]
     }
 
+    /// How many pose slots the pose is held, 1.0 unless the step defines a
+    /// hold or syncopation.
+    pub fn pose_duration(&self, beat: usize) -> f32 {
+        if self.durations.is_empty() {
+            return 1.0;
+        }
+        self.durations[beat % self.durations.len()]
+    }
+
     /// Description identifier for the translated text which describes how the
     /// variation is different from the original.
     ///
@@ -76,29 +103,59 @@ impl StepInfo {
         self.step_variation.clone()
     }
 
+    /// Translated text which describes how the variation is different from the
+    /// original, if the step defines one.
+    pub fn variation_description(&self) -> Option<String> {
+        self.lang
+            .translate_opt(self.variation_descriptions.as_ref())
+    }
+
     /// The number of poses the step takes for one repetition.
     pub fn num_poses(&self) -> usize {
         self.skeletons.len()
     }
+
+    /// The number of subbeats the step takes for one repetition, at one pose
+    /// slot per subbeat.
+    pub fn subbeats(&self) -> usize {
+        if self.durations.is_empty() {
+            return self.num_poses();
+        }
+        StepPace::normal().step_subbeats(&self.durations) as usize
+    }
+
+    /// The index of the pose shown on the given subbeat, at one pose slot per
+    /// subbeat. Held poses cover several subbeats. Poses of previous
+    /// repetitions are counted, as needed for `body_shift`.
+    pub fn pose_at_subbeat(&self, subbeat: usize) -> usize {
+        StepPace::normal().pose_at_subbeat(&self.durations, subbeat as u32) as usize
+    }
 }
 
 impl StepInfo {
     pub(crate) fn from_step(step: Step, db: &TrackerDanceCollection) -> Self {
         let mut skeletons = vec![];
+        let mut pose_ids = vec![];
         let mut body_shift = BodyShift::new();
 
         for (pose_index, direction) in step.poses.iter().zip(&step.directions) {
             let pose = &db.poses()[*pose_index];
             skeletons.push(Skeleton::from_pose(pose, db, *direction));
+            pose_ids.push(db.pose_id(*pose_index).to_owned());
         }
         body_shift.add_step(&step, &skeletons, db);
 
         Self {
             id: step.id,
             name: step.name,
+            names: step.names,
             step_variation: step.variation,
+            variation_descriptions: step.variation_descriptions,
+            lang: db.lang().clone(),
             skeletons,
+            pose_ids,
             jump_heights: step.jump_heights,
+            durations: step.durations,
             body_shift,
         }
     }
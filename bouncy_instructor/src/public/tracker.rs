@@ -1,11 +1,21 @@
+mod bounce_output;
 mod detection_output;
 mod frame_output;
 mod pose_output;
+mod repetition_output;
+mod session_output;
 mod step_output;
 mod teacher_output;
 
-pub use detection_output::{DetectionFailureReason, DetectionResult, PoseHint};
-pub use pose_output::PoseApproximation;
+pub use bounce_output::{Bounce, BounceInstruction, BounceScore, BounceTiming};
+pub use detection_output::{
+    DetectionFailureReason, DetectionResult, FramingHint, PoseHint, TravelHint,
+};
+pub use pose_output::{CorrectionKind, PoseApproximation, PoseCorrection};
+pub use repetition_output::{Repetition, RepetitionError};
+pub use session_output::{
+    AccuracyScore, Adaptation, HintCount, HistogramError, SessionReport, TimingBin,
+};
 pub use step_output::DetectedStep;
 pub use teacher_output::DanceCursor;
 pub use teacher_output::TeacherView;
@@ -13,14 +23,18 @@ pub use teacher_output::TeacherView;
 use super::renderable::RenderableSkeleton;
 use super::wrapper::skeleton_wrapper::SkeletonWrapper;
 use super::TextEffect;
+use crate::intern::bounce::BounceTracking;
 use crate::intern::dance_detector::{DanceDetector, DetectionState};
+use crate::intern::loop_practice::{LoopPart, LoopPractice};
+use crate::intern::rep_counter::RepCounter;
 use crate::intern::skeleton_3d::Skeleton3d;
 use crate::intern::step_pace::StepPace;
 use crate::intern::teacher::Teacher;
+use crate::intern::tempo_map::TempoMap;
 use crate::intern::tracker_dance_collection::{ForeignCollectionError, TrackerDanceCollection};
 use crate::keypoints::{Cartesian3d, Keypoints};
 use crate::skeleton::{Cartesian2d, Skeleton};
-use crate::{AudioEffect, StepInfo};
+use crate::{AudioEffect, BeatsError, DetectorConfig, LoopConfig, Meter, StepInfo};
 use std::rc::Rc;
 use wasm_bindgen::prelude::wasm_bindgen;
 
@@ -46,6 +60,13 @@ pub struct Skeletons {
     pub front: Skeleton,
     pub side: Skeleton,
 }
+/// An empty collection for a tracker that only knows a subset of all steps.
+///
+/// Names are displayed in the language of the global collection.
+pub(crate) fn local_collection() -> TrackerDanceCollection {
+    crate::STATE.with_borrow(|state| TrackerDanceCollection::new(state.global_db.lang().clone()))
+}
+
 impl Default for Tracker {
     fn default() -> Self {
         let db = TrackerDanceCollection::default();
@@ -85,6 +106,19 @@ impl Tracker {
         }
     }
 
+    /// Loop over `parts`, or `None` if there is nothing to loop.
+    pub(crate) fn new_loop(
+        db: impl Into<Rc<TrackerDanceCollection>>,
+        parts: &[LoopPart],
+        config: &LoopConfig,
+        meter: Meter,
+    ) -> Option<Self> {
+        let (teacher, practice) = LoopPractice::teacher(parts, config, meter)?;
+        let mut tracker = Tracker::new_from_teacher(db, teacher);
+        tracker.detector.loop_practice = Some(practice);
+        Some(tracker)
+    }
+
     /// Create a tracker for all known steps.
     #[wasm_bindgen(constructor)]
     pub fn new_from_global_collection() -> Self {
@@ -95,7 +129,7 @@ impl Tracker {
     /// Track one specific step, by name, including its variations (with the same name).
     #[wasm_bindgen(js_name = "StepTracker")]
     pub fn new_step_tracker(step_name: String) -> Result<Tracker, ForeignCollectionError> {
-        let mut db = TrackerDanceCollection::default();
+        let mut db = local_collection();
         crate::STATE.with_borrow(|state| {
             for step in state.global_db.tracker_view.steps_by_name(&step_name) {
                 db.add_foreign_step(&state.global_db.tracker_view, &step.id)?;
@@ -115,7 +149,7 @@ impl Tracker {
     /// specific training session without much regard for timing etc.
     #[wasm_bindgen(js_name = "UniqueStepTracker")]
     pub fn new_unique_step_tracker(step_id: String) -> Result<Tracker, ForeignCollectionError> {
-        let mut db = TrackerDanceCollection::default();
+        let mut db = local_collection();
         crate::STATE.with_borrow(|state| {
             db.add_foreign_pose_by_id(&state.global_db.tracker_view, "standing-straight-side");
             db.add_foreign_pose_by_id(&state.global_db.tracker_view, "standing-straight-front");
@@ -128,6 +162,36 @@ impl Tracker {
         Ok(Tracker::new(db, Some(step_info), None))
     }
 
+    /// Count repetitions of one step, by ID, danced at any speed.
+    ///
+    /// There is no countdown and no beat, tracking starts right away. Fails
+    /// for steps without poses, as there is nothing to count.
+    #[wasm_bindgen(js_name = "RepetitionTracker")]
+    pub fn new_repetition_tracker(step_id: String) -> Result<Tracker, RepetitionError> {
+        let mut tracker = Tracker::new_unique_step_tracker(step_id.clone())?;
+        let step = match tracker.detector.teacher.step_at_subbeat(0) {
+            Some((step, _)) if step.num_poses() > 0 => step.clone(),
+            _ => return Err(RepetitionError::NoPoses(step_id)),
+        };
+        tracker.detector.rep_counter = Some(RepCounter::new(step));
+        Ok(tracker)
+    }
+
+    /// Score bouncing to the music for `num_beats`, on the beat or off-beat
+    /// as instructed, without matching any poses.
+    #[wasm_bindgen(js_name = "BounceTracker")]
+    pub fn new_bounce_tracker(num_beats: u32, instruction: BounceInstruction) -> Tracker {
+        let mut db = local_collection();
+        crate::STATE.with_borrow(|state| {
+            db.add_foreign_pose_by_id(&state.global_db.tracker_view, "standing-straight-front");
+        });
+        let mut teacher = Teacher::default();
+        teacher.add_freestyle(num_beats);
+        let mut tracker = Tracker::new_from_teacher(db, teacher);
+        tracker.detector.bounce = Some(BounceTracking::new(instruction));
+        tracker
+    }
+
     /// Mix a warmup with the given steps, by name.
     ///
     ///
@@ -141,7 +205,7 @@ impl Tracker {
             step_names.len(),
             "must have at least one step for warmup"
         );
-        let mut db = TrackerDanceCollection::default();
+        let mut db = local_collection();
         let mut teacher = Teacher::default();
         // TODO: Allow different paces
         let pace1 = StepPace::half_speed();
@@ -208,6 +272,21 @@ impl Tracker {
             .transition_to_state(DetectionState::TrackingDone, now)
     }
 
+    /// Interrupt tracking, keeping everything detected so far.
+    ///
+    /// Only has an effect during the countdown and tracking.
+    pub fn pause(&mut self) {
+        let now = *self.timestamps.last().unwrap_or(&0.0);
+        self.detector.pause(now);
+    }
+
+    /// Continue after `pause`, with a positioning check and a countdown
+    /// before the teacher continues where it stopped.
+    pub fn resume(&mut self) {
+        let now = *self.timestamps.last().unwrap_or(&0.0);
+        self.detector.resume(now);
+    }
+
     pub fn clear(&mut self) {
         self.keypoints.clear();
         self.timestamps.clear();
@@ -236,14 +315,47 @@ impl Tracker {
         Skeletons { front, side }
     }
 
+    /// Follow a steady tempo, replacing beats set with `setBeats`.
     #[wasm_bindgen(js_name = setBpm)]
     pub fn set_bpm(&mut self, bpm: f32) {
-        self.detector.bpm = bpm;
+        self.detector.tempo = self.detector.tempo.with_bpm(bpm);
     }
 
+    /// Move the beats to start at `first_beat`, keeping the tempo.
     #[wasm_bindgen(js_name = alignBeat)]
     pub fn align_beat(&mut self, first_beat: Timestamp) {
-        self.detector.beat_alignment = Some(first_beat);
+        self.detector.tempo = self.detector.tempo.aligned(first_beat);
+    }
+
+    /// Follow the given beat timestamps, for music with a drifting tempo, for
+    /// example the beats of a `VideoDef`.
+    ///
+    /// Needs at least two beats in strictly increasing order, otherwise the
+    /// tempo remains unchanged.
+    #[wasm_bindgen(js_name = setBeats)]
+    pub fn set_beats(&mut self, beats: Vec<Timestamp>) -> Result<(), BeatsError> {
+        self.detector.tempo = TempoMap::from_beats(beats)?;
+        Ok(())
+    }
+
+    /// Group beats into bars and subbeats, for example for a waltz or swing.
+    ///
+    /// This changes the length of the tracked activity in subbeats and the
+    /// countdown.
+    #[wasm_bindgen(js_name = setMeter)]
+    pub fn set_meter(&mut self, meter: &Meter) {
+        self.detector.teacher.set_meter(*meter);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn meter(&self) -> Meter {
+        *self.detector.meter()
+    }
+
+    /// Tempo of the music around `t`.
+    #[wasm_bindgen(js_name = bpmAt)]
+    pub fn bpm_at(&self, t: Timestamp) -> f32 {
+        self.detector.tempo.bpm_at(t)
     }
 
     #[wasm_bindgen(js_name = enforceBeat)]
@@ -251,9 +363,89 @@ impl Tracker {
         self.detector.force_beat = yes;
     }
 
+    /// In loop practice, the loop after which the dancer reached the mastery
+    /// hit rate, starting at 0. Tracking is done after that loop.
+    #[wasm_bindgen(getter, js_name = masteredLoop)]
+    pub fn mastered_loop(&self) -> Option<u32> {
+        self.detector.mastered_loop
+    }
+
+    /// Bounces found so far by a bounce tracker.
+    pub fn bounces(&self) -> Vec<Bounce> {
+        self.detector
+            .bounce
+            .as_ref()
+            .map(|tracking| tracking.bounces.clone())
+            .unwrap_or_default()
+    }
+
+    /// Timing score of a bounce tracker, once there are bounces.
+    #[wasm_bindgen(getter, js_name = bounceScore)]
+    pub fn bounce_score(&self) -> Option<BounceScore> {
+        let tracking = self.detector.bounce.as_ref()?;
+        BounceScore::new(&tracking.bounces, tracking.instruction.timing())
+    }
+
+    /// Repetitions counted so far in repetition mode.
+    pub fn repetitions(&self) -> Vec<Repetition> {
+        self.detector
+            .rep_counter
+            .as_ref()
+            .map(|counter| counter.repetitions.clone())
+            .unwrap_or_default()
+    }
+
+    /// Repetitions started but abandoned for taking too long, in repetition
+    /// mode.
+    #[wasm_bindgen(getter, js_name = abandonedRepetitions)]
+    pub fn abandoned_repetitions(&self) -> u32 {
+        self.detector
+            .rep_counter
+            .as_ref()
+            .map_or(0, |counter| counter.abandoned)
+    }
+
+    /// The pose of the step the dancer has to match next, in repetition mode.
+    #[wasm_bindgen(getter, js_name = repetitionPoseIndex)]
+    pub fn repetition_pose_index(&self) -> Option<usize> {
+        self.detector
+            .rep_counter
+            .as_ref()
+            .map(RepCounter::next_pose)
+    }
+
+    /// Adjust error thresholds and the pace of upcoming sections to how well
+    /// the dancer hits poses.
+    #[wasm_bindgen(js_name = setAdaptive)]
+    pub fn set_adaptive(&mut self, yes: bool) {
+        self.detector.adaptive = yes;
+    }
+
+    /// Set the size of the video the keypoints are detected in. Keypoints are
+    /// normalized separately per axis, which distorts the body proportions
+    /// unless the video is square.
+    ///
+    /// Ignored unless both sizes are positive.
+    #[wasm_bindgen(js_name = setVideoSize)]
+    pub fn set_video_size(&mut self, width: f32, height: f32) {
+        if width > 0.0 && height > 0.0 && (width / height).is_finite() {
+            self.detector.aspect_ratio = width / height;
+        }
+    }
+
     #[wasm_bindgen(js_name = setErrorThreshold)]
     pub fn set_error_threshold(&mut self, error_threshold: f32) {
-        self.detector.error_threshold = error_threshold;
+        self.detector.config.error_threshold = error_threshold;
+    }
+
+    #[wasm_bindgen(js_name = setDetectorConfig)]
+    pub fn set_detector_config(&mut self, config: &DetectorConfig) {
+        self.detector.config = config.clone();
+    }
+
+    #[wasm_bindgen(getter, js_name = detectorConfig)]
+    pub fn detector_config(&self) -> DetectorConfig {
+        self.detector.config.clone()
     }
 
     #[wasm_bindgen(js_name = useTeacherVideo)]
@@ -294,7 +486,8 @@ impl Tracker {
         let now = *self.timestamps.last().unwrap_or(&0.0);
         let db = &self.db;
         let skeletons = &self.skeletons;
-        self.detector.tick(now, db, skeletons)
+        self.detector
+            .tick(now, db, skeletons, &self.timestamps, &self.keypoints)
     }
 
     #[wasm_bindgen(js_name = poseHint)]
@@ -302,6 +495,13 @@ impl Tracker {
         self.detector.detected.pose_hint()
     }
 
+    /// How the dancer should change their placement in front of the camera
+    /// before tracking can start, while positioning.
+    #[wasm_bindgen(getter, js_name = framingHint)]
+    pub fn framing_hint(&self) -> Option<FramingHint> {
+        self.detector.framing_hint
+    }
+
     #[wasm_bindgen(js_name = currentPoseError)]
     pub fn current_pose_error(&self) -> Option<PoseApproximation> {
         self.detector.detected.pose_error()
@@ -328,9 +528,20 @@ impl Tracker {
         self.detector.next_subbeat_timestamp(now)
     }
 
+    /// Duration of a subbeat at the time of the latest keypoints.
     #[wasm_bindgen(getter, js_name = timeBetweenPoses)]
     pub fn time_between_poses(&self) -> f64 {
-        self.detector.subbeat_time()
+        let now = *self.timestamps.last().unwrap_or(&0.0);
+        self.detector.subbeat_time(now)
+    }
+
+    /// How long the pose at the cursor is held, in ms. Held and syncopated
+    /// poses take more or less than `timeBetweenPoses`, the avatar animation
+    /// uses this to time the movement into the next pose.
+    #[wasm_bindgen(js_name = poseDuration)]
+    pub fn pose_duration(&self, cursor: &DanceCursor) -> f64 {
+        let subbeats = self.detector.teacher.pose_duration(cursor).unwrap_or(1);
+        subbeats as f64 * self.time_between_poses()
     }
 
     #[wasm_bindgen(js_name = nextAudioEffect)]
@@ -346,7 +557,7 @@ impl Tracker {
     /// How long the tracked activity is in total, measured in milliseconds.
     #[wasm_bindgen(js_name = duration)]
     pub fn duration(&mut self) -> f64 {
-        self.time_between_poses() * self.tracked_subbeats() as f64
+        self.detector.tracked_duration()
     }
 
     /// Return a skeleton that's expected now.
@@ -453,6 +664,18 @@ impl Tracker {
         self.detector.detected.clone()
     }
 
+    /// Summary of the tracked session so far, meant to be shown when
+    /// tracking is done.
+    #[wasm_bindgen(js_name = sessionReport)]
+    pub fn session_report(&self) -> SessionReport {
+        SessionReport {
+            log: self.detector.session.clone(),
+            steps: self.detector.detected.steps.clone(),
+            pose_matches: self.detector.detected.pose_matches,
+            pose_misses: self.detector.detected.pose_misses,
+        }
+    }
+
     #[wasm_bindgen(js_name = hipPosition)]
     pub fn hip_position(&self, timestamp: Timestamp) -> Cartesian3d {
         let i = self.timestamps.partition_point(|t| *t < timestamp);
@@ -486,6 +709,20 @@ impl Tracker {
         self.all_pose_approximations(timestamp)
     }
 
+    /// Fit the frame at the given time against the pose the teacher expects
+    /// at the cursor.
+    #[wasm_bindgen(js_name = expectedPoseError)]
+    pub fn expected_pose_error(
+        &self,
+        cursor: &DanceCursor,
+        timestamp: Timestamp,
+    ) -> Option<pose_output::PoseApproximation> {
+        let step = self.detector.step(cursor)?;
+        let pose_id = step.pose_ids.get(cursor.pose_index)?;
+        let pose_index = self.db.pose_by_id(pose_id)?;
+        self.pose_approximation(pose_index, timestamp)
+    }
+
     #[wasm_bindgen(js_name = skeletonAt)]
     pub fn skeleton_at(&self, timestamp: Timestamp) -> Option<Skeleton> {
         let i = self.timestamps.partition_point(|t| *t < timestamp);
@@ -0,0 +1,84 @@
+use super::Timestamp;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// What a bounce tracker asks the dancer to do.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BounceInstruction {
+    OnBeat,
+    /// Halfway between two beats, on the "and".
+    OffBeat,
+}
+
+/// When in the beat a bounce goes down.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BounceTiming {
+    OnBeat,
+    /// Halfway between two beats, on the "and".
+    OffBeat,
+    /// Neither on the beat nor on the off-beat.
+    Between,
+}
+
+/// The lowest point of a bounce, scored against the beat.
+#[derive(Debug, Clone, Copy)]
+#[wasm_bindgen]
+pub struct Bounce {
+    pub timestamp: Timestamp,
+    pub timing: BounceTiming,
+    /// Time in ms from the instructed beat or off-beat, negative if early.
+    pub offset: f64,
+    /// Same as `offset` but in beats, between -0.5 and 0.5.
+    #[wasm_bindgen(js_name = beatOffset)]
+    pub beat_offset: f64,
+}
+
+/// How well the dancer bounced to the music, over all bounces so far.
+#[derive(Debug, Clone, Copy)]
+#[wasm_bindgen]
+pub struct BounceScore {
+    /// Bounces with the instructed timing.
+    pub hits: u32,
+    /// Bounces with any other timing.
+    pub misses: u32,
+    /// How steady the bounces are relative to the beat, regardless of the
+    /// instructed timing. 1.0 means always at the same point in the beat, 0.0
+    /// means no relation to the beat.
+    pub consistency: f32,
+    /// Average offset in ms from the instructed beat or off-beat, negative if
+    /// early.
+    #[wasm_bindgen(js_name = meanOffset)]
+    pub mean_offset: f64,
+}
+
+#[wasm_bindgen]
+impl BounceScore {
+    /// Share of bounces with the instructed timing, between 0.0 and 1.0.
+    #[wasm_bindgen(getter, js_name = hitRate)]
+    pub fn hit_rate(&self) -> f32 {
+        self.hits as f32 / (self.hits + self.misses).max(1) as f32
+    }
+}
+
+impl BounceScore {
+    /// `None` if there are no bounces to score.
+    pub(crate) fn new(bounces: &[Bounce], target: BounceTiming) -> Option<Self> {
+        if bounces.is_empty() {
+            return None;
+        }
+        let n = bounces.len() as f64;
+        let hits = bounces.iter().filter(|b| b.timing == target).count() as u32;
+        // mean resultant length of the bounce phases within the beat
+        let (sin, cos) = bounces.iter().fold((0.0, 0.0), |(sin, cos), b| {
+            let angle = b.beat_offset * std::f64::consts::TAU;
+            (sin + angle.sin(), cos + angle.cos())
+        });
+        Some(Self {
+            hits,
+            misses: bounces.len() as u32 - hits,
+            consistency: ((sin * sin + cos * cos).sqrt() / n) as f32,
+            mean_offset: bounces.iter().map(|b| b.offset).sum::<f64>() / n,
+        })
+    }
+}
@@ -50,12 +50,43 @@ pub enum DetectionFailureReason {
 
 /// Best guess for what the dancer needs to change to fit the pose.
 #[wasm_bindgen]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PoseHint {
     DontKnow,
     LeftRight,
     ZOrder,
     WrongDirection,
+    /// The pose fits but the jump into it was too low.
+    JumpHigher,
+}
+
+/// Which way the dancer should travel more to match the step, as seen by the
+/// camera.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TravelHint {
+    FurtherLeft,
+    FurtherRight,
+}
+
+/// What keeps the dancer from being well placed in the camera frame, checked
+/// before tracking starts.
+///
+/// Left and right are as seen by the camera, not mirrored. The text shown to
+/// the dancer is mirrored to the dancer's view.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FramingHint {
+    NotFullyVisible,
+    TooClose,
+    TooFar,
+    /// Move to the left of the camera, the right of a dancer facing it.
+    MoveLeft,
+    /// Move to the right of the camera, the left of a dancer facing it.
+    MoveRight,
+    CameraTilted,
+    FaceCamera,
+    TurnSideways,
 }
 
 #[wasm_bindgen]
@@ -81,6 +112,12 @@ impl DetectionResult {
         }
     }
 
+    /// How to fix the travel of the latest detected step, if it was off.
+    #[wasm_bindgen(js_name = travelHint)]
+    pub fn travel_hint(&self) -> Option<TravelHint> {
+        self.steps.last().and_then(|step| step.travel_hint)
+    }
+
     #[wasm_bindgen(js_name = poseError)]
     pub fn pose_error(&self) -> Option<PoseApproximation> {
         self.last_error.as_ref().map(|(_hint, err)| err.clone())
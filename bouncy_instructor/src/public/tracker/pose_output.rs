@@ -1,8 +1,10 @@
 use super::Timestamp;
-use crate::intern::pose::{BodyPoint, Limb};
+use crate::intern::jump::JumpScore;
+use crate::intern::pose::{BodyPartOrdering, BodyPoint, Limb};
 use crate::intern::pose_score::ErrorDetails;
 use crate::intern::tracker_dance_collection::LimbIndex;
 use crate::renderable::{RenderableSegment, RenderableSkeleton};
+use crate::step_file::BodyRegion;
 use crate::STATE;
 use wasm_bindgen::prelude::wasm_bindgen;
 
@@ -19,6 +21,8 @@ pub struct PoseApproximation {
     /// Timestamp for which Keypoints were added
     pub timestamp: Timestamp,
     pub(crate) error_details: ErrorDetails,
+    /// Set if the step expects a jump into the pose.
+    pub(crate) jump: Option<JumpScore>,
 }
 
 /// Self-describing error score for a specific limb
@@ -31,6 +35,42 @@ pub struct LimbError {
     pub weight: f32,
 }
 
+/// A concrete change that brings the dancer closer to the pose.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct PoseCorrection {
+    pub kind: CorrectionKind,
+    /// The limb to move, for limb corrections.
+    pub(crate) limb: Option<LimbIndex>,
+    /// The body parts in the wrong order, for ordering corrections.
+    pub(crate) ordering: Option<BodyPartOrdering>,
+    /// How much the correction reduces the pose error, between 0.0 and 1.0.
+    pub impact: f32,
+}
+
+/// What to change about a limb or body part.
+///
+/// Forward and backward are relative to where the dancer faces, outward and
+/// inward relative to the middle of the body.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CorrectionKind {
+    /// Lift the limb further away from pointing down.
+    Raise,
+    /// Bring the limb closer to pointing down.
+    Lower,
+    Forward,
+    Backward,
+    Outward,
+    Inward,
+    /// The hips point the wrong way.
+    TurnHips,
+    /// The shoulders point the wrong way.
+    TurnShoulders,
+    /// One body part should be in front of another.
+    InFront,
+}
+
 #[wasm_bindgen]
 pub struct ZError {
     body_point: BodyPoint,
@@ -91,6 +131,45 @@ impl PoseApproximation {
             .collect()
     }
 
+    /// How much lower than expected the dancer jumped into the pose, between
+    /// 0.0 and 1.0.
+    ///
+    /// Returns `undefined` if the pose expects no jump.
+    #[wasm_bindgen(getter, js_name = jumpError)]
+    pub fn jump_error(&self) -> Option<f32> {
+        self.jump.map(|jump| jump.error)
+    }
+
+    /// Measured jump height, in the units of the step's jump height.
+    #[wasm_bindgen(getter, js_name = jumpHeight)]
+    pub fn jump_height(&self) -> Option<f32> {
+        self.jump.map(|jump| jump.measured)
+    }
+
+    /// Jump height defined in the step.
+    #[wasm_bindgen(getter, js_name = expectedJumpHeight)]
+    pub fn expected_jump_height(&self) -> Option<f32> {
+        self.jump.map(|jump| jump.expected)
+    }
+
+    /// Error of the limbs in one body region, for example to score the arms
+    /// of a layered step separately from the footwork.
+    ///
+    /// Returns `undefined` if the pose doesn't define limbs in the region.
+    #[wasm_bindgen(js_name = regionError)]
+    pub fn region_error(&self, region: BodyRegion) -> Option<f32> {
+        self.error_details.region_error_score(region)
+    }
+
+    /// Corrections to bring the dancer closer to the pose, most important
+    /// first.
+    pub fn corrections(&self) -> Vec<PoseCorrection> {
+        STATE.with_borrow(|state| {
+            self.error_details
+                .corrections(&state.global_db.tracker_view)
+        })
+    }
+
     /// List the `n` limbs with the highest error contribution to the pose error.
     #[wasm_bindgen(js_name = worstLimbs)]
     pub fn worst_limbs(&self, n: usize) -> Vec<LimbError> {
@@ -110,18 +189,11 @@ impl PoseApproximation {
             .sorted_by_error(increasing, weighted)
             .into_iter()
             .map(|i| {
-                let limb_index = self.error_details.limbs[i];
-                STATE.with_borrow(|state| LimbError {
-                    name: state
-                        .global_db
-                        .tracker_view
-                        .limb_name(limb_index)
-                        .to_owned(),
-                    limb: *state.global_db.tracker_view.limb(limb_index),
-                    limb_index,
-                    error: self.error_details.errors[i],
-                    weight: self.error_details.weights[i],
-                })
+                LimbError::new(
+                    self.error_details.limbs[i],
+                    self.error_details.errors[i],
+                    self.error_details.weights[i],
+                )
             })
     }
 
@@ -131,6 +203,22 @@ impl PoseApproximation {
     }
 }
 
+impl LimbError {
+    pub(crate) fn new(limb_index: LimbIndex, error: f32, weight: f32) -> Self {
+        STATE.with_borrow(|state| LimbError {
+            name: state
+                .global_db
+                .tracker_view
+                .limb_name(limb_index)
+                .to_owned(),
+            limb: *state.global_db.tracker_view.limb(limb_index),
+            limb_index,
+            error,
+            weight,
+        })
+    }
+}
+
 #[wasm_bindgen]
 impl LimbError {
     #[wasm_bindgen(getter)]
@@ -157,6 +245,44 @@ impl LimbError {
     }
 }
 
+#[wasm_bindgen]
+impl PoseCorrection {
+    /// Name of the limb to move, for example "LeftThigh".
+    #[wasm_bindgen(getter, js_name = limbName)]
+    pub fn limb_name(&self) -> Option<String> {
+        let limb_index = self.limb?;
+        STATE.with_borrow(|state| {
+            Some(
+                state
+                    .global_db
+                    .tracker_view
+                    .limb_name(limb_index)
+                    .to_owned(),
+            )
+        })
+    }
+
+    /// The body part that should be in front, for example "LeftKnee".
+    #[wasm_bindgen(getter, js_name = frontPart)]
+    pub fn front_part(&self) -> Option<String> {
+        self.ordering
+            .as_ref()
+            .map(|ordering| body_point_name(ordering.forward))
+    }
+
+    /// The body part that should be behind, for example "RightKnee".
+    #[wasm_bindgen(getter, js_name = backPart)]
+    pub fn back_part(&self) -> Option<String> {
+        self.ordering
+            .as_ref()
+            .map(|ordering| body_point_name(ordering.backward))
+    }
+}
+
+fn body_point_name(point: BodyPoint) -> String {
+    format!("{:?}{:?}", point.side, point.part)
+}
+
 #[wasm_bindgen]
 impl ZError {
     #[wasm_bindgen(getter)]
@@ -0,0 +1,37 @@
+use super::Timestamp;
+use crate::intern::tracker_dance_collection::ForeignCollectionError;
+use thiserror::Error;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+/// A repetition of a step counted in repetition mode, with all poses matched
+/// in order.
+#[derive(Debug, Clone, Copy)]
+#[wasm_bindgen]
+pub struct Repetition {
+    /// Counting from 1.
+    pub number: u32,
+    /// When the first pose was matched.
+    pub start: Timestamp,
+    /// When the last pose was matched.
+    pub end: Timestamp,
+    /// Average error of the matched poses.
+    pub error: f32,
+    /// How far below the error thresholds the poses were on average, 1.0 is
+    /// perfect and 0.0 barely matched.
+    pub quality: f32,
+}
+
+#[derive(Error, Debug)]
+pub enum RepetitionError {
+    #[error(transparent)]
+    Collection(#[from] ForeignCollectionError),
+    #[error("step `{0}` has no poses to count")]
+    NoPoses(String),
+}
+
+impl From<RepetitionError> for JsValue {
+    fn from(value: RepetitionError) -> Self {
+        format!("{value}").into()
+    }
+}
@@ -0,0 +1,232 @@
+use super::pose_output::LimbError;
+use super::{DetectedStep, PoseHint};
+use crate::intern::session_log::SessionLog;
+use thiserror::Error;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
+
+/// Summary of a tracking session, to show the dancer what went well and what
+/// didn't after tracking is done.
+#[derive(Debug, Clone)]
+#[wasm_bindgen]
+pub struct SessionReport {
+    pub(crate) log: SessionLog,
+    pub(crate) steps: Vec<DetectedStep>,
+    #[wasm_bindgen(js_name = poseMatches)]
+    pub pose_matches: u32,
+    #[wasm_bindgen(js_name = poseMisses)]
+    pub pose_misses: u32,
+}
+
+/// Pose matches and misses of a step, either overall or in one teacher
+/// section.
+#[derive(Debug, Clone)]
+#[wasm_bindgen]
+pub struct AccuracyScore {
+    pub(crate) step_id: String,
+    pub(crate) step_name: String,
+    pub(crate) section_index: Option<usize>,
+    error_sum: f32,
+    #[wasm_bindgen(js_name = poseMatches)]
+    pub pose_matches: u32,
+    #[wasm_bindgen(js_name = poseMisses)]
+    pub pose_misses: u32,
+}
+
+/// Number of poses with a timing offset between `start` and `end`, in ms
+/// relative to the beat.
+#[derive(Debug, Clone, Copy)]
+#[wasm_bindgen]
+pub struct TimingBin {
+    pub start: f64,
+    pub end: f64,
+    pub count: u32,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum HistogramError {
+    #[error("bin width must be a positive number of ms, found {0}")]
+    BinWidth(f64),
+}
+
+impl From<HistogramError> for JsValue {
+    fn from(value: HistogramError) -> Self {
+        format!("{value}").into()
+    }
+}
+
+/// A change of difficulty in adaptive mode, after the dancer hit many or few
+/// poses in a section.
+#[derive(Debug, Clone, Copy)]
+#[wasm_bindgen]
+pub struct Adaptation {
+    /// The section that was scored.
+    #[wasm_bindgen(js_name = scoredSection)]
+    pub scored_section: usize,
+    /// Share of the section's poses that were matched.
+    #[wasm_bindgen(js_name = hitRate)]
+    pub hit_rate: f32,
+    /// Whether it became easier or harder.
+    pub easier: bool,
+    /// The section slowed down or sped up, unless the pace was at its limit.
+    pub(crate) paced_section: Option<usize>,
+    /// Factor on the pose error thresholds after the adaptation.
+    #[wasm_bindgen(js_name = thresholdFactor)]
+    pub threshold_factor: f32,
+}
+
+/// How many poses a hint was given for.
+#[derive(Debug, Clone, Copy)]
+#[wasm_bindgen]
+pub struct HintCount {
+    pub hint: PoseHint,
+    pub count: u32,
+}
+
+#[wasm_bindgen]
+impl SessionReport {
+    /// Share of poses matched, between 0.0 and 1.0.
+    #[wasm_bindgen(getter)]
+    pub fn accuracy(&self) -> f32 {
+        accuracy(self.pose_matches, self.pose_misses)
+    }
+
+    /// Accuracy per section of the tracked activity, in order.
+    #[wasm_bindgen(js_name = sectionAccuracy)]
+    pub fn section_accuracy(&self) -> Vec<AccuracyScore> {
+        self.log.section_accuracy()
+    }
+
+    /// Accuracy per step, over all sections it appears in.
+    #[wasm_bindgen(js_name = stepAccuracy)]
+    pub fn step_accuracy(&self) -> Vec<AccuracyScore> {
+        self.log.step_accuracy()
+    }
+
+    /// How early or late matched poses were, counted in bins of `binWidth`
+    /// ms. Negative offsets are early.
+    #[wasm_bindgen(js_name = timingHistogram)]
+    pub fn timing_histogram(&self, bin_width: f64) -> Result<Vec<TimingBin>, HistogramError> {
+        self.log.timing_histogram(bin_width)
+    }
+
+    /// Average timing offset of matched poses in ms, negative if early.
+    #[wasm_bindgen(getter, js_name = meanTimingOffset)]
+    pub fn mean_timing_offset(&self) -> Option<f64> {
+        let offsets: Vec<f64> = self.log.timing_offsets().collect();
+        (!offsets.is_empty()).then(|| offsets.iter().sum::<f64>() / offsets.len() as f64)
+    }
+
+    /// The `n` most frequent hints, counted once per pose they were given for.
+    #[wasm_bindgen(js_name = frequentHints)]
+    pub fn frequent_hints(&self, n: usize) -> Vec<HintCount> {
+        self.log.hint_counts().into_iter().take(n).collect()
+    }
+
+    /// The `n` limbs with the highest average error contribution over all
+    /// poses of the session.
+    #[wasm_bindgen(js_name = worstLimbs)]
+    pub fn worst_limbs(&self, n: usize) -> Vec<LimbError> {
+        self.log
+            .limb_errors()
+            .into_iter()
+            .take(n)
+            .map(|(limb_index, error, weight)| LimbError::new(limb_index, error, weight))
+            .collect()
+    }
+
+    /// How the difficulty changed during the session, in adaptive mode.
+    pub fn adaptations(&self) -> Vec<Adaptation> {
+        self.log.adaptations.clone()
+    }
+
+    /// The step with the lowest error, use `start` and `end` to replay it.
+    #[wasm_bindgen(js_name = bestStep)]
+    pub fn best_step(&self) -> Option<DetectedStep> {
+        self.steps
+            .iter()
+            .min_by(|a, b| f32::total_cmp(&a.error, &b.error))
+            .cloned()
+    }
+
+    /// The step with the highest error, use `start` and `end` to replay it.
+    #[wasm_bindgen(js_name = worstStep)]
+    pub fn worst_step(&self) -> Option<DetectedStep> {
+        self.steps
+            .iter()
+            .max_by(|a, b| f32::total_cmp(&a.error, &b.error))
+            .cloned()
+    }
+}
+
+#[wasm_bindgen]
+impl Adaptation {
+    #[wasm_bindgen(getter, js_name = pacedSection)]
+    pub fn paced_section(&self) -> Option<usize> {
+        self.paced_section
+    }
+}
+
+#[wasm_bindgen]
+impl AccuracyScore {
+    #[wasm_bindgen(getter, js_name = stepId)]
+    pub fn step_id(&self) -> String {
+        self.step_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = stepName)]
+    pub fn step_name(&self) -> String {
+        self.step_name.clone()
+    }
+
+    /// Set for scores of a single section.
+    #[wasm_bindgen(getter, js_name = sectionIndex)]
+    pub fn section_index(&self) -> Option<usize> {
+        self.section_index
+    }
+
+    /// Share of poses matched, between 0.0 and 1.0.
+    #[wasm_bindgen(getter)]
+    pub fn accuracy(&self) -> f32 {
+        accuracy(self.pose_matches, self.pose_misses)
+    }
+
+    /// Average pose error, including missed poses.
+    #[wasm_bindgen(getter, js_name = averageError)]
+    pub fn average_error(&self) -> f32 {
+        let poses = self.pose_matches + self.pose_misses;
+        if poses == 0 {
+            return 0.0;
+        }
+        self.error_sum / poses as f32
+    }
+}
+
+impl AccuracyScore {
+    pub(crate) fn new(step_id: String, step_name: String, section_index: Option<usize>) -> Self {
+        Self {
+            step_id,
+            step_name,
+            section_index,
+            error_sum: 0.0,
+            pose_matches: 0,
+            pose_misses: 0,
+        }
+    }
+
+    pub(crate) fn add_pose(&mut self, error: f32, missed: bool) {
+        self.error_sum += error;
+        if missed {
+            self.pose_misses += 1;
+        } else {
+            self.pose_matches += 1;
+        }
+    }
+}
+
+fn accuracy(matches: u32, misses: u32) -> f32 {
+    if matches + misses == 0 {
+        return 0.0;
+    }
+    matches as f32 / (matches + misses) as f32
+}
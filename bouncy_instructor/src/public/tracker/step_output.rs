@@ -1,5 +1,7 @@
 use super::pose_output::PoseApproximation;
-use super::Timestamp;
+use super::{Timestamp, TravelHint};
+use crate::intern::travel::TravelScore;
+use crate::step_file::BodyRegion;
 use wasm_bindgen::prelude::wasm_bindgen;
 
 /// A step detected on a video feed, ready for JS code to render.
@@ -11,6 +13,9 @@ pub struct DetectedStep {
     pub start: Timestamp,
     pub end: Timestamp,
     pub error: f32,
+    /// Set for steps tracked with a teacher, which knows the expected travel.
+    pub(crate) travel: Option<TravelScore>,
+    pub(crate) travel_hint: Option<TravelHint>,
 }
 
 #[wasm_bindgen]
@@ -25,6 +30,58 @@ impl DetectedStep {
         self.poses.clone()
     }
 
+    /// Average error of the limbs in one body region over all poses, the
+    /// score of one layer in a layered step.
+    ///
+    /// Returns `undefined` if no pose defines limbs in the region.
+    #[wasm_bindgen(js_name = regionError)]
+    pub fn region_error(&self, region: BodyRegion) -> Option<f32> {
+        let errors: Vec<f32> = self
+            .poses
+            .iter()
+            .filter_map(|pose| pose.region_error(region))
+            .collect();
+        (!errors.is_empty()).then(|| errors.iter().sum::<f32>() / errors.len() as f32)
+    }
+
+    /// How far off the horizontal travel of the dancer was during the step,
+    /// between 0.0 and 1.0.
+    #[wasm_bindgen(getter, js_name = travelError)]
+    pub fn travel_error(&self) -> Option<f32> {
+        self.travel.map(|travel| travel.error)
+    }
+
+    /// Horizontal travel of the dancer, in body heights, positive to the
+    /// right as seen by the camera.
+    #[wasm_bindgen(getter)]
+    pub fn travel(&self) -> Option<f32> {
+        self.travel.map(|travel| travel.measured)
+    }
+
+    /// Horizontal travel the step expects, in the units of `travel`.
+    #[wasm_bindgen(getter, js_name = expectedTravel)]
+    pub fn expected_travel(&self) -> Option<f32> {
+        self.travel.map(|travel| travel.expected)
+    }
+
+    #[wasm_bindgen(getter, js_name = travelHint)]
+    pub fn travel_hint(&self) -> Option<TravelHint> {
+        self.travel_hint
+    }
+
+    /// Average jump error of all poses that expect a jump.
+    ///
+    /// Returns `undefined` if the step has no jumps.
+    #[wasm_bindgen(getter, js_name = jumpError)]
+    pub fn jump_error(&self) -> Option<f32> {
+        let errors: Vec<f32> = self
+            .poses
+            .iter()
+            .filter_map(|pose| pose.jump_error())
+            .collect();
+        (!errors.is_empty()).then(|| errors.iter().sum::<f32>() / errors.len() as f32)
+    }
+
     #[wasm_bindgen(getter)]
     pub fn bpm(&self) -> f32 {
         let duration = (self.end - self.start) as f32;
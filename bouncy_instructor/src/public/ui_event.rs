@@ -33,6 +33,12 @@ pub struct TextEffect {
 }
 
 impl UiEvents {
+    /// Drop all scheduled effects, for example countdowns that no longer apply.
+    pub(crate) fn clear(&mut self) {
+        self.audio.clear();
+        self.text.clear();
+    }
+
     pub(crate) fn next_audio(&mut self) -> Option<AudioEffect> {
         self.audio.pop_front()
     }
@@ -3,8 +3,10 @@ use std::rc::Rc;
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use crate::intern::content_collection::ContentCollection;
+use crate::intern::loop_practice::LoopPart;
+use crate::intern::step_pace::StepPace;
 use crate::skeleton::Cartesian2d;
-use crate::{dance_file, intern, DanceInfo, STATE};
+use crate::{dance_file, intern, DanceInfo, LoopConfig, Meter, StepInfo, Tracker, STATE};
 
 use super::step_wrapper::StepWrapper;
 
@@ -67,6 +69,26 @@ impl DanceWrapper {
         self.definition().id.clone()
     }
 
+    /// The name in the current language of the collection, falls back to the
+    /// id.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.db
+            .tracker_view
+            .lang()
+            .translate_opt(self.definition().names.as_ref())
+            .unwrap_or_else(|| self.id())
+    }
+
+    /// Translated description of the dance, if available.
+    #[wasm_bindgen(getter)]
+    pub fn description(&self) -> Option<String> {
+        self.db
+            .tracker_view
+            .lang()
+            .translate_opt(self.definition().descriptions.as_ref())
+    }
+
     pub fn length(&self) -> usize {
         self.definition().steps.len()
     }
@@ -95,4 +117,27 @@ impl DanceWrapper {
     pub fn body_shift(&self, beat: usize) -> Cartesian2d {
         self.info().body_shift(beat)
     }
+
+    /// Practice the steps `start..end` of the dance in a loop.
+    ///
+    /// Returns `None` if the range is empty or out of bounds.
+    #[wasm_bindgen(js_name = "loopTracker")]
+    pub fn loop_tracker(&self, start: usize, end: usize, config: &LoopConfig) -> Option<Tracker> {
+        let db = Rc::clone(&self.db.tracker_view);
+        let mut parts = vec![];
+        for dance_step in self.definition().steps.get(start..end)? {
+            let mut step = db.step(&dance_step.id)?.clone();
+            if dance_step.flip_orientation {
+                step = step.flipped();
+            }
+            parts.push(LoopPart {
+                step: StepInfo::from_step(step, &db),
+                repeat: 1,
+                // one pose per subbeat, like the dance preview
+                pace: StepPace::normal(),
+                tracked: true,
+            });
+        }
+        Tracker::new_loop(db, &parts, config, Meter::default())
+    }
 }
@@ -6,6 +6,7 @@ use wasm_bindgen::JsValue;
 use crate::editor::ExportError;
 use crate::pose_file::PoseFile;
 use crate::wrapper::pose_wrapper::PoseWrapper;
+use crate::STATE;
 
 #[derive(Debug, Clone)]
 #[wasm_bindgen]
@@ -99,7 +100,13 @@ impl PoseFileWrapper {
 
 impl PoseFileWrapper {
     fn new(file: PoseFile) -> Self {
-        let poses = file.poses.iter().cloned().map(PoseWrapper::new).collect();
+        let lang = STATE.with_borrow(|state| state.global_db.lang().clone());
+        let poses = file
+            .poses
+            .iter()
+            .cloned()
+            .map(|def| PoseWrapper::new(def, lang.clone()))
+            .collect();
         Self {
             pose_file: Rc::new(RefCell::new(file)),
             poses_cache: Rc::new(RefCell::new(poses)),
@@ -1,5 +1,6 @@
 use super::skeleton_wrapper::SkeletonWrapper;
 use crate::intern::geom::SignedAngle;
+use crate::intern::language::Language;
 use crate::intern::pose::{Limb, LimbPosition, Pose};
 use crate::intern::skeleton_3d::{Direction, Skeleton3d};
 use crate::intern::tracker_dance_collection::TrackerDanceCollection;
@@ -14,6 +15,8 @@ pub struct PoseWrapper {
     /// The source of truth. Modification must only go here first and then
     /// propagate to the other fields.
     pose_definition: pose_file::Pose,
+    /// Language for the translated name, shared with the collection.
+    lang: Language,
 
     // cached values
     // id: Option<String>,
@@ -26,9 +29,10 @@ pub struct PoseWrapper {
 }
 
 impl PoseWrapper {
-    pub(crate) fn new(pose_definition: pose_file::Pose) -> Self {
+    pub(crate) fn new(pose_definition: pose_file::Pose, lang: Language) -> Self {
         Self {
             pose_definition,
+            lang,
             skeleton_cache: None,
             side_skeleton_cache: None,
         }
@@ -40,6 +44,7 @@ impl PoseWrapper {
                 &skeleton.skeleton_3d(),
                 &state.global_db.tracker_view,
             ),
+            lang: state.global_db.lang().clone(),
             // id: None,
             // name: None,
             // pose: None,
@@ -176,6 +181,15 @@ impl PoseWrapper {
         self.pose_definition.id.clone()
     }
 
+    /// The name in the current language of the collection, falls back to the
+    /// id.
+    #[wasm_bindgen(getter, js_name = "translatedName")]
+    pub fn translated_name(&self) -> String {
+        self.lang
+            .translate_opt(self.pose_definition.names.as_ref())
+            .unwrap_or_else(|| self.id())
+    }
+
     pub fn name(&self, lang: String) -> Option<String> {
         if let Some(translated) = &self.pose_definition.names {
             translated.get(&lang)
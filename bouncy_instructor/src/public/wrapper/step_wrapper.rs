@@ -4,6 +4,7 @@ use crate::intern::content_collection::ContentCollection;
 use crate::intern::step::StepSource;
 use crate::intern::tracker_dance_collection::TrackerDanceCollection;
 use crate::skeleton::{Cartesian2d, Skeleton};
+use crate::step_file::BodyRegion;
 use crate::{intern, step_file, StepInfo, STATE};
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -101,6 +102,24 @@ impl StepWrapper {
     }
 
     fn compute_pose_wrappers(&self, db: &ContentCollection) -> Vec<PoseWrapper> {
+        if !self.step_definition.layers.is_empty() {
+            // layered steps have no keyframes, their merged poses are only
+            // known after loading the step
+            let step = db
+                .tracker_view
+                .step(&self.step_definition.id)
+                .unwrap_or_else(|| panic!("step {} not found", self.step_definition.id));
+            return step
+                .poses
+                .iter()
+                .map(|&pose_index| {
+                    let id = db.tracker_view.pose_id(pose_index);
+                    db.pose_by_id(id)
+                        .unwrap_or_else(|| panic!("pose {id} not found"))
+                })
+                .cloned()
+                .collect();
+        }
         self.step_definition
             .keyframes
             .iter()
@@ -159,31 +178,52 @@ impl StepWrapper {
         self.definition().name.clone()
     }
 
+    /// The name to show to users, in the language of the collection. Falls
+    /// back to `name` if no translation is available.
+    #[wasm_bindgen(getter, js_name = "translatedName")]
+    pub fn translated_name(&self) -> String {
+        self.info_unchecked().translated_name()
+    }
+
     #[wasm_bindgen(setter)]
     pub fn set_name(&mut self, name: String) {
         Rc::make_mut(&mut self.step_definition).name = name;
         self.recompute_caches();
     }
 
+    /// The skeleton shown on the given subbeat, held poses repeat over
+    /// several subbeats.
     pub fn skeleton(&self, beat: usize) -> Skeleton {
-        self.info_unchecked().skeleton(beat)
+        let info = self.info_unchecked();
+        info.skeleton(info.pose_at_subbeat(beat))
     }
 
-    /// How much the body position deviates from the origin.
+    /// How much the body position deviates from the origin on the given
+    /// subbeat.
     #[wasm_bindgen(js_name = "bodyShift")]
     pub fn body_shift(&self, beat: usize) -> Cartesian2d {
-        self.info_unchecked().body_shift(beat)
+        let info = self.info_unchecked();
+        info.body_shift(info.pose_at_subbeat(beat))
     }
 
     /// Applies a rotation (in degree) and returns the resulting skelton.
     #[wasm_bindgen(js_name = "rotatedSkeleton")]
     pub fn rotated_skeleton(&self, beat: usize, rotation: f32) -> Skeleton {
-        self.info_unchecked().rotated_skeleton(beat, rotation)
+        let info = self.info_unchecked();
+        info.rotated_skeleton(info.pose_at_subbeat(beat), rotation)
     }
 
     #[wasm_bindgen(js_name = "jumpHeight")]
     pub fn jump_height(&self, beat: usize) -> Option<f32> {
-        self.info_unchecked().jump_height(beat)
+        let info = self.info_unchecked();
+        info.jump_height(info.pose_at_subbeat(beat))
+    }
+
+    /// The index of the pose shown on the given subbeat. The animation
+    /// should only move to a new pose when this changes.
+    #[wasm_bindgen(js_name = "poseAtSubbeat")]
+    pub fn pose_at_subbeat(&self, beat: usize) -> usize {
+        self.info_unchecked().pose_at_subbeat(beat)
     }
 
     /// Description identifier for the translated text which describes how the
@@ -197,10 +237,27 @@ impl StepWrapper {
         self.info_unchecked().variation()
     }
 
-    /// The number of subbeats the step takes for one repetition.
+    /// Translated text which describes how the variation is different from the
+    /// original, if the step file defines one. Otherwise, the app should
+    /// translate the `variation` identifier itself.
+    #[wasm_bindgen(getter, js_name = "variationDescription")]
+    pub fn variation_description(&self) -> Option<String> {
+        self.info_unchecked().variation_description()
+    }
+
+    /// The number of subbeats the step takes for one repetition, at one pose
+    /// per subbeat.
     #[wasm_bindgen(getter)]
     pub fn subbeats(&self) -> usize {
-        self.info_unchecked().num_poses()
+        self.info_unchecked().subbeats()
+    }
+
+    /// How many pose slots the pose shown on the given subbeat is held, 1.0
+    /// unless the step defines a hold or syncopation.
+    #[wasm_bindgen(js_name = "poseDuration")]
+    pub fn pose_duration(&self, beat: usize) -> f32 {
+        let info = self.info_unchecked();
+        info.pose_duration(info.pose_at_subbeat(beat))
     }
 
     /// Look up poses from the global collection, do not use for courses that
@@ -227,6 +284,37 @@ impl StepWrapper {
             .unwrap()
     }
 
+    /// The steps this step is layered from, for practising each layer on its
+    /// own. Empty for steps defined by keyframes.
+    ///
+    /// Looks up steps from the global collection, do not use for courses that
+    /// require a custom collection.
+    pub fn layers(&self) -> Vec<StepWrapper> {
+        STATE.with_borrow(|state| {
+            self.step_definition
+                .layers
+                .iter()
+                .filter_map(|layer| state.global_db.step(&layer.step))
+                .map(|step| {
+                    if self.flipped {
+                        step.flipped(&state.global_db.tracker_view)
+                    } else {
+                        step.clone()
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Which body region the layer at `index` contributes.
+    #[wasm_bindgen(js_name = "layerRegion")]
+    pub fn layer_region(&self, index: usize) -> Option<BodyRegion> {
+        self.step_definition
+            .layers
+            .get(index)
+            .map(|layer| layer.region)
+    }
+
     /// Add poses from the global collection, do not use for courses that
     /// require a custom collection.
     #[wasm_bindgen(js_name = "addPosition")]
@@ -1,7 +1,8 @@
 //! Utilities for unit tests.
 
 use crate::intern::geom::{Angle3d, SignedAngle};
-use crate::keypoints::Cartesian3d;
+use crate::keypoints::{Cartesian3d, Side};
+use crate::Keypoints;
 
 #[track_caller]
 pub(crate) fn assert_float_angle_eq(expected: f32, actual: SignedAngle) {
@@ -52,3 +53,22 @@ pub(crate) fn float_eq(expected: f32, actual: f32) -> bool {
     // fall back to absolute tolerance if expectation is 0.0
     actual.abs() < 1e-6
 }
+
+/// Keypoints of a dancer facing the camera, hips at (`x`, `y`) and `size` from
+/// shoulders to ankles, in image coordinates.
+pub(crate) fn standing_keypoints(x: f32, y: f32, size: f32) -> Keypoints {
+    let side = |dx: f32| {
+        let at = |dy: f32| Cartesian3d::new(x + dx * size, y + dy * size, 0.0);
+        Side {
+            shoulder: at(-0.5),
+            hip: at(0.0),
+            knee: at(0.25),
+            ankle: at(0.5),
+            heel: at(0.5),
+            toes: at(0.5),
+            elbow: at(-0.25),
+            wrist: at(0.0),
+        }
+    };
+    Keypoints::new(side(0.1), side(-0.1), true)
+}
@@ -1,5 +1,6 @@
 use bouncy_instructor::parse_course_str;
 use bouncy_instructor::Cartesian2d;
+use bouncy_instructor::{LoopConfig, Meter, UnlockStatus, UserProgress};
 
 mod common;
 
@@ -40,6 +41,94 @@ fn check_courses_parse(lang: &str) {
     }
 }
 
+#[test]
+fn test_course_switch_language() {
+    let course = parse_course_str(COURSES[0].1, "en").unwrap();
+    let lessons = course.lessons();
+    assert_eq!(course.name(), "Beginner's course");
+    assert_eq!(lessons[1].name(), "Prep for RM");
+
+    course.set_language("de".to_owned());
+    assert_eq!(course.name(), "Anfängerkurs");
+    assert_eq!(lessons[1].name(), "Vorbereitung für RM");
+}
+
+#[test]
+fn test_course_unlocking() {
+    let basics = parse_course_str(COURSES[0].1, "en").unwrap();
+    let practice = parse_course_str(COURSES[3].1, "en").unwrap();
+    let mut progress = UserProgress::new();
+
+    assert_eq!(progress.course_status(&basics), UnlockStatus::Unlocked);
+    assert_eq!(progress.course_status(&practice), UnlockStatus::Locked);
+    assert_eq!(progress.next_lesson(&basics), Some(0));
+    assert_eq!(progress.next_lesson(&practice), None);
+
+    // not good enough to unlock practice
+    progress.add_lesson_result(basics.id(), 5, 6, 4);
+    assert_eq!(progress.course_status(&practice), UnlockStatus::Locked);
+
+    progress.add_lesson_result(basics.id(), 5, 9, 1);
+    assert_eq!(progress.lesson_status(&basics, 5), UnlockStatus::Mastered);
+    assert_eq!(progress.course_status(&practice), UnlockStatus::Unlocked);
+    assert_eq!(progress.next_lesson(&practice), Some(0));
+
+    // a bad session asks for review but keeps the practice course unlocked
+    progress.add_activity_result("course/running-man-basics[5]", 3, 7);
+    assert_eq!(
+        progress.lesson_status(&basics, 5),
+        UnlockStatus::NeedsReview
+    );
+    assert_eq!(progress.lessons_to_review(&basics), vec![5]);
+    assert_eq!(progress.course_status(&basics), UnlockStatus::NeedsReview);
+    assert_eq!(progress.course_status(&practice), UnlockStatus::Unlocked);
+}
+
+/// A meter changes the number of subbeats and the time between poses.
+#[test]
+fn test_tracker_meter() {
+    let course = parse_course_str(COURSES[0].1, "en").unwrap();
+    let mut tracker = course.tracker(0).unwrap();
+    let straight_subbeats = tracker.tracked_subbeats();
+    tracker.set_bpm(60.0);
+
+    // waltz with triplets: 3 subbeats per beat
+    tracker.set_meter(&Meter::new(3, 3, 0.5).unwrap());
+    assert_eq!(tracker.tracked_subbeats() % 3, 0);
+    assert!(tracker.tracked_subbeats() >= straight_subbeats);
+    assert!((tracker.time_between_poses() - 1000.0 / 3.0).abs() < 1e-9);
+}
+
+/// A loop repeats the chosen parts of a lesson, slower at first with a ramp.
+#[test]
+fn test_loop_tracker() {
+    let course = parse_course_str(COURSES[0].1, "en").unwrap();
+    let single = LoopConfig {
+        repetitions: 1,
+        ..LoopConfig::default()
+    };
+    let one_loop = course.loop_tracker(0, 0, 2, &single).unwrap();
+    let looped = LoopConfig {
+        repetitions: 3,
+        ..LoopConfig::default()
+    };
+    let three_loops = course.loop_tracker(0, 0, 2, &looped).unwrap();
+    assert_eq!(
+        three_loops.tracked_subbeats(),
+        3 * one_loop.tracked_subbeats()
+    );
+    let ramped = LoopConfig {
+        tempo_ramp: true,
+        ..looped
+    };
+    let ramped_loops = course.loop_tracker(0, 0, 2, &ramped).unwrap();
+    assert!(ramped_loops.tracked_subbeats() > three_loops.tracked_subbeats());
+    assert_eq!(ramped_loops.mastered_loop(), None);
+
+    assert!(course.loop_tracker(0, 1, 1, &looped).is_none());
+    assert!(course.loop_tracker(0, 0, 100, &looped).is_none());
+}
+
 /// Ensure the instructor does not leave the visible area.
 #[test]
 fn test_courses_in_boundary() {
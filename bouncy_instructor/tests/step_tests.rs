@@ -1,3 +1,4 @@
+use bouncy_instructor::{load_step_str, Keypoints, Tracker};
 use expect_test::expect;
 use std::collections::HashSet;
 
@@ -36,3 +37,83 @@ fn test_unique_step_id() {
         "Duplicate step ID detected. {duplicates:?}"
     )
 }
+
+#[test]
+fn test_practice_tracker() {
+    const DAY_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+    common::load_static_files();
+
+    let mut struggling = bouncy_instructor::PracticeScheduler::new();
+    struggling.add_step_result("reverse-rm-0".to_owned(), 0.0, 2, 8);
+    struggling.add_step_result("step-that-was-removed".to_owned(), 0.0, 2, 8);
+    assert!(struggling.practice_tracker(0.0, 3).unwrap().is_none());
+    let slow = struggling.practice_tracker(DAY_MS, 3).unwrap().unwrap();
+
+    let mut good = bouncy_instructor::PracticeScheduler::new();
+    good.add_step_result("reverse-rm-0".to_owned(), 0.0, 9, 1);
+    let fast = good.practice_tracker(2.0 * DAY_MS, 3).unwrap().unwrap();
+
+    // same number of repetitions but half the speed
+    assert_ne!(fast.tracked_subbeats(), 0);
+    assert_eq!(slow.tracked_subbeats(), 2 * fast.tracked_subbeats());
+}
+
+#[test]
+fn test_expected_pose_error() {
+    common::load_static_files();
+    let keypoints = include_str!("./data/test_poses/left_forward_1.keypoints.ron");
+    let parsed: Vec<(f64, Keypoints)> = ron::from_str(keypoints).expect("parsing test input");
+    let (_, keypoints) = parsed[0];
+
+    let mut tracker = Tracker::new_unique_step_tracker("reverse-rm-0".to_owned()).unwrap();
+    tracker.set_bpm(120.0);
+    tracker.align_beat(0.0);
+    tracker.add_keypoints(keypoints, 500.0);
+
+    // unique step trackers go at half speed, the second pose starts at 500ms
+    let cursor = tracker.cursor(500.0, true);
+    assert_eq!(cursor.pose_index, 1);
+    let expected = tracker.expected_pose_error(&cursor, 500.0).unwrap();
+    assert_eq!(expected.id(), "left-forward");
+    assert!(expected.error < 0.15, "error too big {}", expected.error);
+
+    let other = tracker
+        .expected_pose_error(&tracker.cursor(0.0, true), 500.0)
+        .unwrap();
+    assert_eq!(other.id(), "right-up");
+    assert!(other.error > expected.error);
+}
+
+/// Count running man repetitions in a recording, ignoring the beat.
+#[test]
+fn test_repetition_tracker() {
+    common::load_static_files();
+    let keypoints = include_str!("./data/test_steps/running-man-100bpm-12x.ron");
+    let parsed: Vec<(f64, Keypoints)> = ron::from_str(keypoints).expect("parsing test input");
+
+    let mut tracker = Tracker::new_repetition_tracker("rm-0".to_owned()).unwrap();
+    for (timestamp, keypoints) in parsed {
+        tracker.add_keypoints(keypoints, timestamp);
+        tracker.run_detection();
+    }
+    // the first repetition may start in the middle of the step
+    let counted = tracker.repetitions().len();
+    assert!(
+        (11..=12).contains(&counted),
+        "counted {counted} repetitions"
+    );
+}
+
+#[test]
+fn test_repetition_tracker_without_poses() {
+    common::load_static_files();
+    load_step_str(
+        r#"(version: 0, steps: [(id: "empty", name: "Empty", keyframes: [])])"#,
+        "test".to_owned(),
+    )
+    .unwrap();
+    match Tracker::new_repetition_tracker("empty".to_owned()) {
+        Err(error) => assert_eq!(error.to_string(), "step `empty` has no poses to count"),
+        Ok(_) => panic!("counting a step without poses"),
+    }
+}
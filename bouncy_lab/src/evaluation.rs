@@ -0,0 +1,653 @@
+//! Measure how well the instructor detects steps, against videos annotated
+//! by hand.
+//!
+//! An annotation file looks like this:
+//!
+//! ```ron
+//! (
+//!     video: (
+//!         path: "dance.mp4",
+//!         beats: [500, 1000, 1500],
+//!         markers: {
+//!             500: Start,
+//!             1000: Step("Running Man"),
+//!             2000: Step("Running Man"),
+//!         },
+//!     ),
+//!     keypoints: Some("dance.keypoints.ron"),
+//! )
+//! ```
+//!
+//! `video` uses the same format as videos in course files. Each `Step` marker
+//! is the start of one repetition of the named step. Annotated and detected
+//! steps before the first `Start` marker are ignored, to skip intros. Without
+//! a keypoint recording, poses are detected on the video. Paths are relative
+//! to the annotation file.
+//!
+//! The instructor follows the annotated beats, so tempo drift is no source of
+//! errors. With an explicit `bpm`, it follows a steady tempo instead.
+
+use crate::ffmpeg::VideoInput;
+use crate::keypoints::Keypoints;
+use crate::mediapipe::{detect_pose, DetectionOptions};
+use anyhow::{bail, Context};
+use bouncy_instructor::{DetectorConfig, Tracker};
+use plotters::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Label in the confusion report when a step was missed or detected without
+/// being annotated.
+const NO_STEP: &str = "-";
+
+#[derive(Debug, Deserialize)]
+struct Annotation {
+    video: AnnotatedVideo,
+    #[serde(default)]
+    keypoints: Option<String>,
+    /// Tempo of the music, computed from `beats` if missing.
+    #[serde(default)]
+    bpm: Option<f32>,
+}
+
+/// Copy of `VideoDef::Full` in the instructor's course files.
+#[derive(Debug, Deserialize)]
+struct AnnotatedVideo {
+    path: String,
+    beats: Vec<u32>,
+    markers: BTreeMap<u32, Marker>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+enum Marker {
+    Start,
+    Step(String),
+}
+
+/// Machine-readable result of an evaluation.
+#[derive(Debug, Default, Serialize)]
+pub struct EvaluationReport {
+    pub videos: Vec<VideoReport>,
+    pub total: Counts,
+    /// Mean of absolute timing errors of all matched steps.
+    pub mean_abs_timing_error_ms: f64,
+    pub median_abs_timing_error_ms: f64,
+    /// (annotated step, detected step, count), with `-` for no step.
+    pub confusion: Vec<(String, String, u32)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VideoReport {
+    pub annotation: String,
+    pub bpm: f32,
+    pub counts: Counts,
+    /// Detected minus annotated start of each matched step, in ms.
+    pub timing_errors_ms: Vec<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Counts {
+    pub true_positives: u32,
+    pub false_positives: u32,
+    pub false_negatives: u32,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+/// An annotated video with keypoints, ready to run detection on.
+pub(crate) struct AnnotatedRecording {
+    annotation_path: String,
+    bpm: f32,
+    first_beat: Option<f64>,
+    /// Annotated beats the tracker follows, unless a steady bpm was given.
+    beats: Option<Vec<f64>>,
+    /// Detections before this video time in ms are ignored.
+    start: f64,
+    annotated: Vec<StepEvent>,
+    recording: Vec<(u64, Keypoints)>,
+}
+
+/// An annotated or detected step.
+struct StepEvent {
+    name: String,
+    start: f64,
+    /// Pose IDs with their error, only for detected steps.
+    poses: Vec<(String, f32)>,
+}
+
+/// Result of matching detected steps to annotated steps.
+#[derive(Default)]
+pub(crate) struct Matching {
+    /// (annotated, detected) names with the timing error in ms.
+    matched: Vec<(String, String, f64)>,
+    missed: Vec<String>,
+    unexpected: Vec<String>,
+    /// Pose IDs with their error, for all correctly detected steps.
+    pub(crate) pose_errors: Vec<(String, f32)>,
+}
+
+/// Evaluate step detection on all annotated videos and write `report.ron`,
+/// `timing_errors.png` and `confusion.png` to `output_dir`.
+///
+/// Detected steps count as matched if they start within `tolerance_beats` of
+/// an annotated step.
+pub fn evaluate(
+    annotation_paths: &[&str],
+    output_dir: &str,
+    tolerance_beats: f64,
+    config: &DetectorConfig,
+    options: DetectionOptions,
+) -> anyhow::Result<EvaluationReport> {
+    let mut report = EvaluationReport::default();
+    let mut confusion = BTreeMap::<(String, String), u32>::new();
+
+    for annotated in load_annotations(annotation_paths, options)? {
+        let matching = annotated.evaluate(config, tolerance_beats);
+
+        for (expected, actual, _) in &matching.matched {
+            *confusion
+                .entry((expected.clone(), actual.clone()))
+                .or_default() += 1;
+        }
+        for expected in &matching.missed {
+            *confusion
+                .entry((expected.clone(), NO_STEP.to_owned()))
+                .or_default() += 1;
+        }
+        for actual in &matching.unexpected {
+            *confusion
+                .entry((NO_STEP.to_owned(), actual.clone()))
+                .or_default() += 1;
+        }
+
+        let timing_errors_ms = matching.matched.iter().map(|(_, _, dt)| *dt).collect();
+        report.videos.push(VideoReport {
+            annotation: annotated.annotation_path,
+            bpm: annotated.bpm,
+            counts: matching.counts(),
+            timing_errors_ms,
+        });
+    }
+
+    report.total = Counts::sum(report.videos.iter().map(|video| video.counts));
+
+    let mut abs_errors: Vec<f64> = report
+        .videos
+        .iter()
+        .flat_map(|video| &video.timing_errors_ms)
+        .map(|dt| dt.abs())
+        .collect();
+    abs_errors.sort_by(f64::total_cmp);
+    if !abs_errors.is_empty() {
+        report.mean_abs_timing_error_ms = abs_errors.iter().sum::<f64>() / abs_errors.len() as f64;
+        report.median_abs_timing_error_ms = abs_errors[abs_errors.len() / 2];
+    }
+    report.confusion = confusion
+        .into_iter()
+        .map(|((expected, actual), n)| (expected, actual, n))
+        .collect();
+
+    let output_dir = Path::new(output_dir);
+    std::fs::create_dir_all(output_dir)?;
+    let text = ron::ser::to_string_pretty(&report, ron::ser::PrettyConfig::default())?;
+    std::fs::write(output_dir.join("report.ron"), text)?;
+    plot_timing_errors(&report, &output_dir.join("timing_errors.png"))?;
+    plot_confusion(&report, &output_dir.join("confusion.png"))?;
+
+    Ok(report)
+}
+
+/// Read annotation files and their keypoints, detecting poses if needed.
+pub(crate) fn load_annotations(
+    annotation_paths: &[&str],
+    options: DetectionOptions,
+) -> anyhow::Result<Vec<AnnotatedRecording>> {
+    let mut out = vec![];
+    for annotation_path in annotation_paths {
+        let text = std::fs::read_to_string(annotation_path)
+            .with_context(|| format!("failed reading {annotation_path}"))?;
+        let annotation: Annotation =
+            ron::from_str(&text).with_context(|| format!("failed parsing {annotation_path}"))?;
+        let base_dir = Path::new(annotation_path)
+            .parent()
+            .unwrap_or(Path::new("."));
+
+        let bpm = match annotation.bpm {
+            Some(bpm) => bpm,
+            None => bpm_from_beats(&annotation.video.beats).with_context(|| {
+                format!("{annotation_path} needs at least two increasing beats or a bpm")
+            })?,
+        };
+        let recording = match &annotation.keypoints {
+            Some(path) => crate::read_recording(&base_dir.join(path).to_string_lossy())?,
+            None => {
+                let video_path = base_dir.join(&annotation.video.path);
+                let video = VideoInput::by_path(&video_path.to_string_lossy())?;
+                detect_pose(video, options)?
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default()
+            }
+        };
+
+        let start = annotation
+            .video
+            .markers
+            .iter()
+            .find(|(_, marker)| **marker == Marker::Start)
+            .map_or(0.0, |(t, _)| *t as f64);
+        out.push(AnnotatedRecording {
+            annotation_path: annotation_path.to_string(),
+            bpm,
+            first_beat: annotation.video.beats.first().map(|t| *t as f64),
+            beats: (annotation.bpm.is_none() && annotation.video.beats.len() >= 2)
+                .then(|| annotation.video.beats.iter().map(|t| *t as f64).collect()),
+            start,
+            annotated: annotated_steps(&annotation.video, start),
+            recording,
+        });
+    }
+    Ok(out)
+}
+
+impl AnnotatedRecording {
+    /// Detect steps with the given config and match them to the annotation.
+    pub(crate) fn evaluate(&self, config: &DetectorConfig, tolerance_beats: f64) -> Matching {
+        let detected: Vec<StepEvent> = self
+            .detect_steps(config)
+            .into_iter()
+            .filter(|step| step.start >= self.start)
+            .collect();
+        let tolerance_ms = tolerance_beats * 60_000.0 / self.bpm as f64;
+        match_steps(&self.annotated, &detected, tolerance_ms)
+    }
+
+    /// Run the instructor with all known steps over the recording.
+    fn detect_steps(&self, config: &DetectorConfig) -> Vec<StepEvent> {
+        let mut tracker = Tracker::new_from_global_collection();
+        tracker.set_detector_config(config);
+        let follows_beats = self
+            .beats
+            .as_ref()
+            .is_some_and(|beats| tracker.set_beats(beats.clone()).is_ok());
+        if !follows_beats {
+            tracker.set_bpm(self.bpm);
+            if let Some(first_beat) = self.first_beat {
+                tracker.align_beat(first_beat);
+            }
+        }
+        let mut last_t = None;
+        for (t, keypoints) in &self.recording {
+            // the tracker requires strictly increasing timestamps
+            if last_t.is_some_and(|last| *t <= last) {
+                continue;
+            }
+            tracker.add_keypoints((*keypoints).into(), *t as f64);
+            last_t = Some(*t);
+        }
+        tracker
+            .detect_dance()
+            .steps()
+            .iter()
+            .map(|step| StepEvent {
+                name: step.name(),
+                start: step.start,
+                poses: step
+                    .poses()
+                    .iter()
+                    .map(|pose| (pose.id(), pose.error))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl EvaluationReport {
+    pub fn print_summary(&self) {
+        for video in &self.videos {
+            println!(
+                "{}: precision {:.2}, recall {:.2} ({} tp, {} fp, {} fn)",
+                video.annotation,
+                video.counts.precision,
+                video.counts.recall,
+                video.counts.true_positives,
+                video.counts.false_positives,
+                video.counts.false_negatives,
+            );
+        }
+        println!(
+            "total: precision {:.2}, recall {:.2}, timing error mean {:.0} ms, median {:.0} ms",
+            self.total.precision,
+            self.total.recall,
+            self.mean_abs_timing_error_ms,
+            self.median_abs_timing_error_ms,
+        );
+    }
+}
+
+impl Counts {
+    pub(crate) fn sum(counts: impl Iterator<Item = Counts>) -> Counts {
+        let mut total = Counts::default();
+        for counts in counts {
+            total.true_positives += counts.true_positives;
+            total.false_positives += counts.false_positives;
+            total.false_negatives += counts.false_negatives;
+        }
+        total.with_rates()
+    }
+
+    fn with_rates(mut self) -> Self {
+        let tp = self.true_positives as f64;
+        let detected = tp + self.false_positives as f64;
+        let annotated = tp + self.false_negatives as f64;
+        self.precision = if detected > 0.0 { tp / detected } else { 0.0 };
+        self.recall = if annotated > 0.0 { tp / annotated } else { 0.0 };
+        let sum = self.precision + self.recall;
+        self.f1 = if sum > 0.0 {
+            2.0 * self.precision * self.recall / sum
+        } else {
+            0.0
+        };
+        self
+    }
+}
+
+impl Matching {
+    /// Steps matched in time but with the wrong name count both as false
+    /// positive and false negative.
+    pub(crate) fn counts(&self) -> Counts {
+        let correct = self.matched.iter().filter(|(a, b, _)| a == b).count() as u32;
+        let wrong = self.matched.len() as u32 - correct;
+        Counts {
+            true_positives: correct,
+            false_positives: self.unexpected.len() as u32 + wrong,
+            false_negatives: self.missed.len() as u32 + wrong,
+            ..Default::default()
+        }
+        .with_rates()
+    }
+}
+
+/// Tempo from the median beat interval, beats must be strictly increasing.
+fn bpm_from_beats(beats: &[u32]) -> anyhow::Result<f32> {
+    if let Some(w) = beats.windows(2).find(|w| w[0] >= w[1]) {
+        bail!("beat at {} ms is not after beat at {} ms", w[1], w[0])
+    }
+    let mut intervals: Vec<u32> = beats.windows(2).map(|w| w[1] - w[0]).collect();
+    intervals.sort();
+    let Some(median) = intervals.get(intervals.len() / 2) else {
+        bail!("not enough beats")
+    };
+    Ok(60_000.0 / *median as f32)
+}
+
+/// Annotated steps from `start` on, the same range in which detections count.
+fn annotated_steps(video: &AnnotatedVideo, start: f64) -> Vec<StepEvent> {
+    video
+        .markers
+        .iter()
+        .filter(|(t, _)| **t as f64 >= start)
+        .filter_map(|(t, marker)| match marker {
+            Marker::Start => None,
+            Marker::Step(name) => Some(StepEvent {
+                name: name.clone(),
+                start: *t as f64,
+                poses: vec![],
+            }),
+        })
+        .collect()
+}
+
+/// Pair each annotated step with at most one detected step, closest start
+/// times first, regardless of the step names.
+fn match_steps(annotated: &[StepEvent], detected: &[StepEvent], tolerance_ms: f64) -> Matching {
+    let mut pairs = vec![];
+    for (i, expected) in annotated.iter().enumerate() {
+        for (j, actual) in detected.iter().enumerate() {
+            let dt = actual.start - expected.start;
+            if dt.abs() <= tolerance_ms {
+                pairs.push((dt, i, j));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| a.0.abs().total_cmp(&b.0.abs()));
+
+    let mut annotated_done = vec![false; annotated.len()];
+    let mut detected_done = vec![false; detected.len()];
+    let mut matching = Matching::default();
+    for (dt, i, j) in pairs {
+        if !annotated_done[i] && !detected_done[j] {
+            annotated_done[i] = true;
+            detected_done[j] = true;
+            matching
+                .matched
+                .push((annotated[i].name.clone(), detected[j].name.clone(), dt));
+            if annotated[i].name == detected[j].name {
+                matching
+                    .pose_errors
+                    .extend(detected[j].poses.iter().cloned());
+            }
+        }
+    }
+    matching.missed = annotated
+        .iter()
+        .zip(annotated_done)
+        .filter(|(_, done)| !done)
+        .map(|(step, _)| step.name.clone())
+        .collect();
+    matching.unexpected = detected
+        .iter()
+        .zip(detected_done)
+        .filter(|(_, done)| !done)
+        .map(|(step, _)| step.name.clone())
+        .collect();
+    matching
+}
+
+/// Histogram of signed timing errors, in buckets of 50ms.
+fn plot_timing_errors(report: &EvaluationReport, path: &Path) -> anyhow::Result<()> {
+    const BUCKET_MS: f64 = 50.0;
+    let buckets: Vec<i32> = report
+        .videos
+        .iter()
+        .flat_map(|video| &video.timing_errors_ms)
+        .map(|dt| (dt / BUCKET_MS).floor() as i32)
+        .collect();
+    let min = buckets.iter().copied().min().unwrap_or(-1).min(-1);
+    let max = buckets.iter().copied().max().unwrap_or(1).max(1);
+    let mut counts = vec![0u32; (max - min + 1) as usize];
+    for bucket in &buckets {
+        counts[(bucket - min) as usize] += 1;
+    }
+    let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+
+    let root = BitMapBackend::new(path, (1024, 768)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Step Timing Errors", ("sans-serif", 50).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(
+            (min as f64 * BUCKET_MS)..((max + 1) as f64 * BUCKET_MS),
+            0u32..max_count + 1,
+        )?;
+    chart
+        .configure_mesh()
+        .x_desc("Detected - annotated start [ms]")
+        .y_desc("Steps")
+        .draw()?;
+    chart.draw_series(counts.iter().enumerate().map(|(i, n)| {
+        let x0 = (min + i as i32) as f64 * BUCKET_MS;
+        Rectangle::new([(x0, 0), (x0 + BUCKET_MS, *n)], BLUE.mix(0.6).filled())
+    }))?;
+    root.present()?;
+    Ok(())
+}
+
+/// Matrix of annotated (rows) against detected (columns) steps.
+fn plot_confusion(report: &EvaluationReport, path: &Path) -> anyhow::Result<()> {
+    let mut labels: Vec<&str> = report
+        .confusion
+        .iter()
+        .flat_map(|(a, b, _)| [a.as_str(), b.as_str()])
+        .collect();
+    labels.sort();
+    labels.dedup();
+    let index = |label: &str| labels.binary_search(&label).expect("label was collected");
+    let n = labels.len().max(1);
+    let max_count = report
+        .confusion
+        .iter()
+        .map(|(_, _, count)| *count)
+        .max()
+        .unwrap_or(1);
+
+    let root = BitMapBackend::new(path, (1024, 1024)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let (label_area, matrix_area) = (200, 800);
+    let cell = matrix_area / n as i32;
+    let style = ("sans-serif", 16).into_font();
+    root.draw(&Text::new(
+        "annotated (rows) vs detected (columns)",
+        (10, 10),
+        ("sans-serif", 24).into_font(),
+    ))?;
+    for (i, label) in labels.iter().enumerate() {
+        let offset = label_area + i as i32 * cell + cell / 2;
+        root.draw(&Text::new(label.to_string(), (10, offset), style.clone()))?;
+        root.draw(&Text::new(
+            label.to_string(),
+            (offset, label_area - 20),
+            style.clone().transform(FontTransform::Rotate270),
+        ))?;
+    }
+    for (expected, actual, count) in &report.confusion {
+        let x = label_area + index(actual) as i32 * cell;
+        let y = label_area + index(expected) as i32 * cell;
+        let shade = *count as f64 / max_count as f64;
+        let color = if expected == actual { GREEN } else { RED };
+        root.draw(&Rectangle::new(
+            [(x, y), (x + cell, y + cell)],
+            color.mix(0.2 + 0.8 * shade).filled(),
+        ))?;
+        root.draw(&Text::new(
+            count.to_string(),
+            (x + cell / 2, y + cell / 2),
+            style.clone(),
+        ))?;
+    }
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(name: &str, start: f64) -> StepEvent {
+        StepEvent {
+            name: name.to_owned(),
+            start,
+            poses: vec![(format!("{name}-pose"), 0.1)],
+        }
+    }
+
+    #[test]
+    fn test_match_steps_tolerance() {
+        let annotated = [step("rm", 1000.0), step("rm", 2000.0)];
+        let detected = [step("rm", 1100.0), step("rm", 2100.5)];
+        let matching = match_steps(&annotated, &detected, 100.0);
+        assert_eq!(
+            matching.matched,
+            [("rm".to_owned(), "rm".to_owned(), 100.0)]
+        );
+        assert_eq!(matching.missed, ["rm"]);
+        assert_eq!(matching.unexpected, ["rm"]);
+        // only correctly detected steps contribute pose errors
+        assert_eq!(matching.pose_errors.len(), 1);
+    }
+
+    #[test]
+    fn test_match_steps_once() {
+        // two detections close to one annotated step, the closer one wins
+        let annotated = [step("rm", 1000.0)];
+        let detected = [step("rm", 950.0), step("rm", 1020.0)];
+        let matching = match_steps(&annotated, &detected, 100.0);
+        assert_eq!(matching.matched, [("rm".to_owned(), "rm".to_owned(), 20.0)]);
+        assert!(matching.missed.is_empty());
+        assert_eq!(matching.unexpected.len(), 1);
+
+        // and the other way around
+        let annotated = [step("rm", 950.0), step("rm", 1020.0)];
+        let detected = [step("rm", 1000.0)];
+        let matching = match_steps(&annotated, &detected, 100.0);
+        assert_eq!(
+            matching.matched,
+            [("rm".to_owned(), "rm".to_owned(), -20.0)]
+        );
+        assert_eq!(matching.missed.len(), 1);
+        assert!(matching.unexpected.is_empty());
+    }
+
+    #[test]
+    fn test_wrong_step_counts() {
+        let annotated = [step("rm", 1000.0), step("kick", 2000.0)];
+        let detected = [step("rm", 1000.0), step("rm", 2000.0)];
+        let counts = match_steps(&annotated, &detected, 100.0).counts();
+        assert_eq!(counts.true_positives, 1);
+        assert_eq!(counts.false_positives, 1);
+        assert_eq!(counts.false_negatives, 1);
+        assert_eq!(counts.precision, 0.5);
+        assert_eq!(counts.recall, 0.5);
+        assert_eq!(counts.f1, 0.5);
+    }
+
+    #[test]
+    fn test_rates() {
+        let counts = Counts {
+            true_positives: 3,
+            false_positives: 1,
+            false_negatives: 3,
+            ..Default::default()
+        }
+        .with_rates();
+        assert_eq!(counts.precision, 0.75);
+        assert_eq!(counts.recall, 0.5);
+        assert!((counts.f1 - 0.6).abs() < 1e-9);
+
+        // nothing annotated or detected is no division by zero
+        let empty = Counts::default().with_rates();
+        assert_eq!((empty.precision, empty.recall, empty.f1), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_bpm_from_beats() {
+        assert_eq!(bpm_from_beats(&[0, 500, 1000, 1500]).unwrap(), 120.0);
+        // the median ignores a single missed beat
+        assert_eq!(bpm_from_beats(&[0, 500, 1000, 2000, 2500]).unwrap(), 120.0);
+        assert!(bpm_from_beats(&[500]).is_err());
+        assert!(bpm_from_beats(&[0, 500, 500, 1000]).is_err());
+        assert!(bpm_from_beats(&[0, 1000, 500]).is_err());
+        assert!(bpm_from_beats(&[]).is_err());
+    }
+
+    #[test]
+    fn test_ignore_annotations_before_start() {
+        let text = r#"(
+            path: "dance.mp4",
+            beats: [],
+            markers: {
+                500: Step("intro"),
+                1000: Start,
+                1500: Step("rm"),
+            },
+        )"#;
+        let video: AnnotatedVideo = ron::from_str(text).unwrap();
+        let steps = annotated_steps(&video, 1000.0);
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].name, "rm");
+    }
+}
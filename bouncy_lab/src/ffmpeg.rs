@@ -1,5 +1,12 @@
+use crate::letterbox::Rotation;
 use anyhow::anyhow;
-use ffmpeg_next::{self as ffmpeg, decoder, format::context::Input};
+use ffmpeg_next::{
+    self as ffmpeg, decoder, encoder,
+    format::context::{Input, Output},
+    software::scaling,
+    util::frame,
+    ChannelLayout, Rational,
+};
 
 pub struct VideoInput {
     pub ictx: Input,
@@ -7,6 +14,14 @@ pub struct VideoInput {
     pub video_stream_index: usize,
 }
 
+/// Encodes RGB frames to a H.264 video file.
+pub struct VideoOutput {
+    octx: Output,
+    encoder: encoder::Video,
+    scaler: scaling::Context,
+    time_base: Rational,
+}
+
 impl VideoInput {
     pub fn by_path(file_path: &str) -> anyhow::Result<VideoInput> {
         ffmpeg::init().unwrap();
@@ -28,4 +43,243 @@ impl VideoInput {
             video_stream_index,
         })
     }
+
+    pub fn time_base(&self) -> Rational {
+        self.ictx
+            .stream(self.video_stream_index)
+            .expect("video stream must exist")
+            .time_base()
+    }
+
+    pub fn frame_rate(&self) -> Rational {
+        self.ictx
+            .stream(self.video_stream_index)
+            .expect("video stream must exist")
+            .avg_frame_rate()
+    }
+
+    /// How decoded frames must be rotated to display them upright.
+    ///
+    /// Phones usually record in landscape and store the orientation as a
+    /// display matrix, or in older files as `rotate` tag.
+    pub fn rotation(&self) -> Rotation {
+        let stream = self
+            .ictx
+            .stream(self.video_stream_index)
+            .expect("video stream must exist");
+        let parameters = stream.parameters();
+        // SAFETY: the parameters are owned by the stream, which outlives this block
+        let display_rotation = unsafe {
+            let par = parameters.as_ptr();
+            let side_data = ffmpeg::ffi::av_packet_side_data_get(
+                (*par).coded_side_data,
+                (*par).nb_coded_side_data,
+                ffmpeg::ffi::AVPacketSideDataType::AV_PKT_DATA_DISPLAYMATRIX,
+            );
+            if side_data.is_null() {
+                None
+            } else {
+                // counter-clockwise rotation of the display matrix
+                Some(ffmpeg::ffi::av_display_rotation_get(
+                    (*side_data).data as *const i32,
+                ))
+            }
+        };
+        match display_rotation {
+            Some(degrees) if !degrees.is_nan() => Rotation::from_degrees(-degrees),
+            _ => stream
+                .metadata()
+                .get("rotate")
+                .and_then(|degrees| degrees.parse().ok())
+                .map(Rotation::from_degrees)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Decode all frames of the video stream and pass them on, together with
+    /// their timestamp in ms.
+    pub fn for_each_frame(
+        &mut self,
+        mut f: impl FnMut(&frame::Video, i64) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let time_base = self.time_base();
+        let mut receive_frames = |decoder: &mut decoder::Video| -> anyhow::Result<()> {
+            let mut frame = frame::Video::empty();
+            while decoder.receive_frame(&mut frame).is_ok() {
+                let pts = frame.pts().unwrap_or(0);
+                let timestamp_ms = (pts as f64 * f64::from(time_base) * 1000.0).round() as i64;
+                f(&frame, timestamp_ms)?;
+            }
+            Ok(())
+        };
+
+        for (stream, packet) in self.ictx.packets() {
+            if stream.index() == self.video_stream_index {
+                self.decoder.send_packet(&packet)?;
+                receive_frames(&mut self.decoder)?;
+            }
+        }
+        // flush frames buffered inside the decoder
+        self.decoder.send_eof()?;
+        receive_frames(&mut self.decoder)?;
+        Ok(())
+    }
+}
+
+/// Decode the best audio stream of a file to mono f32 samples at
+/// `sample_rate`.
+pub fn decode_audio(file_path: &str, sample_rate: u32) -> anyhow::Result<Vec<f32>> {
+    ffmpeg::init().unwrap();
+
+    let mut ictx = ffmpeg::format::input(&file_path)?;
+    let input = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Audio)
+        .ok_or_else(|| anyhow!("No audio stream found"))?;
+    let audio_stream_index = input.index();
+    let context_decoder = ffmpeg::codec::context::Context::from_parameters(input.parameters())?;
+    let mut decoder = context_decoder.decoder().audio()?;
+    let mut resampler = decoder.resampler(
+        ffmpeg::format::Sample::F32(ffmpeg::format::sample::Type::Packed),
+        ChannelLayout::MONO,
+        sample_rate,
+    )?;
+
+    let mut samples = vec![];
+    let mut receive_frames = |decoder: &mut decoder::Audio| -> anyhow::Result<()> {
+        let mut frame = frame::Audio::empty();
+        while decoder.receive_frame(&mut frame).is_ok() {
+            let mut resampled = frame::Audio::empty();
+            resampler.run(&frame, &mut resampled)?;
+            samples.extend_from_slice(resampled.plane::<f32>(0));
+        }
+        Ok(())
+    };
+
+    for (stream, packet) in ictx.packets() {
+        if stream.index() == audio_stream_index {
+            decoder.send_packet(&packet)?;
+            receive_frames(&mut decoder)?;
+        }
+    }
+    // flush frames buffered inside the decoder
+    decoder.send_eof()?;
+    receive_frames(&mut decoder)?;
+    Ok(samples)
+}
+
+impl VideoOutput {
+    /// Create a video file that accepts RGB24 frames of the given size.
+    ///
+    /// Frame timestamps are interpreted in `time_base`, usually the time base
+    /// of the input video, which allows copying them over unchanged.
+    pub fn by_path(
+        file_path: &str,
+        width: u32,
+        height: u32,
+        time_base: Rational,
+        frame_rate: Rational,
+    ) -> anyhow::Result<VideoOutput> {
+        ffmpeg::init().unwrap();
+
+        let mut octx = ffmpeg::format::output(&file_path)?;
+        let codec = ffmpeg::encoder::find(ffmpeg::codec::Id::H264)
+            .ok_or_else(|| anyhow!("No H.264 encoder found"))?;
+        let global_header = octx
+            .format()
+            .flags()
+            .contains(ffmpeg::format::Flags::GLOBAL_HEADER);
+
+        let mut context_encoder = ffmpeg::codec::context::Context::new_with_codec(codec)
+            .encoder()
+            .video()?;
+        context_encoder.set_width(width);
+        context_encoder.set_height(height);
+        context_encoder.set_format(ffmpeg::format::Pixel::YUV420P);
+        context_encoder.set_time_base(time_base);
+        context_encoder.set_frame_rate(Some(frame_rate));
+        if global_header {
+            context_encoder.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
+        }
+        let encoder = context_encoder.open_as(codec)?;
+
+        {
+            let mut stream = octx.add_stream(codec)?;
+            stream.set_parameters(&encoder);
+            stream.set_time_base(time_base);
+        }
+        octx.write_header()?;
+
+        let scaler = scaling::Context::get(
+            ffmpeg::format::Pixel::RGB24,
+            width,
+            height,
+            ffmpeg::format::Pixel::YUV420P,
+            width,
+            height,
+            scaling::flag::Flags::BILINEAR,
+        )?;
+
+        Ok(VideoOutput {
+            octx,
+            encoder,
+            scaler,
+            time_base,
+        })
+    }
+
+    /// Encode a RGB24 frame, `pts` in the time base of the output.
+    pub fn write_frame(&mut self, rgb_frame: &frame::Video, pts: i64) -> anyhow::Result<()> {
+        let mut yuv_frame = frame::Video::empty();
+        self.scaler.run(rgb_frame, &mut yuv_frame)?;
+        yuv_frame.set_pts(Some(pts));
+        self.encoder.send_frame(&yuv_frame)?;
+        self.write_packets()
+    }
+
+    /// Flush the encoder and complete the file.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.encoder.send_eof()?;
+        self.write_packets()?;
+        self.octx.write_trailer()?;
+        Ok(())
+    }
+
+    fn write_packets(&mut self) -> anyhow::Result<()> {
+        // the muxer may have changed the time base when writing the header
+        let stream_time_base = self.octx.stream(0).expect("stream was added").time_base();
+        let mut packet = ffmpeg::Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(0);
+            packet.rescale_ts(self.time_base, stream_time_base);
+            packet.write_interleaved(&mut self.octx)?;
+        }
+        Ok(())
+    }
+}
+
+/// Copy pixels of a RGB24 frame into a buffer without row padding, the layout
+/// plotters and the letterbox expect.
+pub fn packed_pixels(frame: &frame::Video) -> Vec<u8> {
+    let row_len = frame.width() as usize * 3;
+    frame
+        .data(0)
+        .chunks(frame.stride(0))
+        .take(frame.height() as usize)
+        .flat_map(|row| &row[..row_len])
+        .copied()
+        .collect()
+}
+
+/// Reverse of `packed_pixels`.
+pub fn unpack_pixels(pixels: &[u8], frame: &mut frame::Video) {
+    let row_len = frame.width() as usize * 3;
+    let stride = frame.stride(0);
+    for (row, packed) in frame
+        .data_mut(0)
+        .chunks_mut(stride)
+        .zip(pixels.chunks(row_len))
+    {
+        row[..row_len].copy_from_slice(packed);
+    }
 }
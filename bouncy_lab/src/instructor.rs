@@ -0,0 +1,43 @@
+//! Feed the instructor with the same poses and steps the app uses.
+
+use anyhow::Context;
+use std::path::Path;
+
+/// Where the frontend keeps its static poses and steps, relative to the lab.
+pub const DEFAULT_ASSETS_DIR: &str = "../bouncy_frontend/src/lib/assets";
+
+/// Load all poses and steps from the assets directory of the frontend.
+///
+/// Each step file is loaded with its file name as step source, like the app
+/// does it.
+pub fn load_assets(assets_dir: &str) -> anyhow::Result<()> {
+    let assets_dir = Path::new(assets_dir);
+    for file_name in ["pose.ron", "animation_poses.ron"] {
+        let path = assets_dir.join(file_name);
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed reading {}", path.display()))?;
+        bouncy_instructor::load_pose_str(&text)
+            .with_context(|| format!("failed parsing {}", path.display()))?;
+    }
+
+    let mut step_files = vec![];
+    for entry in std::fs::read_dir(assets_dir.join("steps")).context("failed listing steps")? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "ron") {
+            step_files.push(path);
+        }
+    }
+    step_files.sort();
+    for path in step_files {
+        let source = path
+            .file_stem()
+            .expect("must have a name")
+            .to_string_lossy()
+            .to_string();
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed reading {}", path.display()))?;
+        bouncy_instructor::load_step_str(&text, source)
+            .with_context(|| format!("failed parsing {}", path.display()))?;
+    }
+    Ok(())
+}
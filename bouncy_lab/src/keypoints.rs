@@ -39,3 +39,80 @@ pub struct Cartesian3d {
     /// distance to camera
     pub z: f32,
 }
+
+impl Keypoints {
+    /// All coordinates, left side first.
+    pub fn points(&self) -> Vec<Cartesian3d> {
+        let mut points = self.left.points().to_vec();
+        points.extend(self.right.points());
+        points
+    }
+
+    /// Apply a coordinate transformation to all body parts.
+    pub fn map(&self, f: impl Fn(Cartesian3d) -> Cartesian3d) -> Keypoints {
+        Keypoints {
+            left: self.left.map(&f),
+            right: self.right.map(&f),
+        }
+    }
+}
+
+impl Side {
+    fn map(&self, f: impl Fn(Cartesian3d) -> Cartesian3d) -> Side {
+        Side {
+            shoulder: f(self.shoulder),
+            hip: f(self.hip),
+            knee: f(self.knee),
+            ankle: f(self.ankle),
+            heel: f(self.heel),
+            toes: f(self.toes),
+            elbow: f(self.elbow),
+            wrist: f(self.wrist),
+        }
+    }
+
+    fn points(&self) -> [Cartesian3d; 8] {
+        [
+            self.shoulder,
+            self.hip,
+            self.knee,
+            self.ankle,
+            self.heel,
+            self.toes,
+            self.elbow,
+            self.wrist,
+        ]
+    }
+}
+
+impl From<Keypoints> for bouncy_instructor::Keypoints {
+    fn from(kp: Keypoints) -> Self {
+        // Same check as in the frontend: Are all body parts inside the frame?
+        let fully_visible = kp
+            .points()
+            .iter()
+            .all(|c| c.x.abs() <= 1.0 && c.y.abs() <= 1.0);
+        bouncy_instructor::Keypoints::new(kp.left.into(), kp.right.into(), fully_visible)
+    }
+}
+
+impl From<Side> for bouncy_instructor::KeypointsSide {
+    fn from(side: Side) -> Self {
+        bouncy_instructor::KeypointsSide::new(
+            side.shoulder.into(),
+            side.hip.into(),
+            side.knee.into(),
+            side.ankle.into(),
+            side.heel.into(),
+            side.toes.into(),
+            side.elbow.into(),
+            side.wrist.into(),
+        )
+    }
+}
+
+impl From<Cartesian3d> for bouncy_instructor::Cartesian3d {
+    fn from(c: Cartesian3d) -> Self {
+        bouncy_instructor::Cartesian3d::new(c.x, c.y, c.z)
+    }
+}
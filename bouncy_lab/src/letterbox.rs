@@ -0,0 +1,234 @@
+//! Prepare video frames for the pose model: upright, optionally cropped to the
+//! dancer, and scaled into a square without distorting body proportions.
+//!
+//! Detected coordinates are mapped back to the full upright frame, which is
+//! the coordinate space keypoints are recorded in.
+
+use crate::keypoints::{Cartesian3d, Keypoints};
+
+/// Clockwise rotation that turns decoded frames upright.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+/// Rectangle in normalized coordinates of the upright frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+/// Mapping between an upright frame region and the square model input.
+#[derive(Clone, Copy, Debug)]
+pub struct Letterbox {
+    rotation: Rotation,
+    roi: Rect,
+    /// Pixel size of the upright frame.
+    upright_width: f32,
+    upright_height: f32,
+    /// Width and height of the model input.
+    size: u32,
+    /// Model input pixels per upright frame pixel.
+    scale: f32,
+    /// Border in model input pixels, left and right of the frame region.
+    pad_x: f32,
+    /// Border in model input pixels, above and below the frame region.
+    pad_y: f32,
+}
+
+impl Rotation {
+    /// Snap degrees (clockwise) to the closest quarter turn.
+    pub fn from_degrees(degrees: f64) -> Self {
+        match ((degrees / 90.0).round() as i64).rem_euclid(4) {
+            0 => Rotation::None,
+            1 => Rotation::Clockwise90,
+            2 => Rotation::Clockwise180,
+            _ => Rotation::Clockwise270,
+        }
+    }
+
+    pub fn swaps_sides(&self) -> bool {
+        matches!(self, Rotation::Clockwise90 | Rotation::Clockwise270)
+    }
+
+    /// Size after rotation.
+    pub fn upright_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.swaps_sides() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Normalized coordinate in the decoded frame for a normalized coordinate
+    /// in the upright frame.
+    fn source_coordinate(&self, u: f32, v: f32) -> (f32, f32) {
+        match self {
+            Rotation::None => (u, v),
+            Rotation::Clockwise90 => (v, 1.0 - u),
+            Rotation::Clockwise180 => (1.0 - u, 1.0 - v),
+            Rotation::Clockwise270 => (1.0 - v, u),
+        }
+    }
+
+    /// Rotate a packed RGB24 image upright.
+    pub fn apply(&self, pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+        if *self == Rotation::None {
+            return pixels.to_vec();
+        }
+        let (w, h) = (width as usize, height as usize);
+        let (upright_w, upright_h) = self.upright_size(width, height);
+        let (upright_w, upright_h) = (upright_w as usize, upright_h as usize);
+        let mut out = vec![0; pixels.len()];
+        for uy in 0..upright_h {
+            for ux in 0..upright_w {
+                let (sx, sy) = match self {
+                    Rotation::None => (ux, uy),
+                    Rotation::Clockwise90 => (uy, h - 1 - ux),
+                    Rotation::Clockwise180 => (w - 1 - ux, h - 1 - uy),
+                    Rotation::Clockwise270 => (w - 1 - uy, ux),
+                };
+                let src = (sy * w + sx) * 3;
+                let dst = (uy * upright_w + ux) * 3;
+                out[dst..dst + 3].copy_from_slice(&pixels[src..src + 3]);
+            }
+        }
+        out
+    }
+}
+
+impl Rect {
+    pub const FULL: Rect = Rect {
+        left: 0.0,
+        top: 0.0,
+        right: 1.0,
+        bottom: 1.0,
+    };
+
+    /// Bounding box of all keypoints of all people, grown by `margin` times
+    /// its size on each side and clamped to the frame.
+    ///
+    /// Falls back to the full frame for degenerate boxes.
+    pub fn around(people: &[Keypoints], margin: f32) -> Rect {
+        let points: Vec<Cartesian3d> = people.iter().flat_map(Keypoints::points).collect();
+        let min_x = points.iter().map(|c| c.x).fold(f32::INFINITY, f32::min);
+        let max_x = points.iter().map(|c| c.x).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = points.iter().map(|c| c.y).fold(f32::INFINITY, f32::min);
+        let max_y = points.iter().map(|c| c.y).fold(f32::NEG_INFINITY, f32::max);
+        let margin_x = (max_x - min_x) * margin;
+        let margin_y = (max_y - min_y) * margin;
+        let rect = Rect {
+            left: (min_x - margin_x).max(0.0),
+            top: (min_y - margin_y).max(0.0),
+            right: (max_x + margin_x).min(1.0),
+            bottom: (max_y + margin_y).min(1.0),
+        };
+        if rect.left < rect.right && rect.top < rect.bottom {
+            rect
+        } else {
+            Rect::FULL
+        }
+    }
+
+    fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    fn height(&self) -> f32 {
+        self.bottom - self.top
+    }
+}
+
+impl Letterbox {
+    /// `width` and `height` are the size of decoded frames, before rotation.
+    pub fn new(width: u32, height: u32, rotation: Rotation, roi: Rect, size: u32) -> Self {
+        let (upright_width, upright_height) = rotation.upright_size(width, height);
+        let (upright_width, upright_height) = (upright_width as f32, upright_height as f32);
+        let roi_width = roi.width() * upright_width;
+        let roi_height = roi.height() * upright_height;
+        let scale = size as f32 / roi_width.max(roi_height);
+        Self {
+            rotation,
+            roi,
+            upright_width,
+            upright_height,
+            size,
+            scale,
+            pad_x: (size as f32 - roi_width * scale) / 2.0,
+            pad_y: (size as f32 - roi_height * scale) / 2.0,
+        }
+    }
+
+    /// Sample the square model input from a packed RGB24 frame, as decoded.
+    ///
+    /// The frame may have a lower resolution than the video, as long as it
+    /// keeps the proportions. The border is filled black.
+    pub fn model_input(&self, pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+        let size = self.size as usize;
+        let mut out = vec![0; size * size * 3];
+        for my in 0..size {
+            for mx in 0..size {
+                let Some((u, v)) = self.upright_coordinate(mx as f32 + 0.5, my as f32 + 0.5) else {
+                    continue;
+                };
+                let (sx, sy) = self.rotation.source_coordinate(u, v);
+                let rgb = sample_bilinear(pixels, width, height, sx, sy);
+                let dst = (my * size + mx) * 3;
+                out[dst..dst + 3].copy_from_slice(&rgb);
+            }
+        }
+        out
+    }
+
+    /// Map a coordinate detected in the model input, normalized to the model
+    /// input size, to normalized coordinates of the upright frame.
+    pub fn back_project(&self, c: Cartesian3d) -> Cartesian3d {
+        let size = self.size as f32;
+        let x_px = self.roi.left * self.upright_width + (c.x * size - self.pad_x) / self.scale;
+        let y_px = self.roi.top * self.upright_height + (c.y * size - self.pad_y) / self.scale;
+        // z uses roughly the same scale as x
+        let z_px = c.z * size / self.scale;
+        Cartesian3d {
+            x: x_px / self.upright_width,
+            y: y_px / self.upright_height,
+            z: z_px / self.upright_width,
+        }
+    }
+
+    /// Normalized upright frame coordinate for a pixel position in the model
+    /// input, `None` for the border.
+    fn upright_coordinate(&self, mx: f32, my: f32) -> Option<(f32, f32)> {
+        let u = self.roi.left + (mx - self.pad_x) / self.scale / self.upright_width;
+        let v = self.roi.top + (my - self.pad_y) / self.scale / self.upright_height;
+        if u < self.roi.left || u > self.roi.right || v < self.roi.top || v > self.roi.bottom {
+            None
+        } else {
+            Some((u, v))
+        }
+    }
+}
+
+/// Read a pixel at normalized coordinates, interpolating between neighbours.
+fn sample_bilinear(pixels: &[u8], width: u32, height: u32, x: f32, y: f32) -> [u8; 3] {
+    let (w, h) = (width as usize, height as usize);
+    let fx = (x * width as f32 - 0.5).clamp(0.0, (w - 1) as f32);
+    let fy = (y * height as f32 - 0.5).clamp(0.0, (h - 1) as f32);
+    let (x0, y0) = (fx as usize, fy as usize);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (dx, dy) = (fx - x0 as f32, fy - y0 as f32);
+    let px = |x: usize, y: usize, channel: usize| pixels[(y * w + x) * 3 + channel] as f32;
+    let mut out = [0; 3];
+    for (channel, value) in out.iter_mut().enumerate() {
+        let top = px(x0, y0, channel) * (1.0 - dx) + px(x1, y0, channel) * dx;
+        let bottom = px(x0, y1, channel) * (1.0 - dx) + px(x1, y1, channel) * dx;
+        *value = (top * (1.0 - dy) + bottom * dy).round() as u8;
+    }
+    out
+}
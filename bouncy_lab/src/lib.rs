@@ -5,25 +5,203 @@ use std::io::Read;
 
 use crate::ffmpeg::VideoInput;
 use crate::mediapipe::detect_pose;
+use bouncy_instructor::{BeatGrid, DetectorConfig};
+
+pub use evaluation::EvaluationReport;
+pub use instructor::DEFAULT_ASSETS_DIR;
+pub use mediapipe::DetectionOptions;
+pub use overlay::StepOverlay;
 
 pub(crate) mod generated;
 
+mod evaluation;
 mod ffmpeg;
+mod instructor;
 mod keypoints;
+mod letterbox;
 mod mediapipe;
+mod overlay;
+mod people;
+mod sweep;
 
-pub fn video_pose_detection(file_path: &str) -> anyhow::Result<()> {
+pub fn video_pose_detection(
+    file_path: &str,
+    output_path: Option<&str>,
+    options: DetectionOptions,
+) -> anyhow::Result<()> {
     let video_input = VideoInput::by_path(file_path)?;
-    detect_pose(video_input)?;
+    let recordings = detect_pose(video_input, options)?;
+    for (person, recording) in recordings.iter().enumerate() {
+        println!(
+            "Detected keypoints of person {person} in {} frames",
+            recording.len()
+        );
+    }
+    if let Some(output_path) = output_path {
+        if recordings.len() == 1 {
+            write_recording(output_path, &recordings[0])?;
+        } else {
+            for (person, recording) in recordings.iter().enumerate() {
+                write_recording(&person_output_path(output_path, person), recording)?;
+            }
+        }
+    }
     Ok(())
 }
 
-pub fn plot_body_part_time_series(file_path: &str) -> anyhow::Result<()> {
+/// Encode a copy of the video with the tracked skeletons drawn on each frame.
+///
+/// Keypoints are read from the recordings if any are given, otherwise pose
+/// detection runs on the video first. The step is compared to the person at
+/// index `person`.
+#[allow(clippy::too_many_arguments)]
+pub fn video_overlay(
+    file_path: &str,
+    recording_paths: &[&str],
+    output_path: &str,
+    step: Option<StepOverlay>,
+    person: usize,
+    assets_dir: &str,
+    options: DetectionOptions,
+) -> anyhow::Result<()> {
+    let recordings = if recording_paths.is_empty() {
+        detect_pose(VideoInput::by_path(file_path)?, options)?
+    } else {
+        recording_paths
+            .iter()
+            .map(|path| read_recording(path))
+            .collect::<anyhow::Result<_>>()?
+    };
+    if step.is_some() {
+        instructor::load_assets(assets_dir)?;
+    }
+    let video_input = VideoInput::by_path(file_path)?;
+    overlay::render_overlay(video_input, &recordings, output_path, step, person)
+}
+
+/// Compare steps detected by the instructor to annotated ground truth, print
+/// a summary and write a report with plots to `output_dir`.
+///
+/// Uses the detector config at `config_path`, or the defaults of the
+/// instructor.
+pub fn evaluate_step_detection(
+    annotation_paths: &[&str],
+    output_dir: &str,
+    tolerance_beats: f64,
+    config_path: Option<&str>,
+    assets_dir: &str,
+    options: DetectionOptions,
+) -> anyhow::Result<EvaluationReport> {
+    instructor::load_assets(assets_dir)?;
+    let config = read_detector_config(config_path)?;
+    let report = evaluation::evaluate(
+        annotation_paths,
+        output_dir,
+        tolerance_beats,
+        &config,
+        options,
+    )?;
+    report.print_summary();
+    Ok(report)
+}
+
+/// Search detection thresholds that work best on annotated videos and write
+/// them as detector config file for the app.
+///
+/// Parameters that are not swept are taken from the config at `config_path`.
+pub fn sweep_detector_config(
+    annotation_paths: &[&str],
+    output_path: &str,
+    tolerance_beats: f64,
+    config_path: Option<&str>,
+    assets_dir: &str,
+    options: DetectionOptions,
+) -> anyhow::Result<()> {
+    instructor::load_assets(assets_dir)?;
+    let config = read_detector_config(config_path)?;
+    sweep::sweep(
+        annotation_paths,
+        output_path,
+        tolerance_beats,
+        &config,
+        options,
+    )?;
+    Ok(())
+}
+
+/// Sample rate for beat tracking, high enough for drums and hi-hats.
+const BEAT_TRACKING_SAMPLE_RATE: u32 = 22050;
+
+/// Find tempo and beats in the audio of a video or song.
+///
+/// Writes a video definition in the format of course files to `output_path`,
+/// or prints it if no path is given. The path in the definition is relative
+/// to the current directory and may need adjustment.
+pub fn detect_beats(
+    file_path: &str,
+    output_path: Option<&str>,
+    min_bpm: f32,
+    max_bpm: f32,
+) -> anyhow::Result<BeatGrid> {
+    let samples = ffmpeg::decode_audio(file_path, BEAT_TRACKING_SAMPLE_RATE)?;
+    let grid = BeatGrid::from_samples_in_range(
+        &samples,
+        BEAT_TRACKING_SAMPLE_RATE as f32,
+        min_bpm,
+        max_bpm,
+    )
+    .ok_or_else(|| anyhow::anyhow!("no steady beat found in {file_path}"))?;
+    println!(
+        "Detected {} beats at {:.1} bpm, first beat at {:.0} ms",
+        grid.beats().len(),
+        grid.bpm,
+        grid.first_beat
+    );
+    let video_def = grid.video_def_ron(file_path.to_owned())?;
+    match output_path {
+        Some(output_path) => std::fs::write(output_path, video_def)?,
+        None => println!("{video_def}"),
+    }
+    Ok(grid)
+}
+
+fn read_detector_config(file_path: Option<&str>) -> anyhow::Result<DetectorConfig> {
+    match file_path {
+        Some(file_path) => Ok(DetectorConfig::from_ron(&std::fs::read_to_string(
+            file_path,
+        )?)?),
+        None => Ok(DetectorConfig::default()),
+    }
+}
+
+/// Output path of a single person, `out.ron` becomes `out.0.ron` for the
+/// first person.
+fn person_output_path(output_path: &str, person: usize) -> String {
+    let path = std::path::Path::new(output_path);
+    match path.extension() {
+        Some(ext) => path
+            .with_extension(format!("{person}.{}", ext.to_string_lossy()))
+            .to_string_lossy()
+            .to_string(),
+        None => format!("{output_path}.{person}"),
+    }
+}
+
+fn read_recording(file_path: &str) -> anyhow::Result<Vec<(u64, Keypoints)>> {
     let mut file = File::open(file_path)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
+    Ok(ron::from_str(&contents)?)
+}
+
+fn write_recording(file_path: &str, recording: &[(u64, Keypoints)]) -> anyhow::Result<()> {
+    let text = ron::ser::to_string_pretty(recording, ron::ser::PrettyConfig::default())?;
+    std::fs::write(file_path, text)?;
+    Ok(())
+}
 
-    let data: Vec<(u64, Keypoints)> = ron::from_str(&contents)?;
+pub fn plot_body_part_time_series(file_path: &str) -> anyhow::Result<()> {
+    let data = read_recording(file_path)?;
 
     let root = BitMapBackend::new("output.png", (1024, 768)).into_drawing_area();
     root.fill(&WHITE)?;
@@ -1,9 +1,13 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, ArgMatches, Command};
 
 fn main() -> anyhow::Result<()> {
     let mut cmd = Command::new("Bouncy Lab")
         .subcommand(plot_body_movements())
-        .subcommand(video_detection());
+        .subcommand(video_detection())
+        .subcommand(video_overlay())
+        .subcommand(evaluate())
+        .subcommand(sweep())
+        .subcommand(beats());
 
     let matches = cmd.clone().get_matches();
 
@@ -14,13 +18,132 @@ fn main() -> anyhow::Result<()> {
 
     if let Some(sub_matches) = matches.subcommand_matches("video-detect") {
         let file_path: &String = sub_matches.get_one("input").expect("input file required");
-        return bouncy_lab::video_pose_detection(file_path);
+        let output: Option<&String> = sub_matches.get_one("output");
+        return bouncy_lab::video_pose_detection(
+            file_path,
+            output.map(String::as_str),
+            detection_options(sub_matches),
+        );
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("video-overlay") {
+        let file_path: &String = sub_matches.get_one("input").expect("input file required");
+        let keypoints: Vec<&str> = sub_matches
+            .get_many::<String>("keypoints")
+            .unwrap_or_default()
+            .map(String::as_str)
+            .collect();
+        let person: usize = *sub_matches.get_one("person").expect("has default");
+        let output: &String = sub_matches.get_one("output").expect("has default");
+        let assets: &String = sub_matches.get_one("assets").expect("has default");
+        let step = match sub_matches.get_one::<String>("step") {
+            Some(step_id) => Some(bouncy_lab::StepOverlay {
+                step_id: step_id.clone(),
+                bpm: *sub_matches.get_one("bpm").expect("required with step"),
+                first_beat_ms: *sub_matches.get_one("first-beat").expect("has default"),
+            }),
+            None => None,
+        };
+        return bouncy_lab::video_overlay(
+            file_path,
+            &keypoints,
+            output,
+            step,
+            person,
+            assets,
+            detection_options(sub_matches),
+        );
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("evaluate") {
+        let annotations: Vec<&str> = sub_matches
+            .get_many::<String>("annotations")
+            .expect("required")
+            .map(String::as_str)
+            .collect();
+        let output: &String = sub_matches.get_one("output").expect("has default");
+        let tolerance: f64 = *sub_matches.get_one("tolerance").expect("has default");
+        let config: Option<&String> = sub_matches.get_one("config");
+        let assets: &String = sub_matches.get_one("assets").expect("has default");
+        bouncy_lab::evaluate_step_detection(
+            &annotations,
+            output,
+            tolerance,
+            config.map(String::as_str),
+            assets,
+            detection_options(sub_matches),
+        )?;
+        return Ok(());
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("sweep") {
+        let annotations: Vec<&str> = sub_matches
+            .get_many::<String>("annotations")
+            .expect("required")
+            .map(String::as_str)
+            .collect();
+        let output: &String = sub_matches.get_one("output").expect("has default");
+        let tolerance: f64 = *sub_matches.get_one("tolerance").expect("has default");
+        let config: Option<&String> = sub_matches.get_one("config");
+        let assets: &String = sub_matches.get_one("assets").expect("has default");
+        return bouncy_lab::sweep_detector_config(
+            &annotations,
+            output,
+            tolerance,
+            config.map(String::as_str),
+            assets,
+            detection_options(sub_matches),
+        );
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("beats") {
+        let input: &String = sub_matches.get_one("input").expect("required");
+        let output: Option<&String> = sub_matches.get_one("output");
+        let min_bpm: f32 = *sub_matches.get_one("min-bpm").expect("has default");
+        let max_bpm: f32 = *sub_matches.get_one("max-bpm").expect("has default");
+        bouncy_lab::detect_beats(input, output.map(String::as_str), min_bpm, max_bpm)?;
+        return Ok(());
     }
 
     cmd.print_help()?;
     Ok(())
 }
 
+fn detection_options(matches: &ArgMatches) -> bouncy_lab::DetectionOptions {
+    bouncy_lab::DetectionOptions {
+        person_roi: matches.get_flag("roi"),
+        // not all subcommands can handle several people
+        max_people: matches
+            .try_get_one("people")
+            .ok()
+            .flatten()
+            .copied()
+            .unwrap_or(1),
+    }
+}
+
+fn roi_arg() -> Arg {
+    Arg::new("roi")
+        .long("roi")
+        .action(ArgAction::SetTrue)
+        .help("Crop frames around the dancers detected in the previous frame")
+}
+
+fn people_arg() -> Arg {
+    Arg::new("people")
+        .long("people")
+        .value_parser(clap::value_parser!(u32).range(1..))
+        .default_value("1")
+        .help("Maximum number of people to detect per frame")
+}
+
+fn assets_arg() -> Arg {
+    Arg::new("assets")
+        .long("assets")
+        .default_value(bouncy_lab::DEFAULT_ASSETS_DIR)
+        .help("Directory with poses and steps")
+}
+
 fn plot_body_movements() -> Command {
     Command::new("plot-body-movements")
         .about("Takes an input RON and plots how each body parts moved over time.")
@@ -31,4 +154,150 @@ fn video_detection() -> Command {
     Command::new("video-detect")
         .about("Run computer vision tasks to detect a person and their pose in a video.")
         .arg(Arg::new("input").required(true))
+        .arg(Arg::new("output").long("output").short('o').help(
+            "Write detected keypoints to this RON file, \
+                    one file per person if there are several (out.0.ron, out.1.ron, ...)",
+        ))
+        .arg(roi_arg())
+        .arg(people_arg())
+}
+
+fn video_overlay() -> Command {
+    Command::new("video-overlay")
+        .about("Encode a copy of a video with the tracked skeleton drawn over each frame.")
+        .arg(Arg::new("input").required(true))
+        .arg(
+            Arg::new("keypoints")
+                .long("keypoints")
+                .short('k')
+                .action(ArgAction::Append)
+                .help("RON keypoint recording of the video, once per person, detects poses if missing"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .default_value("overlay.mp4"),
+        )
+        .arg(
+            Arg::new("step")
+                .long("step")
+                .requires("bpm")
+                .help("Step ID to draw the expected pose and the pose error for"),
+        )
+        .arg(
+            Arg::new("bpm")
+                .long("bpm")
+                .value_parser(clap::value_parser!(f32))
+                .help("Tempo of the music in the video"),
+        )
+        .arg(
+            Arg::new("person")
+                .long("person")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("0")
+                .help("Index of the person compared to the step"),
+        )
+        .arg(
+            Arg::new("first-beat")
+                .long("first-beat")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("0")
+                .help("Video time of the first beat in ms"),
+        )
+        .arg(assets_arg())
+        .arg(roi_arg())
+        .arg(people_arg())
+}
+
+fn evaluate() -> Command {
+    Command::new("evaluate")
+        .about("Measure step detection accuracy against annotated videos.")
+        .arg(
+            Arg::new("annotations")
+                .required(true)
+                .num_args(1..)
+                .help("RON annotation files with beats and step markers"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .default_value("evaluation")
+                .help("Directory for the report and plots"),
+        )
+        .arg(tolerance_arg())
+        .arg(config_arg())
+        .arg(assets_arg())
+        .arg(roi_arg())
+}
+
+fn sweep() -> Command {
+    Command::new("sweep")
+        .about("Search detection thresholds that work best on annotated videos.")
+        .long_about(
+            "Search detection thresholds that work best on annotated videos.\n\n\
+             Only the step detection thresholds `first_pose_threshold`, `min_pose_interval` \
+             and `max_pose_interval` are swept. Per-pose error thresholds are derived from \
+             correctly detected steps and only ever loosen `error_threshold`. Thresholds of \
+             live tracking, like `error_threshold`, `left_right_ratio` and \
+             `positioning_threshold`, are written unchanged from the base config, which are \
+             the instructor defaults without `--config`.",
+        )
+        .arg(
+            Arg::new("annotations")
+                .required(true)
+                .num_args(1..)
+                .help("RON annotation files with beats and step markers"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .default_value("detector_config.ron")
+                .help("Write the best detector config to this RON file"),
+        )
+        .arg(tolerance_arg())
+        .arg(config_arg())
+        .arg(assets_arg())
+        .arg(roi_arg())
+}
+
+fn beats() -> Command {
+    Command::new("beats")
+        .about("Detect tempo and beats in the audio of a video or song.")
+        .arg(Arg::new("input").required(true))
+        .arg(
+            Arg::new("output").long("output").short('o').help(
+                "Write the video definition with beats to this RON file, prints it if missing",
+            ),
+        )
+        .arg(
+            Arg::new("min-bpm")
+                .long("min-bpm")
+                .value_parser(clap::value_parser!(f32))
+                .default_value("60")
+                .help("Slowest tempo to consider, raise it if half the tempo is detected"),
+        )
+        .arg(
+            Arg::new("max-bpm")
+                .long("max-bpm")
+                .value_parser(clap::value_parser!(f32))
+                .default_value("200")
+                .help("Fastest tempo to consider, lower it if double the tempo is detected"),
+        )
+}
+
+fn tolerance_arg() -> Arg {
+    Arg::new("tolerance")
+        .long("tolerance")
+        .value_parser(clap::value_parser!(f64))
+        .default_value("1")
+        .help("Max distance in beats between annotated and detected step starts")
+}
+
+fn config_arg() -> Arg {
+    Arg::new("config")
+        .long("config")
+        .help("Detector config RON file, uses the instructor defaults if missing")
 }
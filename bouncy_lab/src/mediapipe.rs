@@ -1,11 +1,15 @@
 use crate::{
-    ffmpeg::VideoInput,
+    ffmpeg::{packed_pixels, VideoInput},
     generated::pose_bindings::{
-        pose_landmarker_create, pose_landmarker_detect_for_video, BaseOptions,
-        ImageProcessingOptions, MPRectF, MpImageCreateFromUint8Data,
-        MpImageFormat_kMpImageFormatSrgb, MpImageFree, MpImagePtr, MpStatus_kMpOk,
-        PoseLandmarkerOptions, PoseLandmarkerResult, RunningMode_VIDEO,
+        pose_landmarker_close, pose_landmarker_close_result, pose_landmarker_create,
+        pose_landmarker_detect_for_video, BaseOptions, ImageProcessingOptions, MPRectF,
+        MpImageCreateFromUint8Data, MpImageFormat_kMpImageFormatSrgb, MpImageFree, MpImagePtr,
+        MpStatus_kMpOk, NormalizedLandmark, PoseLandmarkerOptions, PoseLandmarkerResult,
+        RunningMode_VIDEO,
     },
+    keypoints::{Cartesian3d, Keypoints, Side},
+    letterbox::{Letterbox, Rect},
+    people::PersonTracker,
 };
 use ::std::os::raw::c_char;
 use anyhow::Context;
@@ -15,25 +19,75 @@ use std::{
     ptr::null_mut,
 };
 
-pub fn detect_pose(mut video: VideoInput) -> anyhow::Result<()> {
+/// Width and height of the model input, RGB24.
+/// See https://ai.google.dev/edge/mediapipe/solutions/vision/pose_landmarker.
+const MODEL_INPUT_SIZE: u32 = 256;
+
+/// Frames are downscaled by ffmpeg to at most this size on the longer side,
+/// before the model input is sampled from them. Large enough to keep details
+/// when cropping to the dancer.
+const MAX_SOURCE_SIZE: u32 = 1024;
+
+/// Space around the dancer, relative to the size of the body, when cropping
+/// to a person-centred region of interest.
+const ROI_MARGIN: f32 = 0.25;
+
+/// While fewer people than the maximum are detected in a cropped frame, the
+/// full frame is searched again after this long, in ms, to find newcomers.
+const FULL_FRAME_SEARCH_INTERVAL: u64 = 1000;
+
+#[derive(Clone, Copy, Debug)]
+pub struct DetectionOptions {
+    /// Crop frames around the people detected in the previous frame, which
+    /// helps when dancers only fill a small part of the frame.
+    pub person_roi: bool,
+    /// How many people the model looks for in each frame.
+    pub max_people: u32,
+}
+
+impl Default for DetectionOptions {
+    fn default() -> Self {
+        Self {
+            person_roi: false,
+            max_people: 1,
+        }
+    }
+}
+
+/// Run pose detection on every frame of the video.
+///
+/// Returns one recording per person, in order of first appearance. Each
+/// recording holds keypoints with their timestamp in ms, in the same format as
+/// keypoint recordings exported by the app. Coordinates are normalized to the
+/// upright frame, after applying the rotation stored in the video.
+pub fn detect_pose(
+    mut video: VideoInput,
+    options: DetectionOptions,
+) -> anyhow::Result<Vec<Vec<(u64, Keypoints)>>> {
     let path = std::fs::read_link("./models/pose_landmarker_heavy.task")
         .context("failed reading model task symlink")?;
     let model_asset = std::fs::read(&path).context("failed reading model task file")?;
     let model_asset_path = CString::new(path.to_string_lossy().to_string())?;
 
-    // The model uses 256 x 256 pixels with RGB24.
-    // See https://ai.google.dev/edge/mediapipe/solutions/vision/pose_landmarker.
-    // We use ffmpeg to scale it accordingly, potentially squeezing the image
-    // out of its original proportion.
+    // Frames are letterboxed into the square model input to keep the body
+    // proportions intact. We also rotate frames ourselves, rather than letting
+    // mediapipe do it, so that the detected coordinates are in the upright
+    // frame, with the y-axis pointing down to the floor.
+    let width = video.decoder.width();
+    let height = video.decoder.height();
+    let rotation = video.rotation();
+    let downscale = (MAX_SOURCE_SIZE as f32 / width.max(height) as f32).min(1.0);
+    let source_width = ((width as f32 * downscale).round() as u32).max(1);
+    let source_height = ((height as f32 * downscale).round() as u32).max(1);
 
     let mut scaler = ffmpeg::software::scaling::context::Context::get(
         video.decoder.format(),
-        video.decoder.width(),
-        video.decoder.height(),
+        width,
+        height,
         ffmpeg::format::Pixel::RGB24,
-        256,
-        256,
-        ffmpeg::software::scaling::flag::Flags::FAST_BILINEAR,
+        source_width,
+        source_height,
+        ffmpeg::software::scaling::flag::Flags::AREA,
     )?;
 
     let mut opt = PoseLandmarkerOptions {
@@ -43,8 +97,7 @@ pub fn detect_pose(mut video: VideoInput) -> anyhow::Result<()> {
             model_asset_path: model_asset_path.as_ptr(),
         },
         running_mode: RunningMode_VIDEO,
-        // max detectable poses, set to 1 for now, experiment with increasing later
-        num_poses: 1,
+        num_poses: options.max_people,
         min_pose_detection_confidence: 0.4,
         min_pose_presence_confidence: 0.4,
         min_tracking_confidence: 0.4,
@@ -61,94 +114,185 @@ pub fn detect_pose(mut video: VideoInput) -> anyhow::Result<()> {
         assert!(!landmarker.is_null());
     }
 
-    for (stream, packet) in video.ictx.packets() {
-        if stream.index() == video.video_stream_index {
-            video.decoder.send_packet(&packet)?;
-            let mut frame = ffmpeg::util::frame::Video::empty();
-            if video.decoder.receive_frame(&mut frame).is_ok() {
-                let pts = frame.pts().unwrap_or(0);
-                let time_base = stream.time_base();
-                let timestamp_ms = (pts as f64 * f64::from(time_base) * 1000.0).round() as i64;
-
-                let mut rgb_frame = ffmpeg::util::frame::Video::empty();
-                scaler.run(&frame, &mut rgb_frame)?;
-
-                let width = rgb_frame.width() as i32;
-                let height = rgb_frame.height() as i32;
-                let data = rgb_frame.data(0);
-                let data_size = rgb_frame.stride(0) * height as usize;
-
-                // Create MpImage
-                let mut image: MpImagePtr = std::ptr::null_mut();
-                let status = unsafe {
-                    MpImageCreateFromUint8Data(
-                        MpImageFormat_kMpImageFormatSrgb,
-                        width,
-                        height,
-                        data.as_ptr(),
-                        data_size as i32,
-                        &mut image,
-                    )
-                };
-
-                if status != MpStatus_kMpOk {
-                    panic!("Failed to create MpImage, error code {}", status);
-                }
-
-                unsafe {
-                    let region_of_interest = MPRectF {
-                        left: 0.0,
-                        top: 0.0,
-                        bottom: 1.0,
-                        right: 1.0,
-                    };
-                    let options = ImageProcessingOptions {
-                        // The optional region-of-interest to crop from the image.
-                        // If has_region_of_interest is 0, the full image is used.
-                        // Coordinates must be in [0,1] with 'left' < 'right' and 'top' < 'bottom'.
-                        has_region_of_interest: 0,
-                        region_of_interest,
-                        // The rotation to apply to the image (or cropped region-of-interest),
-                        // in degrees clockwise. The rotation must be a multiple (positive or
-                        // negative) of 90°.
-                        rotation_degrees: 0,
-                    };
-
-                    // optional paramter may be null?
-                    // let options: *const ImageProcessingOptions = null();
-
-                    let mut result = PoseLandmarkerResult {
-                        segmentation_masks: null_mut(),
-                        segmentation_masks_count: 0,
-                        pose_landmarks: null_mut(),
-                        pose_landmarks_count: 0,
-                        pose_world_landmarks: null_mut(),
-                        pose_world_landmarks_count: 0,
-                    };
-
-                    let mut error_msg = FFIResult::new();
-                    let _err_code = pose_landmarker_detect_for_video(
-                        landmarker,
-                        image,
-                        &options,
-                        timestamp_ms,
-                        &mut result,
-                        error_msg.as_mut(),
-                    );
-                    error_msg.check()?;
-
-                    let landmarks = *result.pose_landmarks;
-                    println!("Detected {} landmarks", landmarks.landmarks_count);
-
-                    MpImageFree(image);
-                }
+    let mut people = PersonTracker::new(options.max_people);
+    let mut roi = Rect::FULL;
+    let mut last_full_frame_search = 0;
+    video.for_each_frame(|frame, timestamp_ms| {
+        let mut rgb_frame = ffmpeg::util::frame::Video::empty();
+        scaler.run(frame, &mut rgb_frame)?;
+        let pixels = packed_pixels(&rgb_frame);
+
+        let letterbox = Letterbox::new(width, height, rotation, roi, MODEL_INPUT_SIZE);
+        let data = letterbox.model_input(&pixels, source_width, source_height);
+        let size = MODEL_INPUT_SIZE as i32;
+
+        // Create MpImage
+        let mut image: MpImagePtr = std::ptr::null_mut();
+        let status = unsafe {
+            MpImageCreateFromUint8Data(
+                MpImageFormat_kMpImageFormatSrgb,
+                size,
+                size,
+                data.as_ptr(),
+                data.len() as i32,
+                &mut image,
+            )
+        };
+
+        if status != MpStatus_kMpOk {
+            panic!("Failed to create MpImage, error code {}", status);
+        }
+
+        unsafe {
+            let region_of_interest = MPRectF {
+                left: 0.0,
+                top: 0.0,
+                bottom: 1.0,
+                right: 1.0,
+            };
+            let processing_options = ImageProcessingOptions {
+                // The optional region-of-interest to crop from the image.
+                // If has_region_of_interest is 0, the full image is used.
+                // Coordinates must be in [0,1] with 'left' < 'right' and 'top' < 'bottom'.
+                // (Not supported by the pose landmarker, we crop in the letterbox instead.)
+                has_region_of_interest: 0,
+                region_of_interest,
+                // The rotation to apply to the image (or cropped region-of-interest),
+                // in degrees clockwise. The rotation must be a multiple (positive or
+                // negative) of 90°.
+                // (The letterbox already rotated the image upright.)
+                rotation_degrees: 0,
+            };
+
+            // optional paramter may be null?
+            // let options: *const ImageProcessingOptions = null();
+
+            let mut result = PoseLandmarkerResult {
+                segmentation_masks: null_mut(),
+                segmentation_masks_count: 0,
+                pose_landmarks: null_mut(),
+                pose_landmarks_count: 0,
+                pose_world_landmarks: null_mut(),
+                pose_world_landmarks_count: 0,
+            };
+
+            let mut error_msg = FFIResult::new();
+            let _err_code = pose_landmarker_detect_for_video(
+                landmarker,
+                image,
+                &processing_options,
+                timestamp_ms,
+                &mut result,
+                error_msg.as_mut(),
+            );
+            error_msg.check()?;
+
+            let poses = if result.pose_landmarks_count > 0 {
+                std::slice::from_raw_parts(
+                    result.pose_landmarks,
+                    result.pose_landmarks_count as usize,
+                )
+            } else {
+                &[]
+            };
+            let detections: Vec<Keypoints> = poses
+                .iter()
+                .filter_map(|pose| {
+                    let landmarks =
+                        std::slice::from_raw_parts(pose.landmarks, pose.landmarks_count as usize);
+                    keypoints_from_landmarks(landmarks)
+                })
+                .map(|keypoints| keypoints.map(|c| letterbox.back_project(c)))
+                .collect();
+
+            let timestamp = timestamp_ms.max(0) as u64;
+            let missing_people = (detections.len() as u32) < options.max_people;
+            if detections.is_empty()
+                || (missing_people
+                    && timestamp.saturating_sub(last_full_frame_search)
+                        >= FULL_FRAME_SEARCH_INTERVAL)
+            {
+                // lost someone, search the full frame again
+                roi = Rect::FULL;
+                last_full_frame_search = timestamp;
+            } else if options.person_roi {
+                roi = Rect::around(&detections, ROI_MARGIN);
             }
+            // frames without a detected person are skipped
+            if !detections.is_empty() {
+                people.add_frame(timestamp, detections);
+            }
+
+            pose_landmarker_close_result(&mut result);
+            MpImageFree(image);
         }
-    }
+        Ok(())
+    })?;
 
+    unsafe {
+        let mut error_msg = FFIResult::new();
+        pose_landmarker_close(landmarker, error_msg.as_mut());
+        error_msg.check()?;
+    }
     drop(model_asset_path);
 
-    Ok(())
+    Ok(people.into_recordings())
+}
+
+/// Landmark indices of the BlazePose model, same as `I` in the frontend's
+/// `pose.js`.
+mod landmark {
+    pub const LEFT_SHOULDER: usize = 11;
+    pub const RIGHT_SHOULDER: usize = 12;
+    pub const LEFT_ELBOW: usize = 13;
+    pub const RIGHT_ELBOW: usize = 14;
+    pub const LEFT_WRIST: usize = 15;
+    pub const RIGHT_WRIST: usize = 16;
+    pub const LEFT_HIP: usize = 23;
+    pub const RIGHT_HIP: usize = 24;
+    pub const LEFT_KNEE: usize = 25;
+    pub const RIGHT_KNEE: usize = 26;
+    pub const LEFT_ANKLE: usize = 27;
+    pub const RIGHT_ANKLE: usize = 28;
+    pub const LEFT_HEEL: usize = 29;
+    pub const RIGHT_HEEL: usize = 30;
+    pub const LEFT_FOOT_INDEX: usize = 31;
+    pub const RIGHT_FOOT_INDEX: usize = 32;
+}
+
+/// Convert landmarks to keypoints, the same way the frontend does it in
+/// `landmarksToKeypoints`.
+fn keypoints_from_landmarks(landmarks: &[NormalizedLandmark]) -> Option<Keypoints> {
+    if landmarks.len() <= landmark::RIGHT_FOOT_INDEX {
+        return None;
+    }
+    let coordinate = |i: usize| Cartesian3d {
+        x: landmarks[i].x,
+        y: landmarks[i].y,
+        z: landmarks[i].z,
+    };
+    Some(Keypoints {
+        left: Side {
+            shoulder: coordinate(landmark::LEFT_SHOULDER),
+            hip: coordinate(landmark::LEFT_HIP),
+            knee: coordinate(landmark::LEFT_KNEE),
+            ankle: coordinate(landmark::LEFT_ANKLE),
+            heel: coordinate(landmark::LEFT_HEEL),
+            toes: coordinate(landmark::LEFT_FOOT_INDEX),
+            elbow: coordinate(landmark::LEFT_ELBOW),
+            wrist: coordinate(landmark::LEFT_WRIST),
+        },
+        right: Side {
+            shoulder: coordinate(landmark::RIGHT_SHOULDER),
+            hip: coordinate(landmark::RIGHT_HIP),
+            knee: coordinate(landmark::RIGHT_KNEE),
+            ankle: coordinate(landmark::RIGHT_ANKLE),
+            heel: coordinate(landmark::RIGHT_HEEL),
+            toes: coordinate(landmark::RIGHT_FOOT_INDEX),
+            elbow: coordinate(landmark::RIGHT_ELBOW),
+            wrist: coordinate(landmark::RIGHT_WRIST),
+        },
+    })
 }
 
 struct FFIResult {
@@ -0,0 +1,276 @@
+//! Draw tracked keypoints over the frames of a video, to see what the
+//! instructor sees.
+//!
+//! Optionally, the expected pose of a step is drawn next to one of the
+//! dancers, together with the error the instructor computes between the two.
+
+use crate::ffmpeg::{packed_pixels, unpack_pixels, VideoInput, VideoOutput};
+use crate::keypoints::{Cartesian3d, Keypoints};
+use bouncy_instructor::{Cartesian2d, DanceCursor, PoseApproximation, Tracker};
+use ffmpeg_next::{self as ffmpeg, software::scaling, util::frame};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+/// One color per person, repeating when there are more people.
+const TRACKED_COLORS: [RGBColor; 4] = [
+    RGBColor(0, 230, 118),
+    RGBColor(41, 182, 246),
+    RGBColor(255, 202, 40),
+    RGBColor(236, 64, 122),
+];
+const EXPECTED_COLOR: RGBColor = RGBColor(255, 255, 255);
+const GOOD_COLOR: RGBColor = RGBColor(0, 230, 118);
+const BAD_COLOR: RGBColor = RGBColor(255, 82, 82);
+
+/// Keypoints older than this are considered lost and not drawn.
+const MAX_KEYPOINTS_AGE_MS: i64 = 200;
+
+/// Pose errors below this are shown as good, same as the default error
+/// threshold of the instructor.
+const GOOD_POSE_ERROR: f32 = 0.075;
+
+/// Torso length of rendered instructor skeletons, relative to their size.
+const RENDERED_TORSO_LEN: f32 = 0.25;
+
+type Area<'a> = DrawingArea<BitMapBackend<'a>, Shift>;
+
+/// Which step to show as reference, and at which tempo.
+pub struct StepOverlay {
+    pub step_id: String,
+    pub bpm: f32,
+    /// Video time of the first beat in ms.
+    pub first_beat_ms: f64,
+}
+
+/// Encode a copy of the video with keypoints drawn on top.
+///
+/// `recordings` hold one recording per person and must use timestamps in ms
+/// of video time, as produced by `detect_pose`. The step is compared to the
+/// recording at index `person`.
+pub fn render_overlay(
+    mut video: VideoInput,
+    recordings: &[Vec<(u64, Keypoints)>],
+    output_path: &str,
+    step: Option<StepOverlay>,
+    person: usize,
+) -> anyhow::Result<()> {
+    let rotation = video.rotation();
+    let (width, height) = rotation.upright_size(video.decoder.width(), video.decoder.height());
+    let mut output = VideoOutput::by_path(
+        output_path,
+        width,
+        height,
+        video.time_base(),
+        video.frame_rate(),
+    )?;
+    let mut to_rgb = scaling::Context::get(
+        video.decoder.format(),
+        video.decoder.width(),
+        video.decoder.height(),
+        ffmpeg::format::Pixel::RGB24,
+        video.decoder.width(),
+        video.decoder.height(),
+        scaling::flag::Flags::BILINEAR,
+    )?;
+
+    let empty = vec![];
+    let compared = recordings.get(person).unwrap_or(&empty);
+    let mut tracker = match &step {
+        Some(step) => {
+            let mut tracker = Tracker::new_unique_step_tracker(step.step_id.clone())?;
+            tracker.set_bpm(step.bpm);
+            tracker.align_beat(step.first_beat_ms);
+            Some(tracker)
+        }
+        None => None,
+    };
+    // number of recorded keypoints already added to the tracker
+    let mut tracked = 0;
+    let mut last_tracked_t = None;
+
+    video.for_each_frame(|frame, timestamp_ms| {
+        let mut rgb_frame = frame::Video::empty();
+        to_rgb.run(frame, &mut rgb_frame)?;
+        // keypoints are in upright coordinates, so the output is upright, too
+        let mut pixels = rotation.apply(
+            &packed_pixels(&rgb_frame),
+            rgb_frame.width(),
+            rgb_frame.height(),
+        );
+
+        {
+            let area = BitMapBackend::with_buffer(&mut pixels, (width, height)).into_drawing_area();
+
+            if let Some(tracker) = &mut tracker {
+                let i = compared.partition_point(|(t, _)| *t as i64 <= timestamp_ms);
+                for (t, keypoints) in &compared[tracked..i] {
+                    // the tracker requires strictly increasing timestamps
+                    if last_tracked_t.is_some_and(|last| *t <= last) {
+                        continue;
+                    }
+                    tracker.add_keypoints((*keypoints).into(), *t as f64);
+                    last_tracked_t = Some(*t);
+                }
+                tracked = i;
+                if let Some((t, keypoints)) = current_keypoints(compared, timestamp_ms) {
+                    let cursor = tracker.cursor(timestamp_ms as f64, true);
+                    draw_expected_pose(&area, tracker, &cursor, keypoints, width, height)?;
+                    if let Some(error) = tracker.expected_pose_error(&cursor, *t as f64) {
+                        draw_pose_error(&area, &error)?;
+                    }
+                }
+            }
+            for (i, recording) in recordings.iter().enumerate() {
+                if let Some((_t, keypoints)) = current_keypoints(recording, timestamp_ms) {
+                    let color = TRACKED_COLORS[i % TRACKED_COLORS.len()];
+                    draw_keypoints(&area, keypoints, color, width, height)?;
+                }
+            }
+            let style = ("sans-serif", 24).into_font().color(&WHITE);
+            area.draw(&Text::new(
+                format!("{timestamp_ms} ms"),
+                (20, height as i32 - 40),
+                style,
+            ))?;
+            area.present()?;
+        }
+
+        let mut upright_frame = frame::Video::new(ffmpeg::format::Pixel::RGB24, width, height);
+        unpack_pixels(&pixels, &mut upright_frame);
+        output.write_frame(&upright_frame, frame.pts().unwrap_or(0))?;
+        Ok(())
+    })?;
+
+    output.finish()
+}
+
+/// Latest keypoints of a recording at the given video time, unless they are
+/// too old.
+fn current_keypoints(
+    recording: &[(u64, Keypoints)],
+    timestamp_ms: i64,
+) -> Option<&(u64, Keypoints)> {
+    let i = recording.partition_point(|(t, _)| *t as i64 <= timestamp_ms);
+    i.checked_sub(1)
+        .map(|i| &recording[i])
+        .filter(|(t, _)| timestamp_ms - (*t as i64) <= MAX_KEYPOINTS_AGE_MS)
+}
+
+fn draw_keypoints(
+    area: &Area,
+    keypoints: &Keypoints,
+    color: RGBColor,
+    width: u32,
+    height: u32,
+) -> anyhow::Result<()> {
+    let px = |c: Cartesian3d| ((c.x * width as f32) as i32, (c.y * height as f32) as i32);
+    let mut segments = vec![
+        (keypoints.left.shoulder, keypoints.right.shoulder),
+        (keypoints.left.hip, keypoints.right.hip),
+    ];
+    for side in [&keypoints.left, &keypoints.right] {
+        segments.extend([
+            (side.shoulder, side.elbow),
+            (side.elbow, side.wrist),
+            (side.shoulder, side.hip),
+            (side.hip, side.knee),
+            (side.knee, side.ankle),
+            (side.ankle, side.heel),
+            (side.heel, side.toes),
+            (side.toes, side.ankle),
+        ]);
+    }
+    for (start, end) in segments {
+        area.draw(&PathElement::new(
+            vec![px(start), px(end)],
+            color.stroke_width(4),
+        ))?;
+    }
+    for side in [&keypoints.left, &keypoints.right] {
+        for point in [
+            side.shoulder,
+            side.elbow,
+            side.wrist,
+            side.hip,
+            side.knee,
+            side.ankle,
+            side.heel,
+            side.toes,
+        ] {
+            area.draw(&Circle::new(px(point), 5, color.filled()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Draw the pose expected at the cursor, scaled to the torso of the tracked
+/// person and placed at their hip.
+fn draw_expected_pose(
+    area: &Area,
+    tracker: &Tracker,
+    cursor: &DanceCursor,
+    keypoints: &Keypoints,
+    width: u32,
+    height: u32,
+) -> anyhow::Result<()> {
+    let px = |c: Cartesian3d| Cartesian2d::new(c.x * width as f32, c.y * height as f32);
+    let mid =
+        |a: Cartesian2d, b: Cartesian2d| Cartesian2d::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+    let hip = mid(px(keypoints.left.hip), px(keypoints.right.hip));
+    let shoulder = mid(px(keypoints.left.shoulder), px(keypoints.right.shoulder));
+    let torso_len = ((hip.x - shoulder.x).powi(2) + (hip.y - shoulder.y).powi(2)).sqrt();
+    if torso_len == 0.0 {
+        return Ok(());
+    }
+
+    let skeleton = tracker
+        .pose_skeleton_at(cursor)
+        .render(hip, torso_len / RENDERED_TORSO_LEN);
+
+    let mut segments = vec![
+        (skeleton.hip.start, skeleton.hip.end),
+        (skeleton.shoulder.start, skeleton.shoulder.end),
+        (skeleton.hip.start, skeleton.shoulder.start),
+        (skeleton.hip.end, skeleton.shoulder.end),
+    ];
+    for side in [&skeleton.left, &skeleton.right] {
+        for segment in [side.thigh, side.shin, side.foot, side.arm, side.forearm] {
+            segments.push((segment.start, segment.end));
+        }
+    }
+    let style = EXPECTED_COLOR.mix(0.6).stroke_width(6);
+    for (start, end) in segments {
+        area.draw(&PathElement::new(
+            vec![
+                (start.x as i32, start.y as i32),
+                (end.x as i32, end.y as i32),
+            ],
+            style,
+        ))?;
+    }
+    Ok(())
+}
+
+fn draw_pose_error(area: &Area, error: &PoseApproximation) -> anyhow::Result<()> {
+    let color = if error.error < GOOD_POSE_ERROR {
+        GOOD_COLOR
+    } else {
+        BAD_COLOR
+    };
+    let style = ("sans-serif", 32).into_font().color(&color);
+    area.draw(&Text::new(
+        format!("{}: {:.3}", error.name(), error.error),
+        (20, 20),
+        style,
+    ))?;
+
+    let style = ("sans-serif", 24).into_font().color(&WHITE);
+    for (i, limb) in error.worst_limbs(3).iter().enumerate() {
+        area.draw(&Text::new(
+            format!("{}: {:.3}", limb.name(), limb.error),
+            (20, 64 + 30 * i as i32),
+            style.clone(),
+        ))?;
+    }
+    Ok(())
+}
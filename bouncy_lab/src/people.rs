@@ -0,0 +1,221 @@
+//! Keep track of who is who when several people dance in the same video.
+//!
+//! The pose model reports people per frame in no particular order. Here,
+//! detections are associated with the person who was closest in the previous
+//! frames, which works well as long as dancers don't swap places quickly.
+
+use crate::keypoints::Keypoints;
+
+/// A detection further away than this from a person's last position, in
+/// normalized frame coordinates, is considered someone else.
+const MAX_DISTANCE: f32 = 0.2;
+
+/// A person not seen for longer than this is considered gone. If they show
+/// up again later, they get a new identity.
+const MAX_GAP_MS: u64 = 1000;
+
+/// Persons with fewer frames than this are dropped as false detections.
+const MIN_FRAMES: usize = 10;
+
+/// Assigns detections to persons across frames.
+///
+/// With a single person, there is nothing to assign and every detection goes
+/// into the same recording, regardless of how far it moved.
+pub struct PersonTracker {
+    max_people: u32,
+    persons: Vec<Person>,
+}
+
+struct Person {
+    /// x, y of the body center in the last frame the person was seen
+    center: (f32, f32),
+    last_seen: u64,
+    recording: Vec<(u64, Keypoints)>,
+}
+
+impl PersonTracker {
+    pub fn new(max_people: u32) -> Self {
+        Self {
+            max_people,
+            persons: vec![],
+        }
+    }
+
+    /// Add all people detected in a frame, timestamps must be increasing.
+    pub fn add_frame(&mut self, timestamp: u64, detections: Vec<Keypoints>) {
+        if self.max_people <= 1 {
+            self.add_single(timestamp, detections);
+            return;
+        }
+        let active: Vec<usize> = (0..self.persons.len())
+            .filter(|&i| timestamp.saturating_sub(self.persons[i].last_seen) <= MAX_GAP_MS)
+            .collect();
+
+        // Greedily match the closest pairs first.
+        let mut pairs = vec![];
+        for (detection_index, keypoints) in detections.iter().enumerate() {
+            let center = center(keypoints);
+            for &person_index in &active {
+                let distance = distance(center, self.persons[person_index].center);
+                if distance <= MAX_DISTANCE {
+                    pairs.push((distance, person_index, detection_index));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut assigned_persons = vec![false; self.persons.len()];
+        let mut assigned_detections = vec![None; detections.len()];
+        for (_distance, person_index, detection_index) in pairs {
+            if !assigned_persons[person_index] && assigned_detections[detection_index].is_none() {
+                assigned_persons[person_index] = true;
+                assigned_detections[detection_index] = Some(person_index);
+            }
+        }
+
+        for (keypoints, person_index) in detections.into_iter().zip(assigned_detections) {
+            let person_index = person_index.unwrap_or_else(|| {
+                self.persons.push(Person {
+                    center: center(&keypoints),
+                    last_seen: timestamp,
+                    recording: vec![],
+                });
+                self.persons.len() - 1
+            });
+            let person = &mut self.persons[person_index];
+            person.center = center(&keypoints);
+            person.last_seen = timestamp;
+            person.recording.push((timestamp, keypoints));
+        }
+    }
+
+    fn add_single(&mut self, timestamp: u64, detections: Vec<Keypoints>) {
+        let Some(keypoints) = detections.into_iter().next() else {
+            return;
+        };
+        if self.persons.is_empty() {
+            self.persons.push(Person {
+                center: center(&keypoints),
+                last_seen: timestamp,
+                recording: vec![],
+            });
+        }
+        let person = &mut self.persons[0];
+        person.center = center(&keypoints);
+        person.last_seen = timestamp;
+        person.recording.push((timestamp, keypoints));
+    }
+
+    /// One recording per person, in order of first appearance.
+    ///
+    /// With a single person, this is always exactly one recording, even if
+    /// it is short or empty.
+    pub fn into_recordings(self) -> Vec<Vec<(u64, Keypoints)>> {
+        if self.max_people <= 1 {
+            let recording = self
+                .persons
+                .into_iter()
+                .next()
+                .map(|person| person.recording)
+                .unwrap_or_default();
+            return vec![recording];
+        }
+        self.persons
+            .into_iter()
+            .map(|person| person.recording)
+            .filter(|recording| recording.len() >= MIN_FRAMES)
+            .collect()
+    }
+}
+
+fn center(keypoints: &Keypoints) -> (f32, f32) {
+    let points = keypoints.points();
+    let n = points.len() as f32;
+    (
+        points.iter().map(|c| c.x).sum::<f32>() / n,
+        points.iter().map(|c| c.y).sum::<f32>() / n,
+    )
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypoints::Cartesian3d;
+
+    /// Keypoints with all body parts at the same spot.
+    fn person_at(x: f32, y: f32) -> Keypoints {
+        Keypoints::default().map(|_| Cartesian3d { x, y, z: 0.0 })
+    }
+
+    fn xs(recording: &[(u64, Keypoints)]) -> Vec<f32> {
+        recording.iter().map(|(_, kp)| kp.left.hip.x).collect()
+    }
+
+    #[test]
+    fn test_assign_closest_person() {
+        let mut tracker = PersonTracker::new(2);
+        for i in 0..MIN_FRAMES as u64 {
+            let t = i * 33;
+            // reported in a different order every frame
+            let mut detections = vec![person_at(0.2, 0.5), person_at(0.7 + 0.01 * i as f32, 0.5)];
+            if i % 2 == 1 {
+                detections.reverse();
+            }
+            tracker.add_frame(t, detections);
+        }
+        let recordings = tracker.into_recordings();
+        assert_eq!(recordings.len(), 2);
+        assert!(xs(&recordings[0]).iter().all(|x| *x == 0.2));
+        assert!(xs(&recordings[1]).iter().all(|x| *x >= 0.7));
+    }
+
+    #[test]
+    fn test_gap_expiry() {
+        let mut tracker = PersonTracker::new(2);
+        for i in 0..MIN_FRAMES as u64 {
+            tracker.add_frame(i * 33, vec![person_at(0.5, 0.5)]);
+        }
+        // back at the same spot, but after too long
+        let start = MIN_FRAMES as u64 * 33 + MAX_GAP_MS + 1;
+        for i in 0..MIN_FRAMES as u64 {
+            tracker.add_frame(start + i * 33, vec![person_at(0.5, 0.5)]);
+        }
+        let recordings = tracker.into_recordings();
+        assert_eq!(recordings.len(), 2);
+        assert_eq!(recordings[1][0].0, start);
+    }
+
+    #[test]
+    fn test_drop_short_detections() {
+        let mut tracker = PersonTracker::new(2);
+        for i in 0..MIN_FRAMES as u64 {
+            let mut detections = vec![person_at(0.2, 0.5)];
+            if i == 3 {
+                detections.push(person_at(0.8, 0.5));
+            }
+            tracker.add_frame(i * 33, detections);
+        }
+        assert_eq!(tracker.into_recordings().len(), 1);
+    }
+
+    #[test]
+    fn test_single_person() {
+        let mut tracker = PersonTracker::new(1);
+        // jumps across the frame and disappears for a while
+        tracker.add_frame(0, vec![person_at(0.1, 0.5)]);
+        tracker.add_frame(33, vec![person_at(0.9, 0.5)]);
+        tracker.add_frame(33 + 5 * MAX_GAP_MS, vec![person_at(0.5, 0.5)]);
+        let recordings = tracker.into_recordings();
+        assert_eq!(recordings.len(), 1);
+        assert_eq!(xs(&recordings[0]), [0.1, 0.9, 0.5]);
+
+        // no output is still one recording
+        let recordings = PersonTracker::new(1).into_recordings();
+        assert_eq!(recordings.len(), 1);
+        assert!(recordings[0].is_empty());
+    }
+}
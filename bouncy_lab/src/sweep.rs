@@ -0,0 +1,96 @@
+//! Search detector thresholds that detect the steps of annotated videos best.
+//!
+//! Only thresholds of step detection on recordings are swept, the same
+//! detection `evaluate` measures. Thresholds of live tracking can't be
+//! replayed on annotated videos. Instead, per-pose error thresholds are
+//! derived from the errors of correctly detected steps, which can only loosen
+//! the general `error_threshold`. All other fields of the written config,
+//! including `error_threshold`, `left_right_ratio` and `positioning_threshold`,
+//! are those of the base config.
+
+use crate::evaluation::{load_annotations, Counts};
+use crate::mediapipe::DetectionOptions;
+use bouncy_instructor::DetectorConfig;
+use std::collections::BTreeMap;
+
+const FIRST_POSE_THRESHOLDS: [f32; 5] = [0.1, 0.15, 0.2, 0.25, 0.3];
+const MIN_POSE_INTERVALS: [f32; 3] = [0.25, 0.5, 0.75];
+const MAX_POSE_INTERVALS: [f32; 4] = [1.0, 1.25, 1.5, 2.0];
+
+/// Poses need this many correctly detected samples to get their own threshold.
+const MIN_POSE_SAMPLES: usize = 5;
+/// Share of correctly detected samples a pose threshold should accept.
+const POSE_ERROR_QUANTILE: f32 = 0.9;
+/// Pose thresholds are never looser than this, a pose must still look like
+/// the pose.
+const MAX_POSE_ERROR_THRESHOLD: f32 = 0.15;
+
+/// Try all parameter combinations on the annotated videos, print the best
+/// ones and write the best config to `output_path`.
+pub fn sweep(
+    annotation_paths: &[&str],
+    output_path: &str,
+    tolerance_beats: f64,
+    base_config: &DetectorConfig,
+    options: DetectionOptions,
+) -> anyhow::Result<DetectorConfig> {
+    let recordings = load_annotations(annotation_paths, options)?;
+
+    let mut results = vec![];
+    for first_pose_threshold in FIRST_POSE_THRESHOLDS {
+        for min_pose_interval in MIN_POSE_INTERVALS {
+            for max_pose_interval in MAX_POSE_INTERVALS {
+                let mut config = base_config.clone();
+                config.first_pose_threshold = first_pose_threshold;
+                config.min_pose_interval = min_pose_interval;
+                config.max_pose_interval = max_pose_interval;
+                let counts = Counts::sum(
+                    recordings
+                        .iter()
+                        .map(|recording| recording.evaluate(&config, tolerance_beats).counts()),
+                );
+                results.push((config, counts));
+            }
+        }
+    }
+    // stable sort keeps the order of the grid for ties
+    results.sort_by(|a, b| b.1.f1.total_cmp(&a.1.f1));
+    for (config, counts) in results.iter().take(10) {
+        println!(
+            "f1 {:.3} (precision {:.2}, recall {:.2}): first pose threshold {}, pose interval {}..{} beats",
+            counts.f1,
+            counts.precision,
+            counts.recall,
+            config.first_pose_threshold,
+            config.min_pose_interval,
+            config.max_pose_interval,
+        );
+    }
+    let Some((mut best, _)) = results.into_iter().next() else {
+        anyhow::bail!("nothing to sweep")
+    };
+
+    let mut pose_errors = BTreeMap::<String, Vec<f32>>::new();
+    for recording in &recordings {
+        for (pose_id, error) in recording.evaluate(&best, tolerance_beats).pose_errors {
+            pose_errors.entry(pose_id).or_default().push(error);
+        }
+    }
+    best.clear_pose_error_thresholds();
+    for (pose_id, mut errors) in pose_errors {
+        if errors.len() < MIN_POSE_SAMPLES {
+            continue;
+        }
+        errors.sort_by(f32::total_cmp);
+        let index = ((errors.len() - 1) as f32 * POSE_ERROR_QUANTILE).round() as usize;
+        let threshold = errors[index].min(MAX_POSE_ERROR_THRESHOLD);
+        // only loosen, a strict threshold can't be justified by correct samples
+        if threshold > best.error_threshold {
+            println!("pose {pose_id}: error threshold {threshold:.3}");
+            best.set_pose_error_threshold(pose_id, threshold);
+        }
+    }
+
+    std::fs::write(output_path, best.to_ron()?)?;
+    Ok(best)
+}